@@ -0,0 +1,128 @@
+// Read-call throughput for `MyTokenClient`.
+//
+// The network benches run against a local Anvil node with a deployed token and
+// are skipped when no node answers. Point them at a deployment with
+// `MY_TOKEN_ADDRESS`, and at a Multicall3 deployment with `MULTICALL_ADDRESS`
+// (without one `balances` falls back to unbatched reads).
+//
+// The decode bench uses a canned-response provider, so it only measures
+// request encoding and response decoding.
+
+use async_trait::async_trait;
+use criterion::{Criterion, criterion_group, criterion_main};
+use ethers::{
+    abi::{self, Token},
+    providers::{Http, JsonRpcClient, Middleware, MockError, Provider},
+    types::{Address, Bytes, U256},
+};
+use my_token::MyTokenClient;
+use serde::{Serialize, de::DeserializeOwned};
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+const RPC_URL: &str = "http://localhost:8545";
+const DEFAULT_TOKEN_ADDRESS: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+const BATCH_SIZE: usize = 50;
+const SPEEDUP_ROUNDS: u32 = 10;
+
+// Answers every request with the same JSON value
+#[derive(Debug, Clone)]
+struct FixedResponse(serde_json::Value);
+
+#[async_trait]
+impl JsonRpcClient for FixedResponse {
+    type Error = MockError;
+
+    async fn request<T, R>(&self, _method: &str, _params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        Ok(serde_json::from_value(self.0.clone())?)
+    }
+}
+
+async fn connect() -> Option<MyTokenClient<Provider<Http>>> {
+    let provider = Provider::<Http>::try_from(RPC_URL).ok()?;
+    let address = std::env::var("MY_TOKEN_ADDRESS")
+        .unwrap_or_else(|_| DEFAULT_TOKEN_ADDRESS.to_string())
+        .parse::<Address>()
+        .ok()?;
+
+    let probe = tokio::time::timeout(Duration::from_secs(2), provider.get_code(address, None));
+    match probe.await {
+        Ok(Ok(code)) if !code.is_empty() => {}
+        _ => return None,
+    }
+
+    let token = MyTokenClient::new(address, Arc::new(provider));
+    Some(match std::env::var("MULTICALL_ADDRESS") {
+        Ok(multicall) => token.with_multicall(multicall.parse().ok()?),
+        Err(_) => token,
+    })
+}
+
+fn network_reads(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let Some(token) = rt.block_on(connect()) else {
+        eprintln!("Skipping network benches: no token deployment reachable at {RPC_URL}");
+        return;
+    };
+
+    let account = Address::random();
+    let accounts: Vec<Address> = (0..BATCH_SIZE).map(|_| Address::random()).collect();
+
+    c.bench_function("balance_of", |b| {
+        b.to_async(&rt)
+            .iter(|| async { token.balance_of(account).await.unwrap() })
+    });
+
+    let mut group = c.benchmark_group(format!("balances_{BATCH_SIZE}"));
+    group.bench_function("batched", |b| {
+        b.to_async(&rt)
+            .iter(|| async { token.balances(&accounts).await.unwrap() })
+    });
+    group.bench_function("unbatched", |b| {
+        b.to_async(&rt)
+            .iter(|| async { token.balances_unbatched(&accounts).await.unwrap() })
+    });
+    group.finish();
+
+    let batched = rt.block_on(time_rounds(|| token.balances(&accounts)));
+    let unbatched = rt.block_on(time_rounds(|| token.balances_unbatched(&accounts)));
+    println!(
+        "balances_{BATCH_SIZE}: batched {batched:?}, unbatched {unbatched:?}, speedup {:.1}x",
+        unbatched.as_secs_f64() / batched.as_secs_f64()
+    );
+}
+
+// Average wall time of `SPEEDUP_ROUNDS` runs of `read`
+async fn time_rounds<F, Fut>(read: F) -> Duration
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = my_token::Result<Vec<U256>>>,
+{
+    let start = Instant::now();
+    for _ in 0..SPEEDUP_ROUNDS {
+        read().await.unwrap();
+    }
+    start.elapsed() / SPEEDUP_ROUNDS
+}
+
+fn decode(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let encoded = Bytes::from(abi::encode(&[Token::Uint(U256::from(1_000_000u64))]));
+    let provider = Provider::new(FixedResponse(serde_json::to_value(encoded).unwrap()));
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+    let account = Address::random();
+
+    c.bench_function("balance_of_decode", |b| {
+        b.to_async(&rt)
+            .iter(|| async { token.balance_of(account).await.unwrap() })
+    });
+}
+
+criterion_group!(benches, decode, network_reads);
+criterion_main!(benches);
@@ -8,11 +8,14 @@ contract DeployScript is Script {
         // Retrieve the private key from environment variable
         uint256 deployerPrivateKey = vm.envUint("PRIVATE_KEY");
 
+        // Token decimals, 18 unless DECIMALS is set
+        uint8 tokenDecimals = uint8(vm.envOr("DECIMALS", uint256(18)));
+
         // Start broadcasting transactions
         vm.startBroadcast(deployerPrivateKey);
 
         // Deploy the contract
-        MyToken token = new MyToken(vm.addr(deployerPrivateKey));
+        MyToken token = new MyToken(vm.addr(deployerPrivateKey), tokenDecimals);
 
         // Stop broadcasting transactions
         vm.stopBroadcast();
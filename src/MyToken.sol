@@ -1,18 +1,386 @@
-
 pragma solidity ^0.8.20;
 
 import "@openzeppelin/contracts/token/ERC20/ERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/ERC20Capped.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/ERC20Permit.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/ERC20FlashMint.sol";
 import "@openzeppelin/contracts/access/Ownable.sol";
+import "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
+import "@openzeppelin/contracts/utils/introspection/ERC165Checker.sol";
+import "@openzeppelin/contracts/interfaces/IERC1363Receiver.sol";
+
+// Implemented by contracts that want to hear about tokens minted to them with
+// `safeMint`, advertised through ERC-165
+interface IMintReceiver {
+    function tokensReceived(address operator, uint256 amount) external;
+}
+
+contract MyToken is ERC20Capped, ERC20Permit, ERC20FlashMint, Ownable, ReentrancyGuard {
+    mapping(address => bool) private _frozen;
+    mapping(address => mapping(address => uint256)) private _allowanceExpiry;
+    uint8 private immutable _decimals;
+    string private _tokenName;
+    string private _tokenSymbol;
+    uint16 private _transferFeeBps;
+    // Zero until a cap change is executed, leaving the constructor's cap
+    uint256 private _changedCap;
+    uint256 private _pendingCap;
+    uint256 private _capChangeTime;
+    uint256 private _capChangeDelay = DEFAULT_CAP_CHANGE_DELAY;
+    // A new delay waits out the current one too, so it can't be lowered to
+    // skip the notice a cap change gives
+    uint256 private _pendingCapChangeDelay;
+    uint256 private _capChangeDelayTime;
+
+    // Transfer fees can't be set above 10%
+    uint16 public constant MAX_TRANSFER_FEE_BPS = 1000;
+
+    // How long a proposed cap change waits before it can be executed, until
+    // the owner sets another delay
+    uint256 public constant DEFAULT_CAP_CHANGE_DELAY = 2 days;
 
-contract MyToken is ERC20, Ownable {
-    constructor(address initialOwner)
+    event Frozen(address indexed account);
+    event Unfrozen(address indexed account);
+    event MetadataUpdated(string name, string symbol);
+    event BatchTransferSkipped(address indexed to, uint256 amount);
+    event TransferFeeUpdated(uint16 bps);
+    event EthReceived(address indexed from, uint256 amount);
+    event EthSwept(address indexed to, uint256 amount);
+    event CapChangeProposed(uint256 newCap, uint256 executableAt);
+    event CapChanged(uint256 oldCap, uint256 newCap);
+    event CapChangeDelayUpdated(uint256 delay, uint256 effectiveAt);
+
+    error AccountFrozen(address account);
+    error AllowanceExpired(address owner, address spender, uint256 expiry);
+    error InvalidTransferReceiver(address receiver);
+    error BatchLengthMismatch(uint256 recipients, uint256 amounts);
+    error InsufficientBalance(uint256 available, uint256 needed);
+    error TransferFeeTooHigh(uint16 bps);
+    error EthTransferFailed(address to);
+    error CapBelowSupply(uint256 cap, uint256 supply);
+    error CapChangeNotReady(uint256 executableAt);
+    error NoPendingCapChange();
+
+    // The cap and initial supply are whole tokens, scaled by `tokenDecimals`
+    constructor(address initialOwner, uint8 tokenDecimals)
         ERC20("MyToken", "MTK")
+        ERC20Capped(10000000 * 10 ** tokenDecimals)
+        ERC20Permit("MyToken")
         Ownable(initialOwner)
     {
-        _mint(msg.sender, 1000000 * 10 ** decimals());
+        _decimals = tokenDecimals;
+        _tokenName = "MyToken";
+        _tokenSymbol = "MTK";
+        _mint(msg.sender, 1000000 * 10 ** tokenDecimals);
+    }
+
+    function decimals() public view override returns (uint8) {
+        return _decimals;
+    }
+
+    function name() public view override returns (string memory) {
+        return _tokenName;
+    }
+
+    function symbol() public view override returns (string memory) {
+        return _tokenSymbol;
+    }
+
+    // The owner can rename the token. The EIP-712 domain used by permits keeps
+    // the name given at deployment, so signatures made before stay valid.
+    function setName(string calldata newName) public onlyOwner {
+        _tokenName = newName;
+        emit MetadataUpdated(newName, _tokenSymbol);
+    }
+
+    function setSymbol(string calldata newSymbol) public onlyOwner {
+        _tokenSymbol = newSymbol;
+        emit MetadataUpdated(_tokenName, newSymbol);
     }
 
     function mint(address to, uint256 amount) public onlyOwner {
         _mint(to, amount);
     }
+
+    // Mint, then call `tokensReceived` if `to` is a contract advertising
+    // IMintReceiver. The hook runs after the balance is credited and can't mint
+    // again while it runs.
+    function safeMint(address to, uint256 amount) public onlyOwner nonReentrant {
+        _mint(to, amount);
+        if (ERC165Checker.supportsInterface(to, type(IMintReceiver).interfaceId)) {
+            IMintReceiver(to).tokensReceived(msg.sender, amount);
+        }
+    }
+
+    function burn(uint256 amount) public {
+        _burn(msg.sender, amount);
+    }
+
+    // Burn `account`'s tokens on its behalf, spending the caller's allowance
+    function burnFrom(address account, uint256 amount) public {
+        _spendAllowance(account, msg.sender, amount);
+        _burn(account, amount);
+    }
+
+    function cap() public view override returns (uint256) {
+        return _changedCap == 0 ? super.cap() : _changedCap;
+    }
+
+    // Schedule a new cap, replacing any pending proposal. It can be executed
+    // once the cap change delay has passed, giving holders notice of it.
+    function proposeCapChange(uint256 newCap) public onlyOwner {
+        if (newCap < totalSupply()) {
+            revert CapBelowSupply(newCap, totalSupply());
+        }
+        if (newCap == 0) {
+            revert ERC20InvalidCap(0);
+        }
+        _pendingCap = newCap;
+        _capChangeTime = block.timestamp + capChangeDelay();
+        emit CapChangeProposed(newCap, _capChangeTime);
+    }
+
+    // Apply the pending cap. Checked against the supply again, as minting may
+    // have continued since it was proposed.
+    function executeCapChange() public onlyOwner {
+        if (_capChangeTime == 0) {
+            revert NoPendingCapChange();
+        }
+        if (block.timestamp < _capChangeTime) {
+            revert CapChangeNotReady(_capChangeTime);
+        }
+        uint256 newCap = _pendingCap;
+        if (newCap < totalSupply()) {
+            revert CapBelowSupply(newCap, totalSupply());
+        }
+        emit CapChanged(cap(), newCap);
+        _changedCap = newCap;
+        delete _pendingCap;
+        delete _capChangeTime;
+    }
+
+    // The proposed cap, or 0 when nothing is pending
+    function pendingCap() public view returns (uint256) {
+        return _pendingCap;
+    }
+
+    // When the pending cap can be executed, or 0 when nothing is pending
+    function capChangeTime() public view returns (uint256) {
+        return _capChangeTime;
+    }
+
+    function capChangeDelay() public view returns (uint256) {
+        if (_capChangeDelayTime != 0 && block.timestamp >= _capChangeDelayTime) {
+            return _pendingCapChangeDelay;
+        }
+        return _capChangeDelay;
+    }
+
+    // Schedule a new delay, taking effect once the current delay has passed so
+    // lowering it gives as much notice as a cap change would. It applies to
+    // proposals made from then on; one already pending keeps its execution
+    // time. Replaces any new delay still waiting.
+    function setCapChangeDelay(uint256 delay) public onlyOwner {
+        uint256 current = capChangeDelay();
+        _capChangeDelay = current;
+        _pendingCapChangeDelay = delay;
+        _capChangeDelayTime = block.timestamp + current;
+        emit CapChangeDelayUpdated(delay, _capChangeDelayTime);
+    }
+
+    // The delay set to take effect, or 0 when no change is waiting
+    function pendingCapChangeDelay() public view returns (uint256) {
+        return _capChangeDelayTime > block.timestamp ? _pendingCapChangeDelay : 0;
+    }
+
+    // When the pending delay takes effect, or 0 when no change is waiting
+    function capChangeDelayTime() public view returns (uint256) {
+        return _capChangeDelayTime > block.timestamp ? _capChangeDelayTime : 0;
+    }
+
+    // How much can still be minted before hitting the cap
+    function remainingMintable() public view returns (uint256) {
+        uint256 supply = totalSupply();
+        return supply >= cap() ? 0 : cap() - supply;
+    }
+
+    // Frozen accounts can still receive tokens, but not send them
+    function freeze(address account) public onlyOwner {
+        _frozen[account] = true;
+        emit Frozen(account);
+    }
+
+    function unfreeze(address account) public onlyOwner {
+        _frozen[account] = false;
+        emit Unfrozen(account);
+    }
+
+    function isFrozen(address account) public view returns (bool) {
+        return _frozen[account];
+    }
+
+    // ETH sent here by mistake is held until the owner sweeps it out. There's
+    // no fallback, so calls to functions the token doesn't have still revert.
+    receive() external payable {
+        emit EthReceived(msg.sender, msg.value);
+    }
+
+    // Send all the ETH the contract holds to `to`
+    function sweepETH(address payable to) public onlyOwner nonReentrant {
+        uint256 amount = address(this).balance;
+        (bool sent,) = to.call{value: amount}("");
+        if (!sent) {
+            revert EthTransferFailed(to);
+        }
+        emit EthSwept(to, amount);
+    }
+
+    // Take `bps` basis points of every transfer for the owner. Transfers to or
+    // from the owner, mints and burns pay nothing.
+    function setTransferFee(uint16 bps) public onlyOwner {
+        if (bps > MAX_TRANSFER_FEE_BPS) {
+            revert TransferFeeTooHigh(bps);
+        }
+        _transferFeeBps = bps;
+        emit TransferFeeUpdated(bps);
+    }
+
+    function transferFeeBps() public view returns (uint16) {
+        return _transferFeeBps;
+    }
+
+    // What a transfer of `value` from `from` to `to` pays in fees, so the
+    // recipient gets `value` less this. Transfers to oneself move nothing, so
+    // they're free and leave the balance as it was.
+    function transferFee(address from, address to, uint256 value) public view returns (uint256) {
+        if (from == address(0) || to == address(0) || from == to || from == owner() || to == owner()) {
+            return 0;
+        }
+        return value * _transferFeeBps / 10000;
+    }
+
+    // ERC-1363 style transfer that lets a contract recipient act on the tokens
+    // in the same transaction, with no approve step. The recipient must answer
+    // `onTransferReceived` with its selector; its revert reason is passed on.
+    function transferAndCall(address to, uint256 value, bytes calldata data) public returns (bool) {
+        if (to.code.length == 0) {
+            revert InvalidTransferReceiver(to);
+        }
+        _transfer(msg.sender, to, value);
+        try IERC1363Receiver(to).onTransferReceived(msg.sender, msg.sender, value, data) returns (bytes4 selector) {
+            if (selector != IERC1363Receiver.onTransferReceived.selector) {
+                revert InvalidTransferReceiver(to);
+            }
+        } catch (bytes memory reason) {
+            if (reason.length == 0) {
+                revert InvalidTransferReceiver(to);
+            }
+            assembly {
+                revert(add(reason, 32), mload(reason))
+            }
+        }
+        return true;
+    }
+
+    // Pay several recipients in one transaction. When `atomic`, any transfer
+    // that fails reverts the whole batch. Otherwise recipients that can't be
+    // paid, being the zero address or owed more than the balance left, are
+    // skipped with a BatchTransferSkipped event. Returns how many were paid.
+    function batchTransfer(address[] calldata to, uint256[] calldata amounts, bool atomic)
+        public
+        returns (uint256 paid)
+    {
+        if (to.length != amounts.length) {
+            revert BatchLengthMismatch(to.length, amounts.length);
+        }
+        for (uint256 i = 0; i < to.length; i++) {
+            if (!atomic && (to[i] == address(0) || balanceOf(msg.sender) < amounts[i])) {
+                emit BatchTransferSkipped(to[i], amounts[i]);
+                continue;
+            }
+            _transfer(msg.sender, to[i], amounts[i]);
+            paid++;
+        }
+    }
+
+    // Flash loans are minted, so they can't take the supply past the cap
+    function maxFlashLoan(address token) public view override returns (uint256) {
+        return token == address(this) ? remainingMintable() : 0;
+    }
+
+    // An allowance `spender` can only use until `deadline`, a unix timestamp
+    function approveWithExpiry(address spender, uint256 amount, uint256 deadline) public returns (bool) {
+        _approve(msg.sender, spender, amount);
+        _allowanceExpiry[msg.sender][spender] = deadline;
+        return true;
+    }
+
+    // When the allowance stops being usable, or 0 if it never expires
+    function allowanceExpiry(address tokenOwner, address spender) public view returns (uint256) {
+        return _allowanceExpiry[tokenOwner][spender];
+    }
+
+    // Moving your own tokens needs no allowance, so it works like `transfer`
+    // and leaves any allowance you gave yourself untouched
+    function transferFrom(address from, address to, uint256 value) public override returns (bool) {
+        if (from == msg.sender) {
+            _transfer(from, to, value);
+            return true;
+        }
+        return super.transferFrom(from, to, value);
+    }
+
+    // Plain approvals and permits replace any expiring allowance with one that
+    // doesn't expire
+    function approve(address spender, uint256 value) public override returns (bool) {
+        delete _allowanceExpiry[msg.sender][spender];
+        return super.approve(spender, value);
+    }
+
+    function permit(
+        address tokenOwner,
+        address spender,
+        uint256 value,
+        uint256 deadline,
+        uint8 v,
+        bytes32 r,
+        bytes32 s
+    ) public override {
+        delete _allowanceExpiry[tokenOwner][spender];
+        super.permit(tokenOwner, spender, value, deadline, v, r, s);
+    }
+
+    function _spendAllowance(address tokenOwner, address spender, uint256 value) internal override {
+        uint256 expiry = _allowanceExpiry[tokenOwner][spender];
+        if (expiry != 0 && block.timestamp > expiry) {
+            revert AllowanceExpired(tokenOwner, spender, expiry);
+        }
+        super._spendAllowance(tokenOwner, spender, value);
+    }
+
+    function _update(address from, address to, uint256 value) internal override(ERC20, ERC20Capped) {
+        if (_frozen[from]) {
+            revert AccountFrozen(from);
+        }
+        // Checked here rather than left to OpenZeppelin so the error carries
+        // just the two amounts, which clients decode
+        if (from != address(0)) {
+            uint256 available = balanceOf(from);
+            if (available < value) {
+                revert InsufficientBalance(available, value);
+            }
+        }
+        uint256 fee = transferFee(from, to, value);
+        if (fee != 0) {
+            super._update(from, owner(), fee);
+        }
+        super._update(from, to, value - fee);
+    }
+
+    // OpenZeppelin v5 stopped emitting Approval when transferFrom spends an
+    // allowance; emit it again so indexers can follow the remaining allowance.
+    // `tokenOwner` rather than `owner`, which would shadow `Ownable.owner()`
+    function _approve(address tokenOwner, address spender, uint256 value, bool) internal override {
+        super._approve(tokenOwner, spender, value, true);
+    }
 }
@@ -0,0 +1,10 @@
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/proxy/ERC1967/ERC1967Proxy.sol";
+
+// EIP-1967 proxy in front of `MyTokenUpgradeable`. Upgrades are handled by the
+// implementation (UUPS), so this holds no logic of its own; `data` is the
+// `initialize` call to run against the first implementation.
+contract MyTokenProxy is ERC1967Proxy {
+    constructor(address implementation, bytes memory data) ERC1967Proxy(implementation, data) {}
+}
@@ -0,0 +1,47 @@
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts-upgradeable/token/ERC20/ERC20Upgradeable.sol";
+import "@openzeppelin/contracts-upgradeable/access/OwnableUpgradeable.sol";
+import "@openzeppelin/contracts-upgradeable/proxy/utils/UUPSUpgradeable.sol";
+
+// The token logic for deployments behind `MyTokenProxy`. State lives in the
+// proxy, so this is set up by `initialize` instead of a constructor, and the
+// owner can swap in a new implementation with `upgradeTo`.
+//
+// This is a plain ERC-20 with owner minting and burning, not an upgradeable
+// `MyToken`: it has 18 decimals and no cap, permits, flash loans, transfer
+// fee, freezing, expiring allowances or batch transfers, so the client calls
+// for those revert against it. Features meant to be upgradeable are added
+// here, in new implementations keeping this storage layout.
+//
+// Built against OpenZeppelin's contracts-upgradeable, installed next to the
+// contracts with `forge install OpenZeppelin/openzeppelin-contracts-upgradeable`
+// and remapped in foundry.toml.
+contract MyTokenUpgradeable is ERC20Upgradeable, OwnableUpgradeable, UUPSUpgradeable {
+    // The implementation itself is never used directly
+    constructor() {
+        _disableInitializers();
+    }
+
+    function initialize(address initialOwner) public initializer {
+        __ERC20_init("MyToken", "MTK");
+        __Ownable_init(initialOwner);
+        __UUPSUpgradeable_init();
+        _mint(msg.sender, 1000000 * 10 ** decimals());
+    }
+
+    function mint(address to, uint256 amount) public onlyOwner {
+        _mint(to, amount);
+    }
+
+    function burn(uint256 amount) public {
+        _burn(msg.sender, amount);
+    }
+
+    // OpenZeppelin v5 only has upgradeToAndCall; most upgrades need no call
+    function upgradeTo(address newImplementation) public {
+        upgradeToAndCall(newImplementation, "");
+    }
+
+    function _authorizeUpgrade(address) internal override onlyOwner {}
+}
@@ -0,0 +1,10 @@
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/token/ERC20/ERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/ERC4626.sol";
+
+// Tokenized vault issuing vMTK shares for deposited MyToken. Shares start out
+// 1:1 with assets and track the vault's balance from there.
+contract MyVault is ERC4626 {
+    constructor(IERC20 asset) ERC20("MyToken Vault", "vMTK") ERC4626(asset) {}
+}
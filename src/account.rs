@@ -0,0 +1,81 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+
+use crate::{MyTokenClient, MyTokenError, Result};
+
+/// The account a client sends from, and what it holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountSummary {
+    pub address: Address,
+    /// In wei.
+    pub eth_balance: U256,
+    /// In raw token units.
+    pub token_balance: U256,
+}
+
+/// A holder's balance, and whether the holder is a contract rather than an
+/// externally owned account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HolderBalance {
+    /// In raw token units.
+    pub balance: U256,
+    pub is_contract: bool,
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// The signer's address with its ETH and token balances, read at the same
+    /// block. Fails with [`MyTokenError::NoSigner`] on a read-only client.
+    pub async fn whoami(&self) -> Result<AccountSummary> {
+        let address = self.sender()?;
+        let client = self.client();
+        let block = client
+            .get_block_number()
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        let eth_balance = client
+            .get_balance(address, Some(block.into()))
+            .await
+            .map_err(MyTokenError::middleware)?;
+        let token_balance = self
+            .contract()
+            .balance_of(address)
+            .block(block)
+            .call()
+            .await?;
+        Ok(AccountSummary {
+            address,
+            eth_balance,
+            token_balance,
+        })
+    }
+
+    /// [`balance_of`](Self::balance_of) along with whether `account` has code,
+    /// read at the same block, so contract holders such as pools can be told
+    /// apart. An account whose contract hasn't been deployed yet, or has
+    /// self-destructed, counts as not a contract.
+    pub async fn balance_of_detailed(&self, account: Address) -> Result<HolderBalance> {
+        let client = self.client();
+        let block = client
+            .get_block_number()
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        let code = client
+            .get_code(account, Some(block.into()))
+            .await
+            .map_err(MyTokenError::middleware)?;
+        let balance = self
+            .contract()
+            .balance_of(account)
+            .block(block)
+            .call()
+            .await?;
+        Ok(HolderBalance {
+            balance,
+            is_contract: !code.is_empty(),
+        })
+    }
+}
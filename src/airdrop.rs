@@ -0,0 +1,256 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use ethers::{
+    abi::AbiEncode,
+    types::{Address, Eip1559TransactionRequest, TxHash, U256},
+};
+
+use crate::{MyTokenError, Result, TransferCall, TxSender, TxStatus, TxSummary};
+
+/// Transfers an airdrop has sent, kept in a file so a re-run after a failure
+/// or a crash neither skips nor repeats any.
+///
+/// Entries are keyed by the recipient's position in the list, its address and
+/// the amount, so an address listed more than once is paid each time. Each
+/// line is the entry's state, `sent`, `mined` or `reverted`, then the key and
+/// the transaction hash. A transfer is logged as `sent` and synced before its
+/// receipt is awaited, so a crash in between leaves its hash behind to check
+/// rather than a recipient to pay again. The last line for an entry wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AirdropLog {
+    path: PathBuf,
+    entries: HashMap<(usize, Address, U256), (TxState, TxHash)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TxState {
+    Sent,
+    Mined,
+    Reverted,
+}
+
+impl TxState {
+    fn as_str(self) -> &'static str {
+        match self {
+            TxState::Sent => "sent",
+            TxState::Mined => "mined",
+            TxState::Reverted => "reverted",
+        }
+    }
+}
+
+impl AirdropLog {
+    /// The log stored at `path`, or an empty one if the file doesn't exist.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let storage = |err: &dyn std::fmt::Display| {
+            MyTokenError::Storage(format!("{}: {err}", path.display()))
+        };
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(storage(&err)),
+        };
+
+        let mut entries = HashMap::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let malformed = || storage(&format!("malformed line {line:?}"));
+            let [state, index, recipient, amount, tx_hash] = fields[..] else {
+                return Err(malformed());
+            };
+            let state = match state {
+                "sent" => TxState::Sent,
+                "mined" => TxState::Mined,
+                "reverted" => TxState::Reverted,
+                _ => return Err(malformed()),
+            };
+            let index = index.parse().map_err(|err| storage(&err))?;
+            let recipient = recipient.parse().map_err(|err| storage(&err))?;
+            let amount = U256::from_dec_str(amount).map_err(|err| storage(&err))?;
+            let tx_hash = tx_hash.parse().map_err(|err| storage(&err))?;
+            entries.insert((index, recipient, amount), (state, tx_hash));
+        }
+        Ok(Self { path, entries })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The mined transfer that paid `amount` to `recipient`, listed at
+    /// `index`, if one did.
+    pub fn credited(&self, index: usize, recipient: Address, amount: U256) -> Option<TxHash> {
+        self.entry(index, recipient, amount, TxState::Mined)
+    }
+
+    /// The transfer sent for the entry but not yet seen mined, if there is
+    /// one, which may still credit it.
+    pub fn pending(&self, index: usize, recipient: Address, amount: U256) -> Option<TxHash> {
+        self.entry(index, recipient, amount, TxState::Sent)
+    }
+
+    fn entry(
+        &self,
+        index: usize,
+        recipient: Address,
+        amount: U256,
+        state: TxState,
+    ) -> Option<TxHash> {
+        match self.entries.get(&(index, recipient, amount)) {
+            Some(&(found, tx_hash)) if found == state => Some(tx_hash),
+            _ => None,
+        }
+    }
+
+    /// Record the entry's transfer as broadcast by `tx_hash`, before waiting
+    /// for it.
+    pub fn record_sent(
+        &mut self,
+        index: usize,
+        recipient: Address,
+        amount: U256,
+        tx_hash: TxHash,
+    ) -> Result<()> {
+        self.append(TxState::Sent, index, recipient, amount, tx_hash)
+    }
+
+    /// Record the entry as credited by `tx_hash`.
+    pub fn record(
+        &mut self,
+        index: usize,
+        recipient: Address,
+        amount: U256,
+        tx_hash: TxHash,
+    ) -> Result<()> {
+        self.append(TxState::Mined, index, recipient, amount, tx_hash)
+    }
+
+    /// Record the entry's transfer `tx_hash` as reverted or dropped, leaving
+    /// it to be sent again.
+    pub fn record_reverted(
+        &mut self,
+        index: usize,
+        recipient: Address,
+        amount: U256,
+        tx_hash: TxHash,
+    ) -> Result<()> {
+        self.append(TxState::Reverted, index, recipient, amount, tx_hash)
+    }
+
+    fn append(
+        &mut self,
+        state: TxState,
+        index: usize,
+        recipient: Address,
+        amount: U256,
+        tx_hash: TxHash,
+    ) -> Result<()> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                writeln!(
+                    file,
+                    "{} {index} {recipient:?} {amount} {tx_hash:?}",
+                    state.as_str()
+                )?;
+                file.sync_data()
+            })
+            .map_err(|err| MyTokenError::Storage(format!("{}: {err}", self.path.display())))?;
+        self.entries
+            .insert((index, recipient, amount), (state, tx_hash));
+        Ok(())
+    }
+}
+
+/// How far an [`airdrop`] has got, passed to its callback after each
+/// recipient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AirdropProgress<'a> {
+    /// Recipients handled so far, this one included.
+    pub done: usize,
+    pub total: usize,
+    pub recipient: Address,
+    /// [`TxStatus::Skipped`] with the earlier transfer's hash when the log
+    /// showed the recipient as already credited.
+    pub summary: &'a TxSummary,
+}
+
+/// Transfer `token` to each of `recipients` in turn, waiting for every
+/// transfer before sending the next so their nonces can't collide.
+///
+/// Entries already credited in `log` are skipped, and each transfer is added
+/// to it, so running the same airdrop again after a failure only sends what's
+/// left. A transfer the log shows as sent but not mined, from a run that
+/// stopped while waiting for it, is waited for again rather than resent. An
+/// address listed twice is paid both amounts. `progress` is called after
+/// every recipient.
+///
+/// Stops at the first transfer that fails or reverts. One that reverted or
+/// was dropped is logged as such, and sent again by the next run.
+pub async fn airdrop<S: TxSender + ?Sized>(
+    sender: &S,
+    token: Address,
+    recipients: &[(Address, U256)],
+    log: &mut AirdropLog,
+    mut progress: impl FnMut(AirdropProgress<'_>),
+) -> Result<Vec<TxSummary>> {
+    let mut summaries = Vec::with_capacity(recipients.len());
+    for (index, &(to, amount)) in recipients.iter().enumerate() {
+        if to.is_zero() {
+            return Err(MyTokenError::InvalidRecipient(to));
+        }
+
+        let summary = match log.credited(index, to, amount) {
+            Some(tx_hash) => TxSummary {
+                tx_hash,
+                ..TxSummary::skipped()
+            },
+            None => {
+                let tx_hash = match log.pending(index, to, amount) {
+                    Some(tx_hash) => tx_hash,
+                    None => {
+                        let data = TransferCall { to, amount }.encode();
+                        let tx = Eip1559TransactionRequest::new().to(token).data(data);
+                        let tx_hash = sender.send(tx.into()).await?;
+                        // A simulated transfer pays nobody, so the log is left
+                        // for the run that sends it
+                        if !sender.is_dry_run() {
+                            log.record_sent(index, to, amount, tx_hash)?;
+                        }
+                        tx_hash
+                    }
+                };
+                let summary = match sender.wait(tx_hash).await {
+                    Ok(summary) => summary,
+                    // Never to credit the entry, so the next run sends it again
+                    // rather than waiting on it
+                    Err(err @ (MyTokenError::Reverted(_) | MyTokenError::Dropped(_))) => {
+                        log.record_reverted(index, to, amount, tx_hash)?;
+                        return Err(err);
+                    }
+                    Err(err) => return Err(err),
+                };
+                if summary.status != TxStatus::Simulated {
+                    log.record(index, to, amount, summary.tx_hash)?;
+                }
+                summary
+            }
+        };
+
+        progress(AirdropProgress {
+            done: index + 1,
+            total: recipients.len(),
+            recipient: to,
+            summary: &summary,
+        });
+        summaries.push(summary);
+    }
+    Ok(summaries)
+}
@@ -0,0 +1,80 @@
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+
+use crate::{MyTokenClient, Result, TxSummary};
+
+/// The token's ERC-20 reads and writes, object safe so that callers such as
+/// web handlers can hold an `Arc<dyn TokenApi>` without naming the
+/// middleware stack underneath.
+///
+/// [`MyTokenClient`] implements it by forwarding to its own methods, with
+/// the same caching, hooks and checks; the client has more beyond these.
+#[async_trait]
+pub trait TokenApi: Send + Sync {
+    /// The token contract's address.
+    fn address(&self) -> Address;
+
+    async fn name(&self) -> Result<String>;
+
+    async fn symbol(&self) -> Result<String>;
+
+    async fn decimals(&self) -> Result<u8>;
+
+    async fn total_supply(&self) -> Result<U256>;
+
+    async fn balance_of(&self, account: Address) -> Result<U256>;
+
+    async fn allowance(&self, owner: Address, spender: Address) -> Result<U256>;
+
+    async fn transfer(&self, to: Address, amount: U256) -> Result<TxSummary>;
+
+    async fn approve(&self, spender: Address, amount: U256) -> Result<TxSummary>;
+
+    async fn transfer_from(&self, from: Address, to: Address, amount: U256) -> Result<TxSummary>;
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> TokenApi for MyTokenClient<M> {
+    fn address(&self) -> Address {
+        MyTokenClient::address(self)
+    }
+
+    async fn name(&self) -> Result<String> {
+        MyTokenClient::name(self).await
+    }
+
+    async fn symbol(&self) -> Result<String> {
+        MyTokenClient::symbol(self).await
+    }
+
+    async fn decimals(&self) -> Result<u8> {
+        MyTokenClient::decimals(self).await
+    }
+
+    async fn total_supply(&self) -> Result<U256> {
+        MyTokenClient::total_supply(self).await
+    }
+
+    async fn balance_of(&self, account: Address) -> Result<U256> {
+        MyTokenClient::balance_of(self, account).await
+    }
+
+    async fn allowance(&self, owner: Address, spender: Address) -> Result<U256> {
+        MyTokenClient::allowance(self, owner, spender).await
+    }
+
+    async fn transfer(&self, to: Address, amount: U256) -> Result<TxSummary> {
+        MyTokenClient::transfer(self, to, amount).await
+    }
+
+    async fn approve(&self, spender: Address, amount: U256) -> Result<TxSummary> {
+        MyTokenClient::approve(self, spender, amount).await
+    }
+
+    async fn transfer_from(&self, from: Address, to: Address, amount: U256) -> Result<TxSummary> {
+        MyTokenClient::transfer_from(self, from, to, amount).await
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{MyTokenClient, Result};
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Balances and total supply as last read, with when they were read.
+#[derive(Debug, Default)]
+pub(crate) struct ReadCache {
+    ttl: Option<Duration>,
+    balances: HashMap<Address, (Instant, U256)>,
+    total_supply: Option<(Instant, U256)>,
+}
+
+impl ReadCache {
+    pub(crate) fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    // Forget every value, e.g. once a write may have changed them
+    pub(crate) fn clear(&mut self) {
+        self.balances.clear();
+        self.total_supply = None;
+    }
+
+    // The value of `entry` unless it's older than the TTL
+    fn live(&self, entry: Option<&(Instant, U256)>) -> Option<U256> {
+        let ttl = self.ttl?;
+        let &(read_at, value) = entry?;
+        (read_at.elapsed() < ttl).then_some(value)
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// [`balance_of`](Self::balance_of), answered from memory when read less
+    /// than the read cache TTL ago, unless `fresh` is set.
+    ///
+    /// Without a TTL, set by [`with_read_cache_ttl`](Self::with_read_cache_ttl)
+    /// or `read_cache_ttl_ms` in the config, every call reads the contract.
+    /// Writes through this client clear the cache; anyone else's aren't seen
+    /// until the TTL runs out.
+    pub async fn balance_of_cached(&self, account: Address, fresh: bool) -> Result<U256> {
+        if !fresh {
+            let cache = self.read_cache().lock().unwrap();
+            if let Some(balance) = cache.live(cache.balances.get(&account)) {
+                return Ok(balance);
+            }
+        }
+
+        let balance = self.balance_of(account).await?;
+        let mut cache = self.read_cache().lock().unwrap();
+        if cache.ttl.is_some() {
+            cache.balances.insert(account, (Instant::now(), balance));
+        }
+        Ok(balance)
+    }
+
+    /// [`total_supply`](Self::total_supply), cached like
+    /// [`balance_of_cached`](Self::balance_of_cached).
+    pub async fn total_supply_cached(&self, fresh: bool) -> Result<U256> {
+        if !fresh {
+            let cache = self.read_cache().lock().unwrap();
+            if let Some(supply) = cache.live(cache.total_supply.as_ref()) {
+                return Ok(supply);
+            }
+        }
+
+        let supply = self.total_supply().await?;
+        let mut cache = self.read_cache().lock().unwrap();
+        if cache.ttl.is_some() {
+            cache.total_supply = Some((Instant::now(), supply));
+        }
+        Ok(supply)
+    }
+}
@@ -0,0 +1,64 @@
+use ethers::types::TxHash;
+
+/// Networks the client knows block explorers for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Chain {
+    Mainnet,
+    Sepolia,
+    Holesky,
+    Optimism,
+    Polygon,
+    Base,
+    Arbitrum,
+    /// Local Anvil or Hardhat node, which has no explorer.
+    Anvil,
+}
+
+impl Chain {
+    pub fn from_id(chain_id: u64) -> Option<Self> {
+        Some(match chain_id {
+            1 => Chain::Mainnet,
+            11155111 => Chain::Sepolia,
+            17000 => Chain::Holesky,
+            10 => Chain::Optimism,
+            137 => Chain::Polygon,
+            8453 => Chain::Base,
+            42161 => Chain::Arbitrum,
+            31337 => Chain::Anvil,
+            _ => return None,
+        })
+    }
+
+    pub fn id(self) -> u64 {
+        match self {
+            Chain::Mainnet => 1,
+            Chain::Sepolia => 11155111,
+            Chain::Holesky => 17000,
+            Chain::Optimism => 10,
+            Chain::Polygon => 137,
+            Chain::Base => 8453,
+            Chain::Arbitrum => 42161,
+            Chain::Anvil => 31337,
+        }
+    }
+
+    /// Base URL of the chain's block explorer, if it has one.
+    pub fn explorer_url(self) -> Option<&'static str> {
+        match self {
+            Chain::Mainnet => Some("https://etherscan.io"),
+            Chain::Sepolia => Some("https://sepolia.etherscan.io"),
+            Chain::Holesky => Some("https://holesky.etherscan.io"),
+            Chain::Optimism => Some("https://optimistic.etherscan.io"),
+            Chain::Polygon => Some("https://polygonscan.com"),
+            Chain::Base => Some("https://basescan.org"),
+            Chain::Arbitrum => Some("https://arbiscan.io"),
+            Chain::Anvil => None,
+        }
+    }
+
+    /// Explorer page for the transaction `hash`.
+    pub fn explorer_tx_url(self, hash: TxHash) -> Option<String> {
+        self.explorer_url()
+            .map(|base| format!("{base}/tx/{hash:?}"))
+    }
+}
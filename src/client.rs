@@ -0,0 +1,1578 @@
+use crate::backoff::Backoff;
+use crate::cache::ReadCache;
+use crate::config::DEFAULT_TIMESTAMP_CACHE_SIZE;
+use crate::idempotency::Idempotency;
+use crate::telemetry::observe;
+use crate::{
+    Chain, Config, EnrichedTransfer, IdempotencyStore, JsonLinesStore, MyToken, MyTokenError,
+    MyTokenEvents, NetworkConfig, ReceiptStore, Result, SimulatedTx, SupplyChange,
+    SupplyChangeKind, TransferFilter, TransferFromEvents, TransferHook, TransportKind, TxStatus,
+    TxSummary, checksum, connect,
+};
+use ethers::{
+    abi::{self, Detokenize, ParamType, Token},
+    contract::{
+        ContractCall, MULTICALL_ADDRESS, Multicall,
+        multicall_contract::{Call as MulticallCall, Multicall3},
+        parse_log,
+    },
+    providers::Middleware,
+    types::{
+        Address, BlockId, Bytes, H256, TransactionReceipt, TxHash, U256,
+        transaction::eip2718::TypedTransaction,
+    },
+    utils::rlp::Rlp,
+};
+use futures::{Stream, StreamExt, TryFutureExt, TryStreamExt, stream};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+
+/// Basis points in a whole balance.
+const MAX_BPS: u16 = 10_000;
+
+/// What a transfer of zero tokens should do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ZeroAmount {
+    /// Fail with [`MyTokenError::ZeroAmount`] before sending anything.
+    #[default]
+    Reject,
+    /// Skip the transaction and return a [`TxStatus::Skipped`] summary.
+    Skip,
+}
+
+/// How many reads [`MyTokenClient::balances_unbatched`] keeps in flight by default.
+const DEFAULT_READ_CONCURRENCY: usize = 10;
+
+/// Typed client for a deployed `MyToken` contract.
+///
+/// Reads go straight to the contract, apart from the name and symbol, which
+/// are remembered; writes are sent as legacy transactions unless the
+/// [`NetworkConfig`] says otherwise, and awaited until mined, returning a
+/// [`TxSummary`].
+pub struct MyTokenClient<M> {
+    contract: MyToken<M>,
+    multicall: Option<Address>,
+    read_concurrency: usize,
+    zero_amount: ZeroAmount,
+    network: NetworkConfig,
+    idempotency: Idempotency,
+    timestamps: Arc<Mutex<LruCache<u64, u64>>>,
+    chain_id: Arc<OnceCell<u64>>,
+    domain_separator: Mutex<Option<(u64, H256)>>,
+    transfer_hooks: Vec<Arc<dyn TransferHook>>,
+    labels: Mutex<Labels>,
+    reads: Mutex<ReadCache>,
+    receipts: Option<Arc<dyn ReceiptStore>>,
+    dry_run: bool,
+    simulated: Arc<Mutex<Vec<SimulatedTx>>>,
+}
+
+/// Name and symbol as last read, kept until this client renames the token.
+#[derive(Debug, Default)]
+struct Labels {
+    name: Option<String>,
+    symbol: Option<String>,
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Clients built from clones of the same `Arc` share one provider, and with
+    /// it one HTTP connection pool or WebSocket, however many tokens they track.
+    pub fn new(address: Address, client: Arc<M>) -> Self {
+        Self {
+            contract: MyToken::new(address, client),
+            multicall: None,
+            read_concurrency: DEFAULT_READ_CONCURRENCY,
+            zero_amount: ZeroAmount::default(),
+            network: NetworkConfig::default(),
+            idempotency: Idempotency::default(),
+            timestamps: timestamp_cache(DEFAULT_TIMESTAMP_CACHE_SIZE),
+            chain_id: Arc::default(),
+            domain_separator: Mutex::default(),
+            transfer_hooks: Vec::new(),
+            labels: Mutex::default(),
+            reads: Mutex::default(),
+            receipts: None,
+            dry_run: false,
+            simulated: Arc::default(),
+        }
+    }
+
+    /// Use the Multicall3 deployment at `address` for batched reads instead of
+    /// the canonical one, e.g. on a local node.
+    pub fn with_multicall(mut self, address: Address) -> Self {
+        self.multicall = Some(address);
+        self
+    }
+
+    /// Cap on concurrent calls when reads can't be batched, 10 by default.
+    pub fn with_read_concurrency(mut self, limit: usize) -> Self {
+        self.read_concurrency = limit.max(1);
+        self
+    }
+
+    /// How transfers of zero tokens are handled, rejecting them by default.
+    pub fn with_zero_amount(mut self, zero_amount: ZeroAmount) -> Self {
+        self.zero_amount = zero_amount;
+        self
+    }
+
+    /// Gas strategy, poll interval and confirmations used for writes.
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Apply the `config` entry for the chain the middleware is connected to.
+    pub async fn with_config(self, config: &Config) -> Result<Self> {
+        let chain_id = self.chain_id().await?;
+        let mut token = self
+            .with_network(config.network(chain_id))
+            .with_timestamp_cache(config.timestamp_cache_size);
+        if let Some(ttl) = config.read_cache_ttl() {
+            token = token.with_read_cache_ttl(ttl);
+        }
+        if let Some(path) = &config.receipt_log {
+            token = token.with_receipt_store(Arc::new(JsonLinesStore::new(path)));
+        }
+        Ok(token)
+    }
+
+    /// How many block timestamps to keep for [`enriched_transfers`](Self::enriched_transfers),
+    /// 1024 by default. Clients made with [`at`](Self::at) share the cache.
+    pub fn with_timestamp_cache(mut self, size: usize) -> Self {
+        self.timestamps = timestamp_cache(size);
+        self
+    }
+
+    /// How long [`balance_of_cached`](Self::balance_of_cached) and
+    /// [`total_supply_cached`](Self::total_supply_cached) may answer from
+    /// memory. Nothing is cached by default.
+    pub fn with_read_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.reads = Mutex::new(ReadCache::new(Some(ttl)));
+        self
+    }
+
+    pub(crate) fn read_cache(&self) -> &Mutex<ReadCache> {
+        &self.reads
+    }
+
+    pub(crate) fn multicall_address(&self) -> Option<Address> {
+        self.multicall
+    }
+
+    pub fn network(&self) -> &NetworkConfig {
+        &self.network
+    }
+
+    /// Where [`transfer_with_key`](Self::transfer_with_key) records sent
+    /// transfers, in memory by default.
+    pub fn with_idempotency_store(mut self, store: Arc<dyn IdempotencyStore>) -> Self {
+        self.idempotency = Idempotency::new(store);
+        self
+    }
+
+    /// Record every write this client sends in `store` once it's mined. A
+    /// record that can't be stored is logged as a `tracing` warning and
+    /// doesn't fail the write, which was mined all the same. Nothing is
+    /// recorded by default.
+    pub fn with_receipt_store(mut self, store: Arc<dyn ReceiptStore>) -> Self {
+        self.receipts = Some(store);
+        self
+    }
+
+    pub(crate) fn simulated(&self) -> &Mutex<Vec<SimulatedTx>> {
+        &self.simulated
+    }
+
+    pub(crate) fn receipt_store(&self) -> Option<&dyn ReceiptStore> {
+        self.receipts.as_deref()
+    }
+
+    /// Simulate every write with `eth_call` instead of sending it, recording
+    /// what would have been sent in [`simulated_txs`](Self::simulated_txs)
+    /// and logging it at `tracing`'s info level. Writes then return a
+    /// [`TxStatus::Simulated`] summary with the hash of the unsigned
+    /// transaction, which was never broadcast, or fail as they would have had
+    /// they been sent.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Run `hook` around every transfer this client sends, after any hooks
+    /// already added.
+    pub fn with_transfer_hook(mut self, hook: Arc<dyn TransferHook>) -> Self {
+        self.transfer_hooks.push(hook);
+        self
+    }
+
+    pub(crate) fn idempotency(&self) -> &Idempotency {
+        &self.idempotency
+    }
+
+    // The permit domain separator last read, with the chain id it was read on
+    pub(crate) fn domain_separator_cache(&self) -> &Mutex<Option<(u64, H256)>> {
+        &self.domain_separator
+    }
+
+    /// Client for the token at `address`, sharing this client's middleware.
+    pub fn at(&self, address: Address) -> Self {
+        Self {
+            contract: MyToken::new(address, self.client()),
+            multicall: self.multicall,
+            read_concurrency: self.read_concurrency,
+            zero_amount: self.zero_amount,
+            network: self.network.clone(),
+            idempotency: self.idempotency.clone(),
+            timestamps: self.timestamps.clone(),
+            chain_id: self.chain_id.clone(),
+            domain_separator: Mutex::default(),
+            transfer_hooks: self.transfer_hooks.clone(),
+            labels: Mutex::default(),
+            reads: Mutex::new(ReadCache::new(self.reads.lock().unwrap().ttl())),
+            receipts: self.receipts.clone(),
+            dry_run: self.dry_run,
+            simulated: self.simulated.clone(),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.contract.address()
+    }
+
+    /// Issues a cheap `eth_chainId` so the connection is established up front
+    /// rather than on the first real call. Returns the chain id.
+    pub async fn warm_up(&self) -> Result<U256> {
+        self.client()
+            .get_chainid()
+            .await
+            .map_err(MyTokenError::middleware)
+    }
+
+    /// Id of the connected chain, asked for once and remembered.
+    pub async fn chain_id(&self) -> Result<u64> {
+        self.chain_id
+            .get_or_try_init(|| async { Ok(self.warm_up().await?.as_u64()) })
+            .await
+            .copied()
+    }
+
+    /// The connected chain, if it's one the client knows.
+    pub async fn chain(&self) -> Result<Option<Chain>> {
+        Ok(Chain::from_id(self.chain_id().await?))
+    }
+
+    /// The shared middleware this client sends through.
+    pub fn client(&self) -> Arc<M> {
+        self.contract.client()
+    }
+
+    /// The underlying `abigen` binding, for calls the client doesn't wrap.
+    pub fn contract(&self) -> &MyToken<M> {
+        &self.contract
+    }
+
+    /// Address writes are sent from.
+    pub fn sender(&self) -> Result<Address> {
+        self.client().default_sender().ok_or(MyTokenError::NoSigner)
+    }
+
+    /// Token name, also accepting the `bytes32` some older tokens return.
+    ///
+    /// Read once and remembered; [`set_name`](Self::set_name) makes the next
+    /// call read it again. Renames sent by anyone else aren't noticed.
+    pub async fn name(&self) -> Result<String> {
+        if let Some(name) = self.labels.lock().unwrap().name.clone() {
+            return Ok(name);
+        }
+        let name = observe("name", self.string_or_bytes32(self.contract.name())).await?;
+        self.labels.lock().unwrap().name = Some(name.clone());
+        Ok(name)
+    }
+
+    /// Token symbol, also accepting the `bytes32` some older tokens return.
+    ///
+    /// Remembered like [`name`](Self::name), until
+    /// [`set_symbol`](Self::set_symbol).
+    pub async fn symbol(&self) -> Result<String> {
+        if let Some(symbol) = self.labels.lock().unwrap().symbol.clone() {
+            return Ok(symbol);
+        }
+        let symbol = observe("symbol", self.string_or_bytes32(self.contract.symbol())).await?;
+        self.labels.lock().unwrap().symbol = Some(symbol.clone());
+        Ok(symbol)
+    }
+
+    /// Owner-only rename of the token. Permits are unaffected, as their
+    /// signing domain keeps the name the token was deployed with.
+    pub async fn set_name(&self, name: &str) -> Result<TxSummary> {
+        let summary = self.send(self.contract.set_name(name.to_owned())).await?;
+        self.labels.lock().unwrap().name = None;
+        Ok(summary)
+    }
+
+    /// Owner-only change of the token symbol.
+    pub async fn set_symbol(&self, symbol: &str) -> Result<TxSummary> {
+        let summary = self
+            .send(self.contract.set_symbol(symbol.to_owned()))
+            .await?;
+        self.labels.lock().unwrap().symbol = None;
+        Ok(summary)
+    }
+
+    pub async fn decimals(&self) -> Result<u8> {
+        observe("decimals", async {
+            Ok(self.contract.decimals().call().await?)
+        })
+        .await
+    }
+
+    /// The account allowed to mint, freeze and unfreeze.
+    pub async fn owner(&self) -> Result<Address> {
+        Ok(self.contract.owner().call().await?)
+    }
+
+    pub async fn total_supply(&self) -> Result<U256> {
+        observe("totalSupply", async {
+            Ok(self.contract.total_supply().call().await?)
+        })
+        .await
+    }
+
+    /// Total supply as of `block`.
+    ///
+    /// The token keeps no supply checkpoints, so this reads historical state
+    /// and needs a node that still has it, such as an archive node or Anvil.
+    pub async fn total_supply_at(&self, block: BlockId) -> Result<U256> {
+        Ok(self.contract.total_supply().block(block).call().await?)
+    }
+
+    /// Maximum total supply the contract allows.
+    pub async fn cap(&self) -> Result<U256> {
+        Ok(self.contract.cap().call().await?)
+    }
+
+    /// Tokens that can still be minted before reaching the cap.
+    pub async fn remaining_mintable(&self) -> Result<U256> {
+        Ok(self.contract.remaining_mintable().call().await?)
+    }
+
+    /// Propose raising or lowering the cap. It takes effect once
+    /// [`execute_cap_change`](Self::execute_cap_change) is called after the
+    /// cap change delay, and can't be below the current total supply.
+    pub async fn propose_cap_change(&self, new_cap: U256) -> Result<TxSummary> {
+        self.send(self.contract.propose_cap_change(new_cap)).await
+    }
+
+    /// Apply the pending cap change, failing with
+    /// [`MyTokenError::CapChangeNotReady`] while its delay hasn't passed.
+    pub async fn execute_cap_change(&self) -> Result<TxSummary> {
+        self.send(self.contract.execute_cap_change()).await
+    }
+
+    /// The proposed cap, or `None` if no change is pending.
+    pub async fn pending_cap(&self) -> Result<Option<U256>> {
+        let cap = self.contract.pending_cap().call().await?;
+        Ok((!cap.is_zero()).then_some(cap))
+    }
+
+    /// When the pending cap change can be executed, or `None` if no change is
+    /// pending.
+    pub async fn cap_change_time(&self) -> Result<Option<U256>> {
+        let time = self.contract.cap_change_time().call().await?;
+        Ok((!time.is_zero()).then_some(time))
+    }
+
+    /// Seconds a proposed cap change waits before it can be executed.
+    pub async fn cap_change_delay(&self) -> Result<U256> {
+        Ok(self.contract.cap_change_delay().call().await?)
+    }
+
+    /// Change the delay for cap changes. The new delay only takes effect once
+    /// the current one has passed, so it can't be lowered to rush a cap
+    /// change through.
+    pub async fn set_cap_change_delay(&self, seconds: U256) -> Result<TxSummary> {
+        self.send(self.contract.set_cap_change_delay(seconds)).await
+    }
+
+    /// The delay waiting to take effect and when it does, or `None` if no
+    /// change is waiting.
+    pub async fn pending_cap_change_delay(&self) -> Result<Option<(U256, U256)>> {
+        let delay = self.contract.pending_cap_change_delay();
+        let time = self.contract.cap_change_delay_time();
+        let (delay, time) = futures::try_join!(delay.call(), time.call())?;
+        Ok((!time.is_zero()).then_some((delay, time)))
+    }
+
+    pub async fn balance_of(&self, account: Address) -> Result<U256> {
+        observe("balanceOf", async {
+            Ok(self.contract.balance_of(account).call().await?)
+        })
+        .await
+    }
+
+    /// `account`'s balance, checked against a second node at `secondary_url`.
+    ///
+    /// Both nodes are asked for the balance at this client's latest block, so
+    /// a node that lags behind, has forked off or reports false state shows up
+    /// as [`MyTokenError::InconsistentState`]. It's only a real check when the
+    /// secondary is run independently of the primary, e.g. by another provider.
+    pub async fn balance_cross_check(&self, account: Address, secondary_url: &str) -> Result<U256> {
+        let block = self
+            .client()
+            .get_block_number()
+            .await
+            .map_err(MyTokenError::middleware)?;
+        let secondary = MyToken::new(self.address(), Arc::new(connect(secondary_url).await?));
+
+        let primary_call = self.contract.balance_of(account).block(block);
+        let secondary_call = secondary.balance_of(account).block(block);
+        let (primary_balance, secondary_balance) = futures::try_join!(
+            primary_call.call().map_err(MyTokenError::from),
+            secondary_call.call().map_err(MyTokenError::from),
+        )?;
+        if primary_balance != secondary_balance {
+            return Err(MyTokenError::InconsistentState {
+                account,
+                block: block.as_u64(),
+                primary: primary_balance,
+                secondary: secondary_balance,
+            });
+        }
+        Ok(primary_balance)
+    }
+
+    /// Balances of `accounts`, in input order, read in a single Multicall3 call.
+    ///
+    /// Falls back to [`balances_unbatched`](Self::balances_unbatched) when no
+    /// Multicall3 deployment is configured or known for the connected chain.
+    pub async fn balances(&self, accounts: &[Address]) -> Result<Vec<U256>> {
+        let Ok(mut multicall) = Multicall::new(self.client(), self.multicall).await else {
+            return self.balances_unbatched(accounts).await;
+        };
+
+        for &account in accounts {
+            multicall.add_call(self.contract.balance_of(account), false);
+        }
+
+        Ok(multicall.call_array().await?)
+    }
+
+    /// Balances of `accounts`, in input order, read in one Multicall3
+    /// `tryAggregate` so a call that fails doesn't take the others with it.
+    ///
+    /// When the batch as a whole can't be made, every entry carries the error.
+    pub async fn try_balances(&self, accounts: &[Address]) -> Vec<Result<U256>> {
+        let calls = accounts
+            .iter()
+            .map(|&account| MulticallCall {
+                target: self.address(),
+                call_data: self
+                    .contract
+                    .balance_of(account)
+                    .calldata()
+                    .unwrap_or_default(),
+            })
+            .collect();
+        let multicall = Multicall3::new(self.multicall.unwrap_or(MULTICALL_ADDRESS), self.client());
+
+        let results = match multicall.try_aggregate(false, calls).call().await {
+            Ok(results) => results,
+            Err(err) => {
+                let message = err.to_string();
+                return accounts
+                    .iter()
+                    .map(|_| Err(MyTokenError::Contract(message.clone())))
+                    .collect();
+            }
+        };
+
+        accounts
+            .iter()
+            .zip(results)
+            .map(|(&account, result)| {
+                let call = || format!("balanceOf({})", checksum(account));
+                if !result.success {
+                    return Err(MyTokenError::Contract(format!("{} reverted", call())));
+                }
+                match abi::decode(&[ParamType::Uint(256)], &result.return_data)
+                    .ok()
+                    .and_then(|mut tokens| tokens.pop())
+                {
+                    Some(Token::Uint(balance)) => Ok(balance),
+                    _ => Err(MyTokenError::Decode(call())),
+                }
+            })
+            .collect()
+    }
+
+    /// Balances of `accounts`, in input order, with one `balanceOf` call each.
+    ///
+    /// Up to the configured read concurrency calls are in flight at once.
+    pub async fn balances_unbatched(&self, accounts: &[Address]) -> Result<Vec<U256>> {
+        let mut balances: Vec<(usize, U256)> = stream::iter(accounts.iter().enumerate())
+            .map(|(index, &account)| async move {
+                self.balance_of(account)
+                    .await
+                    .map(|balance| (index, balance))
+            })
+            .buffer_unordered(self.read_concurrency)
+            .try_collect()
+            .await?;
+
+        balances.sort_unstable_by_key(|&(index, _)| index);
+        Ok(balances.into_iter().map(|(_, balance)| balance).collect())
+    }
+
+    /// `account`'s balance as of each of `blocks`, in input order.
+    ///
+    /// A multicall only ever sees the state of the block it's made in, so each
+    /// block gets its own `balanceOf` call, up to the configured read
+    /// concurrency at once. Like [`total_supply_at`](Self::total_supply_at),
+    /// older blocks need a node that still has their state.
+    pub async fn balance_history(&self, account: Address, blocks: &[u64]) -> Result<Vec<U256>> {
+        stream::iter(blocks)
+            .map(|&block| async move {
+                Ok(self
+                    .contract
+                    .balance_of(account)
+                    .block(block)
+                    .call()
+                    .await?)
+            })
+            .buffered(self.read_concurrency)
+            .try_collect()
+            .await
+    }
+
+    pub async fn allowance(&self, owner: Address, spender: Address) -> Result<U256> {
+        observe("allowance", async {
+            Ok(self.contract.allowance(owner, spender).call().await?)
+        })
+        .await
+    }
+
+    /// How much of `owner`'s tokens `spender` can actually move: the lesser of
+    /// the balance and the allowance, read together in one Multicall3 call.
+    ///
+    /// An owner spending their own tokens needs no allowance, so that's just
+    /// the balance. Without a Multicall3 deployment the two are read
+    /// separately.
+    pub async fn spendable(&self, owner: Address, spender: Address) -> Result<U256> {
+        if owner == spender {
+            return self.balance_of(owner).await;
+        }
+        let (balance, allowance) = match Multicall::new(self.client(), self.multicall).await {
+            Ok(mut multicall) => {
+                multicall
+                    .add_call(self.contract.balance_of(owner), false)
+                    .add_call(self.contract.allowance(owner, spender), false);
+                multicall.call().await?
+            }
+            Err(_) => futures::try_join!(self.balance_of(owner), self.allowance(owner, spender))?,
+        };
+        Ok(balance.min(allowance))
+    }
+
+    /// Transferring to the sender's own address is sent like any other: it
+    /// leaves the balance unchanged, pays no fee and still emits a `Transfer`.
+    pub async fn transfer(&self, to: Address, amount: U256) -> Result<TxSummary> {
+        self.transfer_noting(to, amount, |_| {}).await
+    }
+
+    // `transfer`, handing `sent` the hash as soon as it's broadcast
+    pub(crate) async fn transfer_noting(
+        &self,
+        to: Address,
+        amount: U256,
+        sent: impl FnOnce(TxHash) + Send,
+    ) -> Result<TxSummary> {
+        if let Some(skipped) = self.validate_transfer(to, amount)? {
+            return Ok(skipped);
+        }
+        self.send_transfer_noting(to, amount, self.contract.transfer(to, amount), sent)
+            .await
+    }
+
+    /// Transfer `amount` to `to` only if `to` would receive at least
+    /// `min_received` after the token's transfer fee, failing with
+    /// [`MyTokenError::BelowMinimumReceived`] before anything is sent
+    /// otherwise.
+    ///
+    /// The transfer is simulated against the latest block to catch reverts,
+    /// but what `to` receives is `amount` less the token's `transferFee` view
+    /// at that block, not a measured change in its balance: it accounts for
+    /// `MyToken`'s fee only, and trusts the view to match what the transfer
+    /// takes. A fee raised between then and the transfer being mined still
+    /// applies.
+    pub async fn transfer_min_received(
+        &self,
+        to: Address,
+        amount: U256,
+        min_received: U256,
+    ) -> Result<TxSummary> {
+        if let Some(skipped) = self.validate_transfer(to, amount)? {
+            return Ok(skipped);
+        }
+        let sender = self.sender()?;
+        let block = self
+            .client()
+            .get_block_number()
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        // A transfer that would revert fails here with its reason
+        self.contract
+            .transfer(to, amount)
+            .block(block)
+            .call()
+            .await?;
+        let fee = self
+            .contract
+            .transfer_fee(sender, to, amount)
+            .block(block)
+            .call()
+            .await?;
+        let received = amount - fee;
+        if received < min_received {
+            return Err(MyTokenError::BelowMinimumReceived {
+                received,
+                min_received,
+            });
+        }
+
+        self.send_transfer(to, amount, self.contract.transfer(to, amount))
+            .await
+    }
+
+    /// Transfer `bps` basis points of the sender's balance, rounding down, so
+    /// 5000 sends half. More than 10000 is rejected.
+    pub async fn transfer_percent(&self, to: Address, bps: u16) -> Result<TxSummary> {
+        if bps > MAX_BPS {
+            return Err(MyTokenError::InvalidBps(bps));
+        }
+        let balance = self.balance_of(self.sender()?).await?;
+        // Split so no balance, however large, overflows when multiplied
+        let (max, bps) = (U256::from(MAX_BPS), U256::from(bps));
+        let amount = balance / max * bps + balance % max * bps / max;
+        self.transfer(to, amount).await
+    }
+
+    /// Set `spender`'s allowance to `amount`.
+    ///
+    /// Tokens such as USDT revert, with no reason, when one nonzero allowance
+    /// is changed straight to another. When a nonzero approve fails that way
+    /// while an allowance is in place, and approving zero instead would go
+    /// through, it is retried as an approve of zero followed by the one asked
+    /// for, returning the summary of the last. Any other failure is returned
+    /// as is, and other tokens see a single transaction.
+    pub async fn approve(&self, spender: Address, amount: U256) -> Result<TxSummary> {
+        let result = self.send(self.contract.approve(spender, amount)).await;
+        if !matches!(
+            result,
+            Err(MyTokenError::ExecutionReverted(None) | MyTokenError::Reverted(_))
+        ) || amount.is_zero()
+            || self.allowance(self.sender()?, spender).await?.is_zero()
+            || !self.needs_zero_first(spender, amount).await?
+        {
+            return result;
+        }
+
+        self.send(self.contract.approve(spender, U256::zero()))
+            .await?;
+        self.send(self.contract.approve(spender, amount)).await
+    }
+
+    // Whether the token refuses approving `amount` with a bare revert but
+    // takes an approve of zero, as tokens do that want an allowance reset
+    // before it's changed. A mined revert says nothing of why, so both are
+    // simulated.
+    async fn needs_zero_first(&self, spender: Address, amount: U256) -> Result<bool> {
+        let direct = self.contract.approve(spender, amount).call().await;
+        if !matches!(
+            direct.map_err(MyTokenError::from),
+            Err(MyTokenError::ExecutionReverted(None))
+        ) {
+            return Ok(false);
+        }
+        let reset = self.contract.approve(spender, U256::zero()).call().await;
+        Ok(reset.is_ok())
+    }
+
+    /// Approve `spender` for `amount` until `deadline`, a unix timestamp, after
+    /// which `transferFrom` reverts. A plain [`approve`](Self::approve) or
+    /// permit for the same spender lifts the expiry.
+    pub async fn approve_with_expiry(
+        &self,
+        spender: Address,
+        amount: U256,
+        deadline: U256,
+    ) -> Result<TxSummary> {
+        self.send(self.contract.approve_with_expiry(spender, amount, deadline))
+            .await
+    }
+
+    pub async fn transfer_from(
+        &self,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<TxSummary> {
+        if let Some(skipped) = self.validate_transfer(to, amount)? {
+            return Ok(skipped);
+        }
+        self.send_transfer(to, amount, self.contract.transfer_from(from, to, amount))
+            .await
+    }
+
+    /// Owner-only mint of new tokens to `to`.
+    pub async fn mint(&self, to: Address, amount: U256) -> Result<TxSummary> {
+        self.send(self.contract.mint(to, amount)).await
+    }
+
+    /// Like [`mint`](Self::mint), but lets a contract recipient that implements
+    /// `IMintReceiver` react to the mint through its `tokensReceived` hook.
+    /// Minting to an EOA is the same as a plain mint.
+    pub async fn safe_mint(&self, to: Address, amount: U256) -> Result<TxSummary> {
+        self.send(self.contract.safe_mint(to, amount)).await
+    }
+
+    /// Destroy `amount` of the sender's own tokens.
+    pub async fn burn(&self, amount: U256) -> Result<TxSummary> {
+        self.send(self.contract.burn(amount)).await
+    }
+
+    /// Destroy `amount` of `account`'s tokens out of the allowance it gave the
+    /// sender.
+    pub async fn burn_from(&self, account: Address, amount: U256) -> Result<TxSummary> {
+        self.send(self.contract.burn_from(account, amount)).await
+    }
+
+    /// Largest flash loan the token will make, what's left under the cap.
+    pub async fn max_flash_loan(&self) -> Result<U256> {
+        Ok(self.contract.max_flash_loan(self.address()).call().await?)
+    }
+
+    /// Fee charged on top of a flash loan of `amount`.
+    pub async fn flash_fee(&self, amount: U256) -> Result<U256> {
+        Ok(self
+            .contract
+            .flash_fee(self.address(), amount)
+            .call()
+            .await?)
+    }
+
+    /// EIP-3156 flash loan of `amount` to the `receiver` contract, which gets
+    /// `data` in its `onFlashLoan` callback. The receiver must approve the
+    /// token to take back the amount plus fee, or the whole transaction reverts.
+    pub async fn flash_loan(
+        &self,
+        receiver: Address,
+        amount: U256,
+        data: Bytes,
+    ) -> Result<TxSummary> {
+        self.send(
+            self.contract
+                .flash_loan(receiver, self.address(), amount, data),
+        )
+        .await
+    }
+
+    /// Owner-only freeze of `account`'s outgoing transfers. It can still receive.
+    pub async fn freeze(&self, account: Address) -> Result<TxSummary> {
+        self.send(self.contract.freeze(account)).await
+    }
+
+    pub async fn unfreeze(&self, account: Address) -> Result<TxSummary> {
+        self.send(self.contract.unfreeze(account)).await
+    }
+
+    /// Send all the ETH held by the token contract, sent to it by mistake, to
+    /// `to`. Only the owner can.
+    pub async fn sweep_eth(&self, to: Address) -> Result<TxSummary> {
+        self.send(self.contract.sweep_eth(to)).await
+    }
+
+    /// Take `bps` basis points of each transfer as a fee for the owner, at most
+    /// 1000. Transfers to or from the owner are free.
+    pub async fn set_transfer_fee(&self, bps: u16) -> Result<TxSummary> {
+        self.send(self.contract.set_transfer_fee(bps)).await
+    }
+
+    pub async fn transfer_fee_bps(&self) -> Result<u16> {
+        Ok(self.contract.transfer_fee_bps().call().await?)
+    }
+
+    /// When `spender`'s allowance over `owner`'s tokens expires, or `None` if
+    /// it never does.
+    pub async fn allowance_expiry(&self, owner: Address, spender: Address) -> Result<Option<U256>> {
+        let expiry = self
+            .contract
+            .allowance_expiry(owner, spender)
+            .call()
+            .await?;
+        Ok((!expiry.is_zero()).then_some(expiry))
+    }
+
+    pub async fn is_frozen(&self, account: Address) -> Result<bool> {
+        Ok(self.contract.is_frozen(account).call().await?)
+    }
+
+    /// Set `spender`'s allowance over the sender's tokens back to zero, checking
+    /// afterwards that it really is zero.
+    pub async fn revoke_allowance(&self, spender: Address) -> Result<TxSummary> {
+        let summary = self.approve(spender, U256::zero()).await?;
+
+        let remaining = self.allowance(self.sender()?, spender).await?;
+        if !remaining.is_zero() {
+            return Err(MyTokenError::NotRevoked { spender, remaining });
+        }
+
+        Ok(summary)
+    }
+
+    /// Transfer that also catches tokens signalling failure by returning `false`
+    /// rather than reverting.
+    ///
+    /// The call is simulated first and only sent if it returns `true` or, like
+    /// USDT, returns nothing at all.
+    pub async fn safe_transfer(&self, to: Address, amount: U256) -> Result<TxSummary> {
+        if let Some(skipped) = self.validate_transfer(to, amount)? {
+            return Ok(skipped);
+        }
+
+        let call = self.contract.transfer(to, amount);
+        self.check_returns_true(&call).await?;
+        self.send_transfer(to, amount, call).await
+    }
+
+    /// Transfer to a contract and have it act on the tokens in the same
+    /// transaction through its ERC-1363 `onTransferReceived` hook, which gets
+    /// `data`. No approval is needed.
+    ///
+    /// `to` must be a contract, otherwise [`MyTokenError::NoContract`]. A
+    /// receiver that rejects the transfer fails with its revert reason as
+    /// [`MyTokenError::ExecutionReverted`], found by a dry run before sending.
+    pub async fn transfer_and_call(
+        &self,
+        to: Address,
+        amount: U256,
+        data: Bytes,
+    ) -> Result<TxSummary> {
+        if let Some(skipped) = self.validate_transfer(to, amount)? {
+            return Ok(skipped);
+        }
+        let code = self
+            .client()
+            .get_code(to, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+        if code.is_empty() {
+            return Err(MyTokenError::NoContract(to));
+        }
+
+        // A revert once mined only says that it reverted
+        let call = self.contract.transfer_and_call(to, amount, data);
+        self.client()
+            .call(&call.tx, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+        self.send_transfer(to, amount, call).await
+    }
+
+    /// Pay each of `payouts` in one transaction.
+    ///
+    /// When `atomic`, the whole batch reverts, paying no one, if any transfer
+    /// in it would fail. Otherwise recipients that can't be paid, the zero
+    /// address or anyone owed more than the balance left by then, are skipped
+    /// with a `BatchTransferSkipped` event and the rest still paid. Transfer
+    /// hooks aren't run.
+    pub async fn batch_transfer(
+        &self,
+        payouts: &[(Address, U256)],
+        atomic: bool,
+    ) -> Result<TxSummary> {
+        let (to, amounts) = payouts.iter().copied().unzip();
+        self.send(self.contract.batch_transfer(to, amounts, atomic))
+            .await
+    }
+
+    /// Approve `amount`, resetting a nonzero allowance to zero first.
+    ///
+    /// Some legacy tokens reject changing one nonzero allowance to another, so
+    /// this sends two transactions in that case and one otherwise. Each approve
+    /// is checked for a `false` return like [`safe_transfer`](Self::safe_transfer).
+    pub async fn safe_approve(&self, spender: Address, amount: U256) -> Result<Vec<TxSummary>> {
+        let current = self.allowance(self.sender()?, spender).await?;
+
+        let mut summaries = Vec::with_capacity(2);
+        if !current.is_zero() && !amount.is_zero() {
+            summaries.push(self.checked_approve(spender, U256::zero()).await?);
+        }
+        summaries.push(self.checked_approve(spender, amount).await?);
+
+        Ok(summaries)
+    }
+
+    async fn checked_approve(&self, spender: Address, amount: U256) -> Result<TxSummary> {
+        let call = self.contract.approve(spender, amount);
+        self.check_returns_true(&call).await?;
+        self.send(call).await
+    }
+
+    // Simulate `call` and fail unless it returns `true` or no data
+    async fn check_returns_true(&self, call: &ContractCall<M, bool>) -> Result<()> {
+        let output = self
+            .client()
+            .call(&call.tx, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+        if output.is_empty() {
+            return Ok(());
+        }
+
+        let returned = abi::decode(&[ParamType::Bool], &output)
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next())
+            .and_then(Token::into_bool);
+        match returned {
+            Some(true) => Ok(()),
+            _ => Err(MyTokenError::ReturnedFalse),
+        }
+    }
+
+    /// `Transfer` events since `from_block`, with each block's timestamp.
+    ///
+    /// Timestamps are fetched once per block, however many transfers it holds,
+    /// and kept in an LRU cache shared by later calls. Scanning from block 0
+    /// logs a `tracing` warning, as most nodes refuse or time out on it.
+    pub async fn enriched_transfers(&self, from_block: u64) -> Result<Vec<EnrichedTransfer>> {
+        warn_if_full_history(from_block);
+        self.enriched_transfers_between(from_block, None).await
+    }
+
+    /// [`enriched_transfers`](Self::enriched_transfers) since `from_block`, or
+    /// over the last [`log_lookback_blocks`](NetworkConfig::log_lookback_blocks)
+    /// blocks when it's `None`, rather than from genesis.
+    pub async fn transfer_events(&self, from_block: Option<u64>) -> Result<Vec<EnrichedTransfer>> {
+        let from_block = match from_block {
+            Some(from_block) => {
+                warn_if_full_history(from_block);
+                from_block
+            }
+            None => {
+                let latest = self
+                    .client()
+                    .get_block_number()
+                    .await
+                    .map_err(MyTokenError::middleware)?
+                    .as_u64();
+                (latest + 1).saturating_sub(self.network.log_lookback_blocks)
+            }
+        };
+        self.enriched_transfers_between(from_block, None).await
+    }
+
+    /// Transfers from `from_block` up to the latest block, in pages of
+    /// `page_size` blocks each, oldest first.
+    ///
+    /// Each page is fetched only when the stream is polled for it, so a
+    /// consumer that stops early never pays for the rest of the history. A
+    /// window with no transfers yields an empty page. The latest block is
+    /// read once, when the first page is asked for; the stream ends after
+    /// the page containing it, or after the first error.
+    pub fn transfer_history(
+        &self,
+        from_block: u64,
+        page_size: u64,
+    ) -> impl Stream<Item = Result<Vec<EnrichedTransfer>>> {
+        let page_size = page_size.max(1);
+        stream::try_unfold((from_block, None), move |(next, latest)| async move {
+            let latest = match latest {
+                Some(latest) => latest,
+                None => self
+                    .client()
+                    .get_block_number()
+                    .await
+                    .map_err(MyTokenError::middleware)?
+                    .as_u64(),
+            };
+            if next > latest {
+                return Ok(None);
+            }
+            let to_block = next.saturating_add(page_size - 1).min(latest);
+            let page = self
+                .enriched_transfers_between(next, Some(to_block))
+                .await?;
+            Ok(Some((page, (to_block + 1, Some(latest)))))
+        })
+    }
+
+    // `enriched_transfers` up to and including `to_block`, or the latest block
+    pub(crate) async fn enriched_transfers_between(
+        &self,
+        from_block: u64,
+        to_block: Option<u64>,
+    ) -> Result<Vec<EnrichedTransfer>> {
+        let mut filter = self.contract.transfer_filter().from_block(from_block);
+        if let Some(to_block) = to_block {
+            filter = filter.to_block(to_block);
+        }
+        let events = filter.query_with_meta().await?;
+
+        let mut transfers = Vec::with_capacity(events.len());
+        for (event, meta) in events {
+            let block_number = meta.block_number.as_u64();
+            let timestamp = self.block_timestamp(block_number).await?;
+
+            transfers.push(EnrichedTransfer {
+                from: event.from,
+                to: event.to,
+                value: event.value,
+                block_number,
+                block_hash: meta.block_hash,
+                tx_hash: meta.transaction_hash,
+                log_index: meta.log_index,
+                timestamp,
+            });
+        }
+
+        Ok(transfers)
+    }
+
+    /// Every mint and burn since `from_block`, oldest first.
+    ///
+    /// Relies on the ERC20 convention, which OpenZeppelin follows, that minting
+    /// emits a `Transfer` from the zero address and burning one to it. A token
+    /// changing supply without those events would go unnoticed.
+    pub async fn supply_events(&self, from_block: u64) -> Result<Vec<SupplyChange>> {
+        warn_if_full_history(from_block);
+        let mints = self
+            .contract
+            .transfer_filter()
+            .from_block(from_block)
+            .topic1(Address::zero())
+            .query_with_meta()
+            .await?;
+        let burns = self
+            .contract
+            .transfer_filter()
+            .from_block(from_block)
+            .topic2(Address::zero())
+            .query_with_meta()
+            .await?;
+
+        let mut changes: Vec<_> = mints
+            .into_iter()
+            .map(|(event, meta)| (SupplyChangeKind::Mint, event.to, event, meta))
+            .chain(
+                burns
+                    .into_iter()
+                    .map(|(event, meta)| (SupplyChangeKind::Burn, event.from, event, meta)),
+            )
+            .collect();
+        changes.sort_by_key(|(_, _, _, meta)| (meta.block_number, meta.log_index));
+
+        Ok(changes
+            .into_iter()
+            .map(|(kind, account, event, meta)| SupplyChange {
+                kind,
+                account,
+                amount: event.value,
+                block: meta.block_number.as_u64(),
+            })
+            .collect())
+    }
+
+    /// The `Transfer` and resulting `Approval` emitted by the `transferFrom`
+    /// mined in `tx_hash`, read from its receipt.
+    pub async fn transfer_from_events(&self, tx_hash: TxHash) -> Result<TransferFromEvents> {
+        let receipt = self
+            .client()
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(MyTokenError::middleware)?
+            .ok_or(MyTokenError::NoReceipt(tx_hash))?;
+
+        let mut transfer = None;
+        let mut approval = None;
+        for log in receipt.logs {
+            if log.address != self.address() {
+                continue;
+            }
+            match parse_log::<MyTokenEvents>(log) {
+                Ok(MyTokenEvents::TransferFilter(event)) => transfer = Some(event),
+                Ok(MyTokenEvents::ApprovalFilter(event)) => approval = Some(event),
+                _ => {}
+            }
+        }
+
+        let transfer =
+            transfer.ok_or_else(|| MyTokenError::Decode(format!("Transfer in {tx_hash:?}")))?;
+        Ok(TransferFromEvents { transfer, approval })
+    }
+
+    /// Live `Transfer` events over a WebSocket subscription to `ws_url`,
+    /// reconnecting whenever it drops, until `cancel` fires. Connection
+    /// failures and undecodable events are yielded as errors, and the stream
+    /// goes on after them.
+    pub fn watch_transfers(
+        &self,
+        ws_url: &str,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = Result<TransferFilter>> + use<M> {
+        crate::stream::events(
+            ws_url.to_owned(),
+            self.address(),
+            |contract| contract.transfer_filter(),
+            cancel,
+        )
+    }
+
+    /// Transfers of at least `threshold` tokens, streamed from `ws_url` until
+    /// `cancel` fires. The amount isn't indexed, so smaller transfers are
+    /// dropped after decoding.
+    pub fn watch_large_transfers(
+        &self,
+        threshold: U256,
+        ws_url: &str,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = Result<TransferFilter>> + use<M> {
+        self.watch_transfers(ws_url, cancel).filter(move |event| {
+            std::future::ready(
+                event
+                    .as_ref()
+                    .map_or(true, |event| event.value >= threshold),
+            )
+        })
+    }
+
+    /// The allowance of `spender` over `owner`'s tokens each time an `Approval`
+    /// for the pair arrives over a WebSocket subscription to `ws_url`, until
+    /// `cancel` fires.
+    pub fn watch_allowance(
+        &self,
+        owner: Address,
+        spender: Address,
+        ws_url: &str,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = Result<U256>> + use<M> {
+        crate::stream::events(
+            ws_url.to_owned(),
+            self.address(),
+            move |contract| contract.approval_filter().topic1(owner).topic2(spender),
+            cancel,
+        )
+        .filter_map(move |event| async move {
+            match event {
+                Ok(event) => {
+                    (event.owner == owner && event.spender == spender).then_some(Ok(event.value))
+                }
+                Err(err) => Some(Err(err)),
+            }
+        })
+    }
+
+    /// `account`'s balance each time it changes, until `cancel` fires,
+    /// subscribing over `url` when it's a WebSocket and otherwise polling it
+    /// every `interval`.
+    pub fn watch_balance(
+        &self,
+        url: &str,
+        account: Address,
+        interval: Duration,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = Result<U256>> + use<M> {
+        match TransportKind::from_url(url) {
+            Ok(TransportKind::Ws) => self
+                .watch_balance_subscribed(url, account, cancel)
+                .left_stream(),
+            _ => self
+                .watch_balance_polling(url, account, interval, cancel)
+                .right_stream(),
+        }
+    }
+
+    /// `account`'s balance, read again whenever a `Transfer` to or from it
+    /// arrives over a WebSocket subscription to `ws_url`, and yielded when it
+    /// changed, until `cancel` fires.
+    pub fn watch_balance_subscribed(
+        &self,
+        ws_url: &str,
+        account: Address,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = Result<U256>> + use<M> {
+        let token = self.at(self.address());
+        let transfers = self.watch_transfers(ws_url, cancel);
+        async_stream::stream! {
+            tokio::pin!(transfers);
+            let mut last = None;
+            while let Some(event) = transfers.next().await {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(err) => {
+                        yield Err(err);
+                        continue;
+                    }
+                };
+                if event.from != account && event.to != account {
+                    continue;
+                }
+                match token.balance_of(account).await {
+                    Ok(balance) if last == Some(balance) => {}
+                    Ok(balance) => {
+                        last = Some(balance);
+                        yield Ok(balance);
+                    }
+                    Err(err) => yield Err(err),
+                }
+            }
+        }
+    }
+
+    /// `account`'s balance each time it changes, reading `balanceOf` over
+    /// `http_url` every `interval` until `cancel` fires, for RPCs without
+    /// subscriptions.
+    ///
+    /// The first read only sets the balance changes are looked for against,
+    /// so nothing is yielded until one happens. A change and its reversal
+    /// between two reads go unseen.
+    pub fn watch_balance_polling(
+        &self,
+        http_url: &str,
+        account: Address,
+        interval: Duration,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = Result<U256>> + use<M> {
+        let url = http_url.to_owned();
+        let address = self.address();
+        async_stream::stream! {
+            let provider = match connect(&url).await {
+                Ok(provider) => provider,
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            };
+            let contract = MyToken::new(address, Arc::new(provider));
+            let mut last = None;
+            loop {
+                match contract.balance_of(account).call().await {
+                    Ok(balance) => {
+                        if last.is_some_and(|last| last != balance) {
+                            yield Ok(balance);
+                        }
+                        last = Some(balance);
+                    }
+                    Err(err) => yield Err(err.into()),
+                }
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(interval) => {}
+                }
+            }
+        }
+    }
+
+    // Decode a string getter's output, retrying as a zero-padded `bytes32` when
+    // it isn't a valid ABI string
+    async fn string_or_bytes32(&self, call: ContractCall<M, String>) -> Result<String> {
+        let output = self
+            .client()
+            .call(&call.tx, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        if let Ok(mut tokens) = abi::decode(&[ParamType::String], &output)
+            && let Some(Token::String(value)) = tokens.pop()
+        {
+            return Ok(value);
+        }
+
+        match abi::decode(&[ParamType::FixedBytes(32)], &output) {
+            Ok(mut tokens) => match tokens.pop() {
+                Some(Token::FixedBytes(bytes)) => {
+                    let end = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+                    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+                }
+                _ => Err(MyTokenError::Decode(call.function.name.clone())),
+            },
+            Err(_) => Err(MyTokenError::Decode(call.function.name.clone())),
+        }
+    }
+
+    pub(crate) async fn block_timestamp(&self, block_number: u64) -> Result<u64> {
+        if let Some(&timestamp) = self.timestamps.lock().unwrap().get(&block_number) {
+            return Ok(timestamp);
+        }
+
+        let block = self
+            .client()
+            .get_block(block_number)
+            .await
+            .map_err(MyTokenError::middleware)?
+            .ok_or(MyTokenError::MissingBlock(block_number))?;
+        let timestamp = block.timestamp.as_u64();
+        self.timestamps.lock().unwrap().put(block_number, timestamp);
+        Ok(timestamp)
+    }
+
+    // Catch transfers the contract would reject (or that do nothing) before
+    // paying gas to find out. Returns a summary when the transfer is skipped.
+    fn validate_transfer(&self, to: Address, amount: U256) -> Result<Option<TxSummary>> {
+        if to.is_zero() {
+            return Err(MyTokenError::InvalidRecipient(to));
+        }
+        if amount.is_zero() {
+            return match self.zero_amount {
+                ZeroAmount::Reject => Err(MyTokenError::ZeroAmount),
+                ZeroAmount::Skip => Ok(Some(TxSummary::skipped())),
+            };
+        }
+        Ok(None)
+    }
+
+    // `send` for a transfer, run between the transfer hooks
+    async fn send_transfer<D: Detokenize>(
+        &self,
+        to: Address,
+        amount: U256,
+        call: ContractCall<M, D>,
+    ) -> Result<TxSummary> {
+        self.send_transfer_noting(to, amount, call, |_| {}).await
+    }
+
+    async fn send_transfer_noting<D: Detokenize>(
+        &self,
+        to: Address,
+        amount: U256,
+        call: ContractCall<M, D>,
+        sent: impl FnOnce(TxHash) + Send,
+    ) -> Result<TxSummary> {
+        for hook in &self.transfer_hooks {
+            hook.before(to, amount);
+        }
+        let summary = self.send_noting(call, sent).await?;
+        for hook in &self.transfer_hooks {
+            hook.after(&summary);
+        }
+        Ok(summary)
+    }
+
+    pub(crate) async fn send<D: Detokenize>(&self, call: ContractCall<M, D>) -> Result<TxSummary> {
+        self.send_noting(call, |_| {}).await
+    }
+
+    // `send`, calling `sent` with the hash once broadcast and before it's
+    // waited for. A dry run broadcasts nothing, so never calls it.
+    async fn send_noting<D: Detokenize>(
+        &self,
+        call: ContractCall<M, D>,
+        sent: impl FnOnce(TxHash) + Send,
+    ) -> Result<TxSummary> {
+        let data = call.tx.data().cloned().unwrap_or_default();
+        let (operation, _) = self.describe_call(&data);
+        observe(&operation, self.broadcast(call, sent)).await
+    }
+
+    async fn broadcast<D: Detokenize>(
+        &self,
+        mut call: ContractCall<M, D>,
+        sent: impl FnOnce(TxHash) + Send,
+    ) -> Result<TxSummary> {
+        if self.dry_run {
+            return self.dry_run(&call.tx).await;
+        }
+        // Pins the nonce too, so a dropped transaction can later be told apart
+        // from one whose nonce went to another transaction
+        self.prepare(&mut call.tx).await?;
+        let tx = call.tx.clone();
+        if let Some(relay) = self.network.relay_url.as_deref() {
+            let (tx_hash, raw) = self.relay(relay, &tx).await?;
+            sent(tx_hash);
+            return self.confirm(tx_hash, Broadcast::Relayed(raw)).await;
+        }
+        let tx_hash = *call.send().await?;
+        sent(tx_hash);
+        self.confirm(tx_hash, Broadcast::Call(Box::new(tx))).await
+    }
+
+    // Wait for a broadcast transaction and summarize it, failing on a revert
+    pub(crate) async fn confirm(&self, tx_hash: TxHash, broadcast: Broadcast) -> Result<TxSummary> {
+        let receipt = self.wait_for_receipt(tx_hash, &broadcast).await?;
+        // Mined, reverted or not, so cached balances may be out of date
+        self.reads.lock().unwrap().clear();
+        let mut summary = TxSummary::from(&receipt);
+        // Only a label, so a node that can't say which chain it's on doesn't
+        // fail a write that was mined
+        summary.chain = self.chain().await.ok().flatten();
+        if let Err(err) = self.record_receipt(&receipt, &summary, &broadcast).await {
+            // The write went through, so failing it here would only get it
+            // sent again
+            tracing::warn!(tx_hash = ?receipt.transaction_hash, "receipt not recorded: {err}");
+        }
+        if summary.status == TxStatus::Reverted {
+            return Err(MyTokenError::Reverted(receipt.transaction_hash));
+        }
+
+        Ok(summary)
+    }
+
+    // Poll until `tx_hash` is mined with enough confirmations, backing off
+    // while nothing changes and polling quickly again after each new block.
+    // A receipt that disappears in a reorg is simply waited for again.
+    async fn poll_receipt(&self, tx_hash: TxHash) -> Result<TransactionReceipt> {
+        let client = self.client();
+        let mut backoff = Backoff::new(
+            self.network.poll_interval(),
+            self.network.max_poll_interval(),
+        );
+        let mut last_block = None;
+
+        loop {
+            let receipt = client
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map_err(MyTokenError::middleware)?;
+            let latest = client
+                .get_block_number()
+                .await
+                .map_err(MyTokenError::middleware)?
+                .as_u64();
+
+            if let Some(receipt) = receipt
+                && let Some(mined) = receipt.block_number
+                && (latest + 1).saturating_sub(mined.as_u64()) >= self.network.confirmations as u64
+            {
+                return Ok(receipt);
+            }
+
+            if last_block.is_some_and(|last| latest > last) {
+                backoff.reset();
+            }
+            last_block = Some(latest);
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    }
+
+    // Wait for `tx_hash` to be mined. When no receipt arrives in time and the
+    // node no longer knows the transaction, it is broadcast once more, unless
+    // its nonce has meanwhile been used by another transaction.
+    async fn wait_for_receipt(
+        &self,
+        mut tx_hash: TxHash,
+        broadcast: &Broadcast,
+    ) -> Result<TransactionReceipt> {
+        let client = self.client();
+        let mut rebroadcast = false;
+
+        loop {
+            let polled = self.poll_receipt(tx_hash);
+            if let Ok(receipt) = tokio::time::timeout(self.network.receipt_timeout(), polled).await
+            {
+                return receipt;
+            }
+
+            let known = match (broadcast, self.network.relay_url.as_deref()) {
+                // A privately relayed transaction never reaches the node's
+                // mempool, so only the relay can tell whether it still has it
+                (Broadcast::Relayed(_), Some(relay)) => self.relay_knows(relay, tx_hash).await,
+                _ => client
+                    .get_transaction(tx_hash)
+                    .await
+                    .map_err(MyTokenError::middleware)?
+                    .is_some(),
+            };
+            if known {
+                // Still pending, just slow to be mined
+                continue;
+            }
+
+            let Some((from, nonce)) = broadcast.origin() else {
+                return Err(MyTokenError::Dropped(tx_hash));
+            };
+            let next_nonce = client
+                .get_transaction_count(from, None)
+                .await
+                .map_err(MyTokenError::middleware)?;
+            if rebroadcast || next_nonce > nonce {
+                return Err(MyTokenError::Dropped(tx_hash));
+            }
+
+            tx_hash = match broadcast {
+                Broadcast::Call(tx) => *client
+                    .send_transaction(*tx.clone(), None)
+                    .await
+                    .map_err(MyTokenError::middleware)?,
+                Broadcast::Raw(raw) => *client
+                    .send_raw_transaction(raw.clone())
+                    .await
+                    .map_err(MyTokenError::middleware)?,
+                // Back to the relay, never the public mempool
+                Broadcast::Relayed(raw) => match self.network.relay_url.as_deref() {
+                    Some(relay) => self.relay_raw(relay, raw.clone()).await?,
+                    None => return Err(MyTokenError::Dropped(tx_hash)),
+                },
+                Broadcast::Untracked => return Err(MyTokenError::Dropped(tx_hash)),
+            };
+            rebroadcast = true;
+        }
+    }
+}
+
+// What was broadcast, kept so it can be sent again if the node drops it
+pub(crate) enum Broadcast {
+    Call(Box<TypedTransaction>),
+    Raw(Bytes),
+    // Signed here and sent to the network's relay
+    Relayed(Bytes),
+    // Sent by an earlier call that kept nothing, so it can't be sent again
+    Untracked,
+}
+
+impl Broadcast {
+    // The sender and nonce of the transaction
+    fn origin(&self) -> Option<(Address, U256)> {
+        match self {
+            Broadcast::Call(tx) => Some((*tx.from()?, *tx.nonce()?)),
+            Broadcast::Raw(raw) | Broadcast::Relayed(raw) => {
+                let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(raw)).ok()?;
+                Some((signature.recover(tx.sighash()).ok()?, *tx.nonce()?))
+            }
+            Broadcast::Untracked => None,
+        }
+    }
+
+    // The transaction's calldata
+    pub(crate) fn data(&self) -> Option<Bytes> {
+        match self {
+            Broadcast::Call(tx) => Some(tx.data().cloned().unwrap_or_default()),
+            Broadcast::Raw(raw) | Broadcast::Relayed(raw) => {
+                let (tx, _) = TypedTransaction::decode_signed(&Rlp::new(raw)).ok()?;
+                Some(tx.data().cloned().unwrap_or_default())
+            }
+            Broadcast::Untracked => None,
+        }
+    }
+}
+
+fn timestamp_cache(size: usize) -> Arc<Mutex<LruCache<u64, u64>>> {
+    let size = NonZeroUsize::new(size).unwrap_or(NonZeroUsize::MIN);
+    Arc::new(Mutex::new(LruCache::new(size)))
+}
+
+// Scanning from genesis is one huge `eth_getLogs` on a real chain, which most
+// nodes refuse or time out on
+fn warn_if_full_history(from_block: u64) {
+    if from_block == 0 {
+        tracing::warn!("querying events over the full chain history, from block 0");
+    }
+}
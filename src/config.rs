@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+
+use crate::{ETHERSCAN_API_URL, MyTokenError, Result, Verification};
+
+pub(crate) const DEFAULT_TIMESTAMP_CACHE_SIZE: usize = 1024;
+const DEFAULT_POLL_INTERVAL_MS: u64 = 250;
+
+/// Client settings, loaded from TOML.
+///
+/// ```toml
+/// timestamp_cache_size = 4096
+/// read_cache_ttl_ms = 2000
+/// receipt_log = "receipts.jsonl"
+///
+/// [networks.1]
+/// tx_type = "eip1559"
+/// gas = { strategy = "oracle", percentile = 60.0 }
+/// confirmations = 3
+///
+/// [networks.31337]
+/// tx_type = "legacy"
+/// gas = { strategy = "fixed", limit = 300000, price = 1000000000 }
+/// poll_interval_ms = 100
+///
+/// [networks.11155111.verify]
+/// etherscan_api_key = "..."
+/// standard_json = "standard-input.json"
+/// compiler_version = "v0.8.24+commit.e11b9ed9"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Per-network defaults keyed by chain id.
+    #[serde(deserialize_with = "chain_id_keys")]
+    pub networks: HashMap<u64, NetworkConfig>,
+    /// How many block timestamps a client keeps for event enrichment.
+    pub timestamp_cache_size: usize,
+    /// How long cached balance and supply reads stay good; none are cached
+    /// if unset.
+    pub read_cache_ttl_ms: Option<u64>,
+    /// JSON-lines file every write is recorded in once mined; nothing is
+    /// recorded if unset.
+    pub receipt_log: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            networks: HashMap::new(),
+            timestamp_cache_size: DEFAULT_TIMESTAMP_CACHE_SIZE,
+            read_cache_ttl_ms: None,
+            receipt_log: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(source: &str) -> Result<Self> {
+        toml::from_str(source).map_err(|err| MyTokenError::Config(err.to_string()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| MyTokenError::Config(format!("{}: {err}", path.display())))?;
+        Self::from_toml(&source)
+    }
+
+    pub fn read_cache_ttl(&self) -> Option<Duration> {
+        self.read_cache_ttl_ms.map(Duration::from_millis)
+    }
+
+    /// Settings for `chain_id`, or the defaults when it has no entry.
+    pub fn network(&self, chain_id: u64) -> NetworkConfig {
+        self.networks.get(&chain_id).cloned().unwrap_or_default()
+    }
+}
+
+// TOML table keys are always strings, so parse the chain ids ourselves
+fn chain_id_keys<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<HashMap<u64, NetworkConfig>, D::Error> {
+    HashMap::<String, NetworkConfig>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, network)| match key.parse() {
+            Ok(chain_id) => Ok((chain_id, network)),
+            Err(_) => Err(D::Error::custom(format!("invalid chain id {key:?}"))),
+        })
+        .collect()
+}
+
+/// How writes are sent and awaited on one network.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub tx_type: TxType,
+    pub gas: GasStrategy,
+    /// First interval to poll for the receipt at, 250 ms if unset. It doubles
+    /// while the transaction is pending, up to `max_poll_interval_ms`, and
+    /// starts over with each new block.
+    pub poll_interval_ms: Option<u64>,
+    /// Longest the receipt poll interval grows to.
+    pub max_poll_interval_ms: u64,
+    /// Blocks to wait for before a write counts as mined.
+    pub confirmations: usize,
+    /// How long to wait for a receipt before checking whether the node dropped
+    /// the transaction.
+    pub receipt_timeout_ms: u64,
+    /// How many recent blocks an event query covers when no start block is
+    /// given.
+    pub log_lookback_blocks: u64,
+    /// Private relay, such as Flashbots Protect, that writes are signed for
+    /// and sent to instead of the node, out of sight of frontrunners. Needs
+    /// a middleware that signs locally. Writes go to the node's mempool when
+    /// unset.
+    pub relay_url: Option<String>,
+    /// Explorer to verify tokens deployed here on, through
+    /// [`TokenDeployer::with_config`](crate::TokenDeployer::with_config).
+    /// Deployments aren't verified when unset.
+    pub verify: Option<VerifyConfig>,
+}
+
+impl NetworkConfig {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS))
+    }
+
+    pub fn max_poll_interval(&self) -> Duration {
+        Duration::from_millis(self.max_poll_interval_ms)
+    }
+
+    pub fn receipt_timeout(&self) -> Duration {
+        Duration::from_millis(self.receipt_timeout_ms)
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            tx_type: TxType::default(),
+            gas: GasStrategy::default(),
+            poll_interval_ms: None,
+            max_poll_interval_ms: 10_000,
+            confirmations: 1,
+            receipt_timeout_ms: 120_000,
+            log_lookback_blocks: 10_000,
+            relay_url: None,
+            verify: None,
+        }
+    }
+}
+
+/// Source verification settings for one network, as a [`Verification`] is
+/// made from them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VerifyConfig {
+    pub etherscan_api_key: String,
+    /// Etherscan's multichain API, [`ETHERSCAN_API_URL`], if unset.
+    pub api_url: Option<String>,
+    /// File holding the Solidity standard JSON input the token was compiled
+    /// from.
+    pub standard_json: PathBuf,
+    pub compiler_version: String,
+}
+
+impl VerifyConfig {
+    /// The verification these settings describe, reading the standard JSON
+    /// input from its file.
+    pub fn verification(&self) -> Result<Verification> {
+        let path = &self.standard_json;
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| MyTokenError::Config(format!("{}: {err}", path.display())))?;
+        let api_url = self.api_url.as_deref().unwrap_or(ETHERSCAN_API_URL);
+        Ok(
+            Verification::new(&self.etherscan_api_key, source, &self.compiler_version)
+                .with_api_url(api_url),
+        )
+    }
+}
+
+/// Transaction type used for writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxType {
+    /// Type 0 transactions with a single gas price.
+    #[default]
+    Legacy,
+    /// EIP-1559 transactions, with a fee cap and a priority fee.
+    Eip1559,
+}
+
+/// How the gas limit and fees of a write are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "strategy", rename_all = "lowercase")]
+pub enum GasStrategy {
+    /// Exactly this limit and price in wei, sent as a legacy transaction
+    /// whatever the [`TxType`].
+    Fixed { limit: u64, price: u64 },
+    /// The node's gas estimate times `multiplier`, at least 1.0, with fees
+    /// left to the middleware.
+    Estimate {
+        #[serde(deserialize_with = "gas_multiplier")]
+        multiplier: f64,
+    },
+    /// An estimated limit, with the priority fee at `percentile` (0 to 100) of
+    /// what recent blocks paid, from `eth_feeHistory`.
+    Oracle { percentile: f64 },
+}
+
+impl Default for GasStrategy {
+    fn default() -> Self {
+        GasStrategy::Estimate { multiplier: 1.2 }
+    }
+}
+
+impl GasStrategy {
+    /// [`Estimate`](Self::Estimate), failing with [`MyTokenError::Config`]
+    /// unless `multiplier` is a finite number of at least 1.0.
+    pub fn estimate(multiplier: f64) -> Result<Self> {
+        let multiplier = check_multiplier(multiplier).map_err(MyTokenError::Config)?;
+        Ok(GasStrategy::Estimate { multiplier })
+    }
+}
+
+// Below 1.0 the limit would be cut under what the node estimated the write to
+// need, so it would run out of gas
+pub(crate) fn check_multiplier(multiplier: f64) -> std::result::Result<f64, String> {
+    if multiplier.is_finite() && multiplier >= 1.0 {
+        Ok(multiplier)
+    } else {
+        Err(format!(
+            "gas multiplier must be a finite number of at least 1.0, not {multiplier}"
+        ))
+    }
+}
+
+fn gas_multiplier<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<f64, D::Error> {
+    check_multiplier(f64::deserialize(deserializer)?).map_err(D::Error::custom)
+}
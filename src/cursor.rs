@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+use ethers::providers::Middleware;
+
+use crate::{EnrichedTransfer, MyTokenClient, MyTokenError, Result};
+
+/// The last block an incremental scan has fully processed, kept in a small
+/// file so the next run carries on from there.
+///
+/// The file holds just the block number in decimal. Updates are written to a
+/// temporary file and renamed over it, so a crash mid-write leaves the old
+/// value rather than a torn one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanCursor {
+    path: PathBuf,
+    last_block: Option<u64>,
+}
+
+impl ScanCursor {
+    /// The cursor stored at `path`, or a fresh one if the file doesn't exist.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let last_block = match std::fs::read_to_string(&path) {
+            Ok(contents) => Some(
+                contents
+                    .trim()
+                    .parse()
+                    .map_err(|err| MyTokenError::Storage(format!("{}: {err}", path.display())))?,
+            ),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(MyTokenError::Storage(format!("{}: {err}", path.display()))),
+        };
+        Ok(Self { path, last_block })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The last block fully processed, `None` before the first scan.
+    pub fn last_block(&self) -> Option<u64> {
+        self.last_block
+    }
+
+    /// Record `block` as fully processed.
+    pub fn advance(&mut self, block: u64) -> Result<()> {
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, block.to_string())
+            .and_then(|()| std::fs::rename(&temp, &self.path))
+            .map_err(|err| MyTokenError::Storage(format!("{}: {err}", self.path.display())))?;
+        self.last_block = Some(block);
+        Ok(())
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// [`enriched_transfers`](Self::enriched_transfers) from where `cursor`
+    /// left off up to the latest block, moving the cursor to that block.
+    ///
+    /// A cursor ahead of the chain, as after restarting a local node, is
+    /// taken to belong to an earlier chain and the scan starts again from
+    /// block 0.
+    pub async fn enriched_transfers_since(
+        &self,
+        cursor: &mut ScanCursor,
+    ) -> Result<Vec<EnrichedTransfer>> {
+        let latest = self
+            .client()
+            .get_block_number()
+            .await
+            .map_err(MyTokenError::middleware)?
+            .as_u64();
+        let from_block = match cursor.last_block() {
+            Some(last) if last < latest => last + 1,
+            Some(last) if last == latest => return Ok(Vec::new()),
+            _ => 0,
+        };
+
+        let transfers = self
+            .enriched_transfers_between(from_block, Some(latest))
+            .await?;
+        cursor.advance(latest)?;
+        Ok(transfers)
+    }
+}
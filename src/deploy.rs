@@ -0,0 +1,177 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ethers::{
+    abi::{self, Abi, Tokenize},
+    contract::ContractFactory,
+    providers::Middleware,
+    types::{Address, Bytes, TxHash},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Config, MyTokenError, Result, Verification};
+
+/// Where `forge build` leaves the token's artifact.
+pub const DEFAULT_ARTIFACT: &str = "out/MyToken.sol/MyToken.json";
+
+/// A deployed token, in the form written to `deployments/<chain id>.json` for
+/// frontends and other tooling.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deployment {
+    pub address: Address,
+    /// The contract ABI, as the JSON array from the build artifact.
+    pub abi: serde_json::Value,
+    pub deployer: Address,
+    pub block: u64,
+    pub tx_hash: TxHash,
+}
+
+impl Deployment {
+    /// Write this deployment to `<dir>/<chain_id>.json`, replacing any earlier
+    /// one for the chain, and return the file's path.
+    pub fn write(&self, dir: impl AsRef<Path>, chain_id: u64) -> Result<PathBuf> {
+        let dir = dir.as_ref();
+        let path = dir.join(format!("{chain_id}.json"));
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| MyTokenError::Artifact(err.to_string()))?;
+        std::fs::create_dir_all(dir)
+            .and_then(|()| std::fs::write(&path, json))
+            .map_err(|err| MyTokenError::Artifact(format!("{}: {err}", path.display())))?;
+        Ok(path)
+    }
+}
+
+/// Deploys `MyToken` from a Foundry build artifact.
+///
+/// ```no_run
+/// # async fn run(client: std::sync::Arc<ethers::providers::Provider<ethers::providers::Http>>) -> my_token::Result<()> {
+/// let deployment = my_token::TokenDeployer::new(client, my_token::DEFAULT_ARTIFACT)
+///     .with_decimals(6)
+///     .with_output_dir("deployments")
+///     .deploy()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TokenDeployer<M> {
+    client: Arc<M>,
+    artifact: PathBuf,
+    owner: Option<Address>,
+    decimals: u8,
+    output_dir: Option<PathBuf>,
+    verification: Option<Verification>,
+}
+
+impl<M: Middleware + 'static> TokenDeployer<M> {
+    pub fn new(client: Arc<M>, artifact: impl Into<PathBuf>) -> Self {
+        Self {
+            client,
+            artifact: artifact.into(),
+            owner: None,
+            decimals: 18,
+            output_dir: None,
+            verification: None,
+        }
+    }
+
+    /// Account that may mint and freeze, the deployer unless set.
+    pub fn with_owner(mut self, owner: Address) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn with_decimals(mut self, decimals: u8) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Also write the deployment to `<dir>/<chain id>.json`.
+    pub fn with_output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(dir.into());
+        self
+    }
+
+    /// Verify the token's source on a block explorer once it's deployed.
+    ///
+    /// The deployment is written first, so when verification fails the
+    /// record is still there to retry from.
+    pub fn with_verification(mut self, verification: Verification) -> Self {
+        self.verification = Some(verification);
+        self
+    }
+
+    /// Verify the token as the `config` entry for the connected chain says, if
+    /// it has a `verify` table.
+    pub async fn with_config(self, config: &Config) -> Result<Self> {
+        let chain_id = self
+            .client
+            .get_chainid()
+            .await
+            .map_err(MyTokenError::middleware)?
+            .as_u64();
+        match &config.network(chain_id).verify {
+            Some(verify) => Ok(self.with_verification(verify.verification()?)),
+            None => Ok(self),
+        }
+    }
+
+    pub async fn deploy(self) -> Result<Deployment> {
+        let deployer = self.client.default_sender().ok_or(MyTokenError::NoSigner)?;
+        let (abi, bytecode) = self.load_artifact()?;
+        let parsed: Abi = serde_json::from_value(abi.clone())
+            .map_err(|err| MyTokenError::Artifact(format!("{}: {err}", self.artifact.display())))?;
+
+        let factory = ContractFactory::new(parsed, bytecode, self.client.clone());
+        let owner = self.owner.unwrap_or(deployer);
+        let constructor_args = (owner, self.decimals);
+        let (contract, receipt) = factory
+            .deploy(constructor_args)?
+            .send_with_receipt()
+            .await?;
+
+        let deployment = Deployment {
+            address: contract.address(),
+            abi,
+            deployer,
+            block: receipt.block_number.unwrap_or_default().as_u64(),
+            tx_hash: receipt.transaction_hash,
+        };
+        if self.output_dir.is_none() && self.verification.is_none() {
+            return Ok(deployment);
+        }
+
+        let chain_id = self
+            .client
+            .get_chainid()
+            .await
+            .map_err(MyTokenError::middleware)?
+            .as_u64();
+        if let Some(dir) = &self.output_dir {
+            deployment.write(dir, chain_id)?;
+        }
+        if let Some(verification) = &self.verification {
+            let encoded = abi::encode(&constructor_args.into_tokens());
+            verification
+                .verify(chain_id, deployment.address, &encoded)
+                .await?;
+        }
+        Ok(deployment)
+    }
+
+    fn load_artifact(&self) -> Result<(serde_json::Value, Bytes)> {
+        let path = self.artifact.display();
+        let source = std::fs::read_to_string(&self.artifact).map_err(|err| {
+            MyTokenError::Artifact(format!("{path}: {err}, run `forge build` first"))
+        })?;
+        let mut json: serde_json::Value = serde_json::from_str(&source)
+            .map_err(|err| MyTokenError::Artifact(format!("{path}: {err}")))?;
+
+        let bytecode = json["bytecode"]["object"]
+            .as_str()
+            .and_then(|hex| hex.parse::<Bytes>().ok())
+            .ok_or_else(|| MyTokenError::Artifact(format!("{path} has no bytecode")))?;
+        Ok((json["abi"].take(), bytecode))
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+use ethers::{
+    providers::Middleware,
+    types::{TxHash, U256, transaction::eip2718::TypedTransaction},
+};
+
+use crate::{MyTokenClient, MyTokenError, Result, TxSummary, checksum};
+
+/// A write a dry-run client simulated instead of sending, as listed by
+/// [`simulated_txs`](MyTokenClient::simulated_txs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedTx {
+    /// Hash of the unsigned transaction, which identifies it in the
+    /// [`TxSummary`] and to [`TxSender::wait`](crate::TxSender::wait). Nothing
+    /// with this hash was broadcast.
+    pub tx_hash: TxHash,
+    pub tx: TypedTransaction,
+    /// What it would have done, such as `transfer(to=0x…, amount=5) on 0x…`.
+    pub description: String,
+}
+
+impl fmt::Display for SimulatedTx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "would {}", self.description)
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    // Run `tx` with `eth_call` instead of broadcasting it, recording what
+    // would have been sent. Fails as the write would, should it revert.
+    pub(crate) async fn dry_run(&self, tx: &TypedTransaction) -> Result<TxSummary> {
+        self.client()
+            .call(tx, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        let to = tx
+            .to_addr()
+            .map_or_else(|| "nobody".to_owned(), |to| checksum(*to));
+        let data = tx.data().cloned().unwrap_or_default();
+        let value = tx.value().copied().unwrap_or_else(U256::zero);
+        let description = if data.is_empty() {
+            format!("send {value} wei to {to}")
+        } else {
+            let (operation, args) = self.describe_call(&data);
+            let args: Vec<String> = args
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+            format!("call {operation}({}) on {to}", args.join(", "))
+        };
+        let simulated = SimulatedTx {
+            tx_hash: tx.sighash(),
+            tx: tx.clone(),
+            description,
+        };
+        tracing::info!(tx_hash = ?simulated.tx_hash, "dry run: {simulated}");
+
+        let summary = TxSummary {
+            tx_hash: simulated.tx_hash,
+            ..TxSummary::simulated()
+        };
+        self.simulated().lock().unwrap().push(simulated);
+        Ok(summary)
+    }
+
+    /// Every write this client has simulated in dry-run mode, oldest first.
+    /// Clients made with [`at`](Self::at) share the list.
+    pub fn simulated_txs(&self) -> Vec<SimulatedTx> {
+        self.simulated().lock().unwrap().clone()
+    }
+
+    // The summary of a write `dry_run` simulated, if `tx_hash` is one
+    pub(crate) fn simulated_summary(&self, tx_hash: TxHash) -> Option<TxSummary> {
+        let simulated = self.simulated().lock().unwrap();
+        simulated
+            .iter()
+            .any(|simulated| simulated.tx_hash == tx_hash)
+            .then(|| TxSummary {
+                tx_hash,
+                ..TxSummary::simulated()
+            })
+    }
+}
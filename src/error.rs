@@ -0,0 +1,280 @@
+use crate::{MyTokenErrors, checksum};
+use ethers::{
+    contract::{ContractError, ContractRevert, MulticallError},
+    providers::{JsonRpcError, Middleware, MiddlewareError, ProviderError},
+    types::{Address, H256, TxHash, U256},
+};
+
+pub type Result<T, E = MyTokenError> = std::result::Result<T, E>;
+
+/// Errors returned by [`MyTokenClient`](crate::MyTokenClient).
+#[derive(Debug, thiserror::Error)]
+pub enum MyTokenError {
+    #[error("contract call failed: {0}")]
+    Contract(String),
+
+    #[error("invalid config: {0}")]
+    Config(String),
+
+    #[error("index storage failed: {0}")]
+    Storage(String),
+
+    #[error("deployment artifact: {0}")]
+    Artifact(String),
+
+    #[error("source verification failed: {0}")]
+    Verification(String),
+
+    #[error(transparent)]
+    Provider(ProviderError),
+
+    #[error("could not decode the output of {0}")]
+    Decode(String),
+
+    #[error("middleware error: {0}")]
+    Middleware(String),
+
+    #[error("block {0} not found")]
+    MissingBlock(u64),
+
+    #[error("no contract deployed at {}", checksum(*.0))]
+    NoContract(Address),
+
+    #[error("invalid recipient {}", checksum(*.0))]
+    InvalidRecipient(Address),
+
+    #[error("refusing to transfer a zero amount")]
+    ZeroAmount,
+
+    #[error("{0} basis points is more than the whole balance")]
+    InvalidBps(u16),
+
+    #[error("invalid token amount {0:?}")]
+    InvalidAmount(String),
+
+    #[error("amount does not fit in 256 bits")]
+    AmountOverflow,
+
+    #[error("token reports {0} decimals, more than the 77 a 256-bit amount can carry")]
+    UnsupportedDecimals(u8),
+
+    #[error("allowance of {} is still {remaining} after revoking", checksum(*.spender))]
+    NotRevoked { spender: Address, remaining: U256 },
+
+    #[error("token call returned false")]
+    ReturnedFalse,
+
+    #[error("signing failed: {0}")]
+    Signing(String),
+
+    #[error("client has no signer to send transactions from")]
+    NoSigner,
+
+    #[error("refusing to run on chain {0}, only on a local test chain")]
+    NotTestChain(u64),
+
+    #[error("no receipt returned for transaction {0:?}")]
+    NoReceipt(TxHash),
+
+    #[error("transaction {0:?} reverted")]
+    Reverted(TxHash),
+
+    #[error("user operation {0:?} was included, but its call reverted")]
+    UserOpFailed(H256),
+
+    #[error("transaction {0:?} was dropped from the mempool and never mined")]
+    Dropped(TxHash),
+
+    #[error("the sender can't pay for gas plus value; fund the account with more ETH")]
+    InsufficientFunds,
+
+    #[error(
+        "nonce already used by a mined transaction; send again to use the account's next nonce"
+    )]
+    NonceTooLow,
+
+    #[error(
+        "a pending transaction with this nonce pays a higher fee; raise the gas price by at least 10% to replace it"
+    )]
+    ReplacementUnderpriced,
+
+    #[error("execution reverted: {}", .0.as_deref().unwrap_or("no reason given"))]
+    ExecutionReverted(Option<String>),
+
+    #[error("balance of {available} is less than the {needed} needed")]
+    InsufficientBalance { available: U256, needed: U256 },
+
+    #[error("account {} is frozen", checksum(*.0))]
+    AccountFrozen(Address),
+
+    #[error(
+        "allowance of {} from {} expired at {expiry}",
+        checksum(*.spender),
+        checksum(*.owner)
+    )]
+    AllowanceExpired {
+        owner: Address,
+        spender: Address,
+        expiry: U256,
+    },
+
+    #[error("{0} basis points is more than the highest transfer fee allowed")]
+    TransferFeeTooHigh(u16),
+
+    #[error("recipient would receive {received}, less than the minimum of {min_received}")]
+    BelowMinimumReceived { received: U256, min_received: U256 },
+
+    #[error("could not send ETH to {}", checksum(*.0))]
+    EthTransferFailed(Address),
+
+    #[error("cap of {cap} would be below the total supply of {supply}")]
+    CapBelowSupply { cap: U256, supply: U256 },
+
+    #[error("the cap change can't be executed before {0}")]
+    CapChangeNotReady(U256),
+
+    #[error("no cap change has been proposed")]
+    NoPendingCapChange,
+
+    #[error("batch has {recipients} recipients but {amounts} amounts")]
+    BatchLengthMismatch { recipients: U256, amounts: U256 },
+
+    #[error(
+        "balance of {} at block {block} is {primary} on the primary node but {secondary} on the secondary",
+        checksum(*.account)
+    )]
+    InconsistentState {
+        account: Address,
+        block: u64,
+        primary: U256,
+        secondary: U256,
+    },
+
+    #[error("transaction {index} of the batch ({tx_hash:?}) failed: {source}")]
+    Batch {
+        index: usize,
+        tx_hash: TxHash,
+        source: Box<MyTokenError>,
+    },
+}
+
+impl MyTokenError {
+    /// Wrap an error from a middleware stack, whose concrete type is generic.
+    pub(crate) fn middleware(err: impl MiddlewareError) -> Self {
+        err.as_error_response()
+            .and_then(Self::from_rpc)
+            .unwrap_or_else(|| MyTokenError::Middleware(err.to_string()))
+    }
+
+    pub(crate) fn storage(err: impl std::fmt::Display) -> Self {
+        MyTokenError::Storage(err.to_string())
+    }
+
+    // Whether the call reverted, with a reason string or a custom error
+    pub(crate) fn is_revert(&self) -> bool {
+        matches!(
+            self,
+            MyTokenError::ExecutionReverted(_)
+                | MyTokenError::InsufficientBalance { .. }
+                | MyTokenError::AccountFrozen(_)
+                | MyTokenError::AllowanceExpired { .. }
+                | MyTokenError::BatchLengthMismatch { .. }
+                | MyTokenError::TransferFeeTooHigh(_)
+                | MyTokenError::EthTransferFailed(_)
+                | MyTokenError::CapBelowSupply { .. }
+                | MyTokenError::CapChangeNotReady(_)
+                | MyTokenError::NoPendingCapChange
+        )
+    }
+}
+
+impl MyTokenError {
+    // The failures a caller can do something about, recognised by the message
+    // text since nodes mostly share the one -32000 code between them
+    fn from_rpc(err: &JsonRpcError) -> Option<Self> {
+        let message = err.message.to_lowercase();
+        if message.contains("insufficient funds") {
+            Some(MyTokenError::InsufficientFunds)
+        } else if message.contains("nonce too low") {
+            Some(MyTokenError::NonceTooLow)
+        } else if message.contains("underpriced") {
+            Some(MyTokenError::ReplacementUnderpriced)
+        } else if message.contains("execution reverted") {
+            err.as_revert_data()
+                .and_then(|data| Self::from_revert(&data))
+                .or_else(|| {
+                    let (_, reason) = err.message.split_once("execution reverted: ")?;
+                    Some(MyTokenError::ExecutionReverted(Some(reason.to_owned())))
+                })
+                .or(Some(MyTokenError::ExecutionReverted(None)))
+        } else {
+            None
+        }
+    }
+
+    // A revert reason string, or one of the token's custom errors with its
+    // fields
+    fn from_revert(data: &[u8]) -> Option<Self> {
+        let decoded = match MyTokenErrors::decode_with_selector(data)? {
+            MyTokenErrors::InsufficientBalance(err) => MyTokenError::InsufficientBalance {
+                available: err.available,
+                needed: err.needed,
+            },
+            MyTokenErrors::AccountFrozen(err) => MyTokenError::AccountFrozen(err.account),
+            MyTokenErrors::AllowanceExpired(err) => MyTokenError::AllowanceExpired {
+                owner: err.owner,
+                spender: err.spender,
+                expiry: err.expiry,
+            },
+            MyTokenErrors::BatchLengthMismatch(err) => MyTokenError::BatchLengthMismatch {
+                recipients: err.recipients,
+                amounts: err.amounts,
+            },
+            MyTokenErrors::TransferFeeTooHigh(err) => MyTokenError::TransferFeeTooHigh(err.bps),
+            MyTokenErrors::EthTransferFailed(err) => MyTokenError::EthTransferFailed(err.to),
+            MyTokenErrors::CapBelowSupply(err) => MyTokenError::CapBelowSupply {
+                cap: err.cap,
+                supply: err.supply,
+            },
+            MyTokenErrors::CapChangeNotReady(err) => {
+                MyTokenError::CapChangeNotReady(err.executable_at)
+            }
+            MyTokenErrors::NoPendingCapChange(_) => MyTokenError::NoPendingCapChange,
+            MyTokenErrors::InvalidTransferReceiver(err) => {
+                MyTokenError::InvalidRecipient(err.receiver)
+            }
+            MyTokenErrors::RevertString(reason) => MyTokenError::ExecutionReverted(Some(reason)),
+        };
+        Some(decoded)
+    }
+}
+
+impl From<ProviderError> for MyTokenError {
+    fn from(err: ProviderError) -> Self {
+        err.as_error_response()
+            .and_then(Self::from_rpc)
+            .unwrap_or(MyTokenError::Provider(err))
+    }
+}
+
+impl<M: Middleware> From<ContractError<M>> for MyTokenError {
+    fn from(err: ContractError<M>) -> Self {
+        let response = match &err {
+            ContractError::Revert(data) => {
+                return Self::from_revert(data).unwrap_or(MyTokenError::ExecutionReverted(None));
+            }
+            ContractError::MiddlewareError { e } => e.as_error_response(),
+            ContractError::ProviderError { e } => e.as_error_response(),
+            _ => None,
+        };
+        response
+            .and_then(Self::from_rpc)
+            .unwrap_or_else(|| MyTokenError::Contract(err.to_string()))
+    }
+}
+
+impl<M: Middleware> From<MulticallError<M>> for MyTokenError {
+    fn from(err: MulticallError<M>) -> Self {
+        MyTokenError::Contract(err.to_string())
+    }
+}
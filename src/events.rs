@@ -0,0 +1,74 @@
+use crate::{ApprovalFilter, Result, TransferFilter, checksum, from_token_units};
+use ethers::{
+    abi::RawLog,
+    contract::EthEvent,
+    types::{Address, H256, Log, TxHash, U256},
+};
+
+/// Topic 0 of every `Transfer` event: the keccak-256 hash of
+/// `Transfer(address,address,uint256)`.
+pub const TRANSFER_TOPIC: H256 = H256([
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+]);
+
+/// A decoded `Transfer` event together with where and when it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnrichedTransfer {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub block_number: u64,
+    /// Tells the block apart from others at the same height on forks.
+    pub block_hash: H256,
+    pub tx_hash: TxHash,
+    pub log_index: U256,
+    /// Unix timestamp of the block, in seconds.
+    pub timestamp: u64,
+}
+
+/// Whether a supply change created or destroyed tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupplyChangeKind {
+    Mint,
+    Burn,
+}
+
+/// A mint or burn, recovered from a `Transfer` from or to the zero address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupplyChange {
+    pub kind: SupplyChangeKind,
+    /// Recipient of a mint, or holder whose tokens were burned.
+    pub account: Address,
+    pub amount: U256,
+    pub block: u64,
+}
+
+/// The events emitted by a single `transferFrom`: the move itself and the
+/// allowance left afterwards. `approval` is `None` when the allowance was
+/// unlimited and so left untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferFromEvents {
+    pub transfer: TransferFilter,
+    pub approval: Option<ApprovalFilter>,
+}
+
+/// One-line rendering of a transfer, `from -> to: amount`, in human units.
+pub fn format_transfer(event: &TransferFilter, decimals: u8) -> Result<String> {
+    Ok(format!(
+        "{} -> {}: {}",
+        checksum(event.from),
+        checksum(event.to),
+        from_token_units(event.value, decimals)?
+    ))
+}
+
+/// Decode a raw log from any ERC-20 contract as a `Transfer`, or `None` if it
+/// isn't one: another event, or a `Transfer` with other indexed fields, like
+/// ERC-721's.
+pub fn decode_transfer_log(log: &Log) -> Option<TransferFilter> {
+    if log.topics.first() != Some(&TRANSFER_TOPIC) {
+        return None;
+    }
+    TransferFilter::decode_log(&RawLog::from(log.clone())).ok()
+}
@@ -0,0 +1,63 @@
+use crate::{MyTokenClient, MyTokenError, Result, TxSummary, client::Broadcast};
+use ethers::{
+    providers::Middleware,
+    types::{Address, TransactionRequest, U256},
+};
+
+/// Chain id of Anvil and Hardhat, the only chain a [`Faucet`] will drip on.
+pub const TEST_CHAIN_ID: u64 = 31337;
+
+/// Tops up test accounts with ETH and tokens from the client's signer,
+/// normally the deployer.
+pub struct Faucet<M> {
+    token: MyTokenClient<M>,
+}
+
+impl<M: Middleware + 'static> Faucet<M> {
+    pub fn new(token: MyTokenClient<M>) -> Self {
+        Self { token }
+    }
+
+    /// Send `eth` wei and `tokens` raw token units to `to`, skipping whichever
+    /// is zero. Tokens are minted when the signer owns the token, so the
+    /// deployer's balance isn't drained, and transferred otherwise.
+    ///
+    /// Fails with [`MyTokenError::NotTestChain`] anywhere but chain 31337.
+    pub async fn drip(&self, to: Address, eth: U256, tokens: U256) -> Result<Vec<TxSummary>> {
+        let chain_id = self.token.warm_up().await?.as_u64();
+        if chain_id != TEST_CHAIN_ID {
+            return Err(MyTokenError::NotTestChain(chain_id));
+        }
+
+        let mut summaries = Vec::new();
+        if !eth.is_zero() {
+            let mut tx = TransactionRequest::new().to(to).value(eth).into();
+            if self.token.is_dry_run() {
+                summaries.push(self.token.dry_run(&tx).await?);
+            } else {
+                self.token.prepare(&mut tx).await?;
+                let tx_hash = *self
+                    .token
+                    .client()
+                    .send_transaction(tx.clone(), None)
+                    .await
+                    .map_err(MyTokenError::middleware)?;
+                // Waited for like any other write, so a revert or drop fails
+                // the drip and the receipt is recorded
+                let broadcast = Broadcast::Call(Box::new(tx));
+                summaries.push(self.token.confirm(tx_hash, broadcast).await?);
+            }
+        }
+
+        if !tokens.is_zero() {
+            let summary = if self.token.owner().await? == self.token.sender()? {
+                self.token.mint(to, tokens).await?
+            } else {
+                self.token.transfer(to, tokens).await?
+            };
+            summaries.push(summary);
+        }
+
+        Ok(summaries)
+    }
+}
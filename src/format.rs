@@ -0,0 +1,6 @@
+use ethers::{types::Address, utils::to_checksum};
+
+/// EIP-55 mixed-case form of `address`, the form to show users.
+pub fn checksum(address: Address) -> String {
+    to_checksum(&address, None)
+}
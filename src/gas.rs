@@ -0,0 +1,83 @@
+use crate::{GasStrategy, MyTokenClient, MyTokenError, Result, TxType, config::check_multiplier};
+use ethers::{
+    providers::Middleware,
+    types::{BlockNumber, U256, transaction::eip2718::TypedTransaction},
+};
+
+/// Blocks of fee history the oracle strategy averages over.
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    // Fill in `tx` the way every write is sent: the network's transaction
+    // type, gas per its gas strategy, and whatever else the middleware fills,
+    // such as the nonce
+    pub(crate) async fn prepare(&self, tx: &mut TypedTransaction) -> Result<()> {
+        let strategy = self.network().gas;
+        let legacy = self.network().tx_type == TxType::Legacy
+            || matches!(strategy, GasStrategy::Fixed { .. });
+        if let (true, TypedTransaction::Eip1559(inner)) = (legacy, &*tx) {
+            *tx = TypedTransaction::Legacy(inner.clone().into());
+        }
+
+        let estimated = tx.gas().is_none();
+        match strategy {
+            GasStrategy::Fixed { limit, price } => {
+                tx.set_gas(limit);
+                tx.set_gas_price(price);
+            }
+            GasStrategy::Estimate { .. } => {}
+            GasStrategy::Oracle { percentile } => {
+                let (base_fee, priority_fee) = self.oracle_fees(percentile).await?;
+                match tx {
+                    TypedTransaction::Eip1559(inner) => {
+                        // Room for the base fee to double before this is mined
+                        inner.max_fee_per_gas = Some(base_fee * 2 + priority_fee);
+                        inner.max_priority_fee_per_gas = Some(priority_fee);
+                    }
+                    _ => {
+                        tx.set_gas_price(base_fee + priority_fee);
+                    }
+                }
+            }
+        }
+
+        let client = self.client();
+        client
+            .fill_transaction(tx, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        if let (true, GasStrategy::Estimate { multiplier }, Some(gas)) =
+            (estimated, strategy, tx.gas().copied())
+        {
+            // Checked here too, as the variant can be built without `estimate`
+            let multiplier = check_multiplier(multiplier).map_err(MyTokenError::Config)?;
+            // In hundredths, as U256 has no floating point
+            let hundredths = U256::from((multiplier * 100.0).round() as u64);
+            tx.set_gas(gas * hundredths / 100);
+        }
+        Ok(())
+    }
+
+    // The next block's base fee, and the priority fee at `percentile` averaged
+    // over the last few blocks
+    async fn oracle_fees(&self, percentile: f64) -> Result<(U256, U256)> {
+        let client = self.client();
+        let history = client
+            .fee_history(FEE_HISTORY_BLOCKS, BlockNumber::Latest, &[percentile])
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        let base_fee = history.base_fee_per_gas.last().copied().unwrap_or_default();
+        let rewards: Vec<U256> = history
+            .reward
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        let priority_fee = match rewards.len() {
+            0 => U256::zero(),
+            blocks => rewards.into_iter().fold(U256::zero(), |sum, fee| sum + fee) / blocks,
+        };
+        Ok((base_fee, priority_fee))
+    }
+}
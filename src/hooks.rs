@@ -0,0 +1,21 @@
+use ethers::types::{Address, U256};
+
+use crate::TxSummary;
+
+/// Side effects to run around every transfer a [`MyTokenClient`] sends, such
+/// as metrics or notifications, registered with
+/// [`with_transfer_hook`](crate::MyTokenClient::with_transfer_hook).
+///
+/// Hooks cover `transfer`, `transfer_from` and `safe_transfer`, and the
+/// helpers built on them. Transfers the client skips or rejects before sending
+/// run neither callback.
+///
+/// [`MyTokenClient`]: crate::MyTokenClient
+pub trait TransferHook: Send + Sync {
+    /// Called just before the transfer is sent.
+    fn before(&self, to: Address, amount: U256);
+
+    /// Called once the transfer is mined successfully. Not called when
+    /// sending fails or the transfer reverts.
+    fn after(&self, summary: &TxSummary);
+}
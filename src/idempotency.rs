@@ -0,0 +1,137 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, TxHash, U256},
+};
+
+use crate::{MyTokenClient, Result, TxStatus, TxSummary, client::Broadcast};
+
+/// What an [`IdempotencyStore`] holds for a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyedTransfer {
+    /// Broadcast, but not yet seen mined, so a repeat waits for this hash
+    Sent(TxHash),
+    Mined(TxSummary),
+}
+
+/// Where [`MyTokenClient::transfer_with_key`] remembers the transfers it has
+/// already sent, so a retried request returns the original summary.
+///
+/// A key is stored as soon as its transfer is broadcast and again once it's
+/// mined. Implement it over a database to have keys survive restarts or be
+/// shared between processes.
+pub trait IdempotencyStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<KeyedTransfer>;
+    fn insert(&self, key: &str, transfer: KeyedTransfer);
+}
+
+/// An [`IdempotencyStore`] that lives as long as the process, the default.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    sent: Mutex<HashMap<String, KeyedTransfer>>,
+}
+
+impl IdempotencyStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<KeyedTransfer> {
+        self.sent.lock().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: &str, transfer: KeyedTransfer) {
+        self.sent.lock().unwrap().insert(key.to_owned(), transfer);
+    }
+}
+
+type KeyLock = Arc<tokio::sync::Mutex<()>>;
+
+#[derive(Clone)]
+pub(crate) struct Idempotency {
+    store: Arc<dyn IdempotencyStore>,
+    // One lock per key in use, held from lookup to record so two concurrent
+    // retries can't both send, without holding up other keys
+    sending: Arc<Mutex<HashMap<String, KeyLock>>>,
+}
+
+impl Idempotency {
+    pub(crate) fn new(store: Arc<dyn IdempotencyStore>) -> Self {
+        Self {
+            store,
+            sending: Arc::default(),
+        }
+    }
+
+    fn lock_for(&self, key: &str) -> KeyLock {
+        let mut sending = self.sending.lock().unwrap();
+        sending.entry(key.to_owned()).or_default().clone()
+    }
+
+    // Forget `key`'s lock once no other call is waiting on it
+    fn release(&self, key: &str, lock: KeyLock) {
+        let mut sending = self.sending.lock().unwrap();
+        // Ours and the map's
+        if Arc::strong_count(&lock) == 2 {
+            sending.remove(key);
+        }
+    }
+}
+
+impl Default for Idempotency {
+    fn default() -> Self {
+        Self::new(Arc::new(MemoryStore::default()))
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Like [`transfer`](Self::transfer), but a repeat of an earlier `key`
+    /// returns that transfer's summary instead of sending again. If the
+    /// earlier call was broadcast but failed before it was mined, the repeat
+    /// waits for that transaction, so a repeat of one that reverted or was
+    /// dropped fails the same way.
+    pub async fn transfer_with_key(
+        &self,
+        key: &str,
+        to: Address,
+        amount: U256,
+    ) -> Result<TxSummary> {
+        let idempotency = self.idempotency();
+        let lock = idempotency.lock_for(key);
+        let sending = lock.lock().await;
+        let result = self.transfer_keyed(idempotency, key, to, amount).await;
+        drop(sending);
+        idempotency.release(key, lock);
+        result
+    }
+
+    async fn transfer_keyed(
+        &self,
+        idempotency: &Idempotency,
+        key: &str,
+        to: Address,
+        amount: U256,
+    ) -> Result<TxSummary> {
+        let store = &idempotency.store;
+        let summary = match store.get(key) {
+            Some(KeyedTransfer::Mined(summary)) => return Ok(summary),
+            Some(KeyedTransfer::Sent(tx_hash)) => {
+                self.confirm(tx_hash, Broadcast::Untracked).await?
+            }
+            None => {
+                let summary = self
+                    .transfer_noting(to, amount, |tx_hash| {
+                        store.insert(key, KeyedTransfer::Sent(tx_hash))
+                    })
+                    .await?;
+                // A dry run sent nothing, so the key is still unused
+                if summary.status == TxStatus::Simulated {
+                    return Ok(summary);
+                }
+                summary
+            }
+        };
+        store.insert(key, KeyedTransfer::Mined(summary.clone()));
+        Ok(summary)
+    }
+}
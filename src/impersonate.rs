@@ -0,0 +1,68 @@
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::Address,
+};
+
+use crate::{MyTokenError, Result};
+
+/// A provider sending every transaction as an account whose key it doesn't
+/// have, made by [`impersonate`]. Only nodes that allow impersonation, such
+/// as Anvil, accept its transactions.
+///
+/// Dropping it asks the node to stop impersonating the account. That request
+/// is sent from a spawned task, so it needs a Tokio runtime and isn't
+/// awaited; outside one the node keeps impersonating.
+#[derive(Debug)]
+pub struct Impersonated<P: JsonRpcClient + Clone + 'static> {
+    provider: Provider<P>,
+    who: Address,
+}
+
+/// Have the node behind `provider` accept unsigned transactions from `who`,
+/// e.g. a large holder on a mainnet fork, and return a middleware sending as
+/// them. The account still needs ETH for gas.
+pub async fn impersonate<P: JsonRpcClient + Clone + 'static>(
+    provider: &Provider<P>,
+    who: Address,
+) -> Result<Impersonated<P>> {
+    provider
+        .request::<_, ()>("anvil_impersonateAccount", [who])
+        .await
+        .map_err(MyTokenError::middleware)?;
+    Ok(Impersonated {
+        provider: provider.clone().with_sender(who),
+        who,
+    })
+}
+
+impl<P: JsonRpcClient + Clone + 'static> Impersonated<P> {
+    /// The account transactions are sent as.
+    pub fn address(&self) -> Address {
+        self.who
+    }
+}
+
+impl<P: JsonRpcClient + Clone + 'static> Middleware for Impersonated<P> {
+    type Error = ProviderError;
+    type Provider = P;
+    type Inner = Provider<P>;
+
+    fn inner(&self) -> &Provider<P> {
+        &self.provider
+    }
+}
+
+impl<P: JsonRpcClient + Clone + 'static> Drop for Impersonated<P> {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let provider = self.provider.clone();
+        let who = self.who;
+        runtime.spawn(async move {
+            let _ = provider
+                .request::<_, ()>("anvil_stopImpersonatingAccount", [who])
+                .await;
+        });
+    }
+}
@@ -0,0 +1,479 @@
+use crate::{EnrichedTransfer, MyTokenClient, MyTokenError, Result};
+use ethers::{
+    contract::Multicall,
+    providers::Middleware,
+    types::{Address, Block, H256, U256},
+};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// How often [`Indexer::run`] looks for new blocks by default.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Holders whose balances `Indexer::reconcile` reads in one multicall, few
+// enough to stay well inside a node's gas limit for `eth_call`
+const RECONCILE_BATCH_SIZE: usize = 500;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS transfers (
+        block_number INTEGER NOT NULL,
+        log_index INTEGER NOT NULL,
+        tx_hash TEXT NOT NULL,
+        sender TEXT NOT NULL,
+        recipient TEXT NOT NULL,
+        value TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        block_hash TEXT NOT NULL,
+        PRIMARY KEY (block_number, log_index)
+    );
+    CREATE TABLE IF NOT EXISTS checkpoint (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        last_block INTEGER NOT NULL,
+        last_hash TEXT NOT NULL
+    );
+";
+
+// Everything indexed, dropped by `Indexer::reindex` to start over. Tables
+// written before block hashes were stored go this way rather than being
+// migrated.
+const DROP_TABLES: &str = "
+    DROP TABLE IF EXISTS transfers;
+    DROP TABLE IF EXISTS checkpoint;
+";
+
+/// What [`Indexer::reconcile`] found comparing the index to the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconcileReport {
+    /// The checkpoint block, which balances were compared at.
+    pub block: u64,
+    /// Accounts the indexed transfers move tokens to or from.
+    pub holders: usize,
+    /// Holders whose indexed balance isn't what the token reports, in
+    /// address order.
+    pub mismatches: Vec<BalanceMismatch>,
+}
+
+impl ReconcileReport {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// A holder whose balance recomputed from the index disagrees with
+/// `balanceOf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceMismatch {
+    pub account: Address,
+    /// Tokens in less tokens out over the indexed transfers, or zero when
+    /// more went out than came in.
+    pub indexed: U256,
+    pub on_chain: U256,
+}
+
+/// Copies the token's `Transfer` history into a SQLite database and keeps it
+/// up to date.
+///
+/// Each batch of transfers is committed together with the last block it
+/// covers, so a stopped or crashed indexer picks up where it left off without
+/// gaps or duplicates.
+///
+/// Rows keep the hash of their block, and the checkpoint that of the last
+/// block indexed. When the chain no longer has that block, it has reorged:
+/// transfers from blocks that left the canonical chain are rolled back and
+/// indexing resumes from the newest block still on it.
+pub struct Indexer<M> {
+    token: MyTokenClient<M>,
+    db: Mutex<Connection>,
+    start_block: u64,
+    poll_interval: Duration,
+}
+
+impl<M: Middleware + 'static> Indexer<M> {
+    /// Indexer writing to the database at `path`, created if missing.
+    ///
+    /// A database from before block hashes were indexed is refused with
+    /// [`MyTokenError::Storage`], as it can't tell a reorg apart. Open it with
+    /// [`reindex`](Self::reindex) to index it again from the start.
+    pub fn open(token: MyTokenClient<M>, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let db = Connection::open(path).map_err(MyTokenError::storage)?;
+        if is_unhashed(&db)? {
+            return Err(MyTokenError::Storage(format!(
+                "{} was indexed without block hashes; reindex it to keep using it",
+                path.display()
+            )));
+        }
+        Self::with_schema(token, db)
+    }
+
+    /// Indexer writing to the database at `path`, throwing away whatever it
+    /// holds so indexing starts over from the start block.
+    pub fn reindex(token: MyTokenClient<M>, path: impl AsRef<Path>) -> Result<Self> {
+        let db = Connection::open(path).map_err(MyTokenError::storage)?;
+        db.execute_batch(DROP_TABLES)
+            .map_err(MyTokenError::storage)?;
+        Self::with_schema(token, db)
+    }
+
+    fn with_schema(token: MyTokenClient<M>, db: Connection) -> Result<Self> {
+        db.execute_batch(SCHEMA).map_err(MyTokenError::storage)?;
+        Ok(Self {
+            token,
+            db: Mutex::new(db),
+            start_block: 0,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        })
+    }
+
+    /// Where to begin when the database has no checkpoint yet, block 0 by
+    /// default.
+    pub fn with_start_block(mut self, block: u64) -> Self {
+        self.start_block = block;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// The last block fully indexed, if any.
+    pub fn last_block(&self) -> Result<Option<u64>> {
+        self.db
+            .lock()
+            .unwrap()
+            .query_row("SELECT last_block FROM checkpoint", [], |row| row.get(0))
+            .optional()
+            .map_err(MyTokenError::storage)
+    }
+
+    // The checkpoint's block and its hash
+    fn checkpoint(&self) -> Result<Option<(u64, H256)>> {
+        let checkpoint = self
+            .db
+            .lock()
+            .unwrap()
+            .query_row("SELECT last_block, last_hash FROM checkpoint", [], |row| {
+                Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+            })
+            .optional()
+            .map_err(MyTokenError::storage)?;
+        checkpoint
+            .map(|(block, hash)| Ok((block, hash.parse().map_err(MyTokenError::storage)?)))
+            .transpose()
+    }
+
+    /// Every indexed transfer, oldest first.
+    pub fn transfers(&self) -> Result<Vec<EnrichedTransfer>> {
+        let db = self.db.lock().unwrap();
+        let mut statement = db
+            .prepare(
+                "SELECT sender, recipient, value, block_number, tx_hash, log_index, timestamp,
+                        block_hash
+                 FROM transfers ORDER BY block_number, log_index",
+            )
+            .map_err(MyTokenError::storage)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, u64>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, u64>(5)?,
+                    row.get::<_, u64>(6)?,
+                    row.get::<_, String>(7)?,
+                ))
+            })
+            .map_err(MyTokenError::storage)?;
+
+        rows.map(|row| {
+            let (from, to, value, block_number, tx_hash, log_index, timestamp, block_hash) =
+                row.map_err(MyTokenError::storage)?;
+            Ok(EnrichedTransfer {
+                from: from.parse().map_err(MyTokenError::storage)?,
+                to: to.parse().map_err(MyTokenError::storage)?,
+                value: U256::from_dec_str(&value).map_err(MyTokenError::storage)?,
+                block_number,
+                block_hash: block_hash.parse().map_err(MyTokenError::storage)?,
+                tx_hash: tx_hash.parse().map_err(MyTokenError::storage)?,
+                log_index: U256::from(log_index),
+                timestamp,
+            })
+        })
+        .collect()
+    }
+
+    /// Index everything from the checkpoint up to the latest block, returning
+    /// that block.
+    ///
+    /// A checkpoint ahead of the chain, as after restarting a local node,
+    /// means the database describes an earlier chain, so it is cleared and
+    /// indexing starts over. A checkpoint block the chain has replaced
+    /// means a reorg, which is rolled back first.
+    pub async fn index_once(&self) -> Result<u64> {
+        let latest = self
+            .token
+            .client()
+            .get_block_number()
+            .await
+            .map_err(MyTokenError::middleware)?
+            .as_u64();
+        let latest_hash = self.block_hash(latest).await?;
+        let from_block = match self.checkpoint()? {
+            Some((last, _)) if last > latest => {
+                self.clear()?;
+                self.start_block
+            }
+            Some((last, hash)) => {
+                // The next block's parent, or the checkpoint block itself when
+                // there is no next block yet
+                let canonical = if last < latest {
+                    self.parent_hash(last + 1).await?
+                } else {
+                    latest_hash
+                };
+                if canonical == hash {
+                    last + 1
+                } else {
+                    self.roll_back().await?
+                }
+            }
+            None => self.start_block,
+        };
+        if from_block > latest {
+            return Ok(latest);
+        }
+
+        let transfers = self
+            .token
+            .enriched_transfers_between(from_block, Some(latest))
+            .await?;
+        self.commit(&transfers, latest, latest_hash)?;
+        Ok(latest)
+    }
+
+    // Drop the transfers from blocks no longer on the chain, returning the
+    // block to index from. That follows the newest indexed block the chain
+    // still has, or is the start block when it has none of them.
+    async fn roll_back(&self) -> Result<u64> {
+        let indexed: Vec<(u64, String)> = {
+            let db = self.db.lock().unwrap();
+            let mut statement = db
+                .prepare(
+                    "SELECT DISTINCT block_number, block_hash FROM transfers
+                     ORDER BY block_number DESC",
+                )
+                .map_err(MyTokenError::storage)?;
+            statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(MyTokenError::storage)?
+                .collect::<rusqlite::Result<_>>()
+                .map_err(MyTokenError::storage)?
+        };
+
+        for (block, hash) in indexed {
+            let hash: H256 = hash.parse().map_err(MyTokenError::storage)?;
+            if self.block_hash(block).await? != hash {
+                continue;
+            }
+            let mut db = self.db.lock().unwrap();
+            let tx = db.transaction().map_err(MyTokenError::storage)?;
+            tx.execute(
+                "DELETE FROM transfers WHERE block_number > ?1",
+                params![block],
+            )
+            .map_err(MyTokenError::storage)?;
+            tx.execute(
+                "UPDATE checkpoint SET last_block = ?1, last_hash = ?2",
+                params![block, format!("{hash:?}")],
+            )
+            .map_err(MyTokenError::storage)?;
+            tx.commit().map_err(MyTokenError::storage)?;
+            return Ok(block + 1);
+        }
+
+        self.clear()?;
+        Ok(self.start_block)
+    }
+
+    // Hash of the block at `number` on the current chain
+    async fn block_hash(&self, number: u64) -> Result<H256> {
+        self.block(number)
+            .await?
+            .hash
+            .ok_or(MyTokenError::MissingBlock(number))
+    }
+
+    async fn parent_hash(&self, number: u64) -> Result<H256> {
+        Ok(self.block(number).await?.parent_hash)
+    }
+
+    async fn block(&self, number: u64) -> Result<Block<H256>> {
+        self.token
+            .client()
+            .get_block(number)
+            .await
+            .map_err(MyTokenError::middleware)?
+            .ok_or(MyTokenError::MissingBlock(number))
+    }
+
+    /// Recompute every holder's balance from the indexed transfers and compare
+    /// it with `balanceOf` at the checkpoint block, read in Multicall3 batches
+    /// when the token's client has one to use, as
+    /// [`spendable`](MyTokenClient::spendable) does.
+    ///
+    /// Only an index holding the token's whole history, from its deployment
+    /// on, can match exactly: one with a later start block misses earlier
+    /// transfers, and so reports their holders as mismatched too.
+    pub async fn reconcile(&self) -> Result<ReconcileReport> {
+        let Some((block, _)) = self.checkpoint()? else {
+            return Err(MyTokenError::Storage("nothing has been indexed yet".into()));
+        };
+
+        let mut flows: BTreeMap<Address, (U256, U256)> = BTreeMap::new();
+        for transfer in self.transfers()? {
+            let received = &mut flows.entry(transfer.to).or_default().0;
+            *received = received.saturating_add(transfer.value);
+            let sent = &mut flows.entry(transfer.from).or_default().1;
+            *sent = sent.saturating_add(transfer.value);
+        }
+        // Mints come from and burns go to the zero address, which holds nothing
+        flows.remove(&Address::zero());
+
+        let accounts: Vec<Address> = flows.keys().copied().collect();
+        let balances = self.balances_at(&accounts, block).await?;
+
+        let mut mismatches = Vec::new();
+        for ((&account, &(received, sent)), on_chain) in flows.iter().zip(balances) {
+            let indexed = received.saturating_sub(sent);
+            if indexed != on_chain {
+                mismatches.push(BalanceMismatch {
+                    account,
+                    indexed,
+                    on_chain,
+                });
+            }
+        }
+        Ok(ReconcileReport {
+            block,
+            holders: flows.len(),
+            mismatches,
+        })
+    }
+
+    // `accounts`' balances at `block`, in input order, read through Multicall3
+    // a batch at a time where there is one, and with a call each otherwise
+    async fn balances_at(&self, accounts: &[Address], block: u64) -> Result<Vec<U256>> {
+        let token = &self.token;
+        let Ok(multicall) = Multicall::new(token.client(), token.multicall_address()).await else {
+            let mut balances = Vec::with_capacity(accounts.len());
+            for &account in accounts {
+                let balance = token.contract().balance_of(account).block(block);
+                balances.push(balance.call().await?);
+            }
+            return Ok(balances);
+        };
+
+        let mut multicall = multicall.block(block);
+        let mut balances = Vec::with_capacity(accounts.len());
+        for batch in accounts.chunks(RECONCILE_BATCH_SIZE) {
+            multicall.clear_calls();
+            for &account in batch {
+                multicall.add_call(token.contract().balance_of(account), false);
+            }
+            balances.extend(multicall.call_array::<U256>().await?);
+        }
+        Ok(balances)
+    }
+
+    /// Keep indexing new blocks until `cancel` fires, then return the last
+    /// block indexed.
+    ///
+    /// A batch interrupted by cancellation is dropped before anything from it
+    /// is written, leaving the checkpoint at the previous batch.
+    pub async fn run(&self, cancel: CancellationToken) -> Result<Option<u64>> {
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                indexed = self.index_once() => { indexed?; }
+            }
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+        self.last_block()
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.db
+            .lock()
+            .unwrap()
+            .execute_batch("BEGIN; DELETE FROM transfers; DELETE FROM checkpoint; COMMIT;")
+            .map_err(MyTokenError::storage)
+    }
+
+    // Write a batch and move the checkpoint in one transaction
+    fn commit(
+        &self,
+        transfers: &[EnrichedTransfer],
+        last_block: u64,
+        last_hash: H256,
+    ) -> Result<()> {
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction().map_err(MyTokenError::storage)?;
+        for transfer in transfers {
+            tx.execute(
+                "INSERT OR REPLACE INTO transfers
+                 (block_number, log_index, tx_hash, sender, recipient, value, timestamp,
+                  block_hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    transfer.block_number,
+                    transfer.log_index.as_u64(),
+                    format!("{:?}", transfer.tx_hash),
+                    format!("{:?}", transfer.from),
+                    format!("{:?}", transfer.to),
+                    transfer.value.to_string(),
+                    transfer.timestamp,
+                    format!("{:?}", transfer.block_hash),
+                ],
+            )
+            .map_err(MyTokenError::storage)?;
+        }
+        tx.execute(
+            "INSERT INTO checkpoint (id, last_block, last_hash) VALUES (0, ?1, ?2)
+             ON CONFLICT (id) DO UPDATE
+             SET last_block = excluded.last_block, last_hash = excluded.last_hash",
+            params![last_block, format!("{last_hash:?}")],
+        )
+        .map_err(MyTokenError::storage)?;
+        tx.commit().map_err(MyTokenError::storage)
+    }
+}
+
+// Whether `db` holds transfers indexed before their block hashes were
+fn is_unhashed(db: &Connection) -> Result<bool> {
+    db.query_row(
+        "SELECT COUNT(*) > 0 AND SUM(name = 'block_hash') = 0
+         FROM pragma_table_info('transfers')",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(MyTokenError::storage)
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// [`Indexer::reconcile`] the database at `db_path` against this token.
+    pub async fn reconcile(&self, db_path: impl AsRef<Path>) -> Result<ReconcileReport> {
+        Indexer::open(self.at(self.address()), db_path)?
+            .reconcile()
+            .await
+    }
+}
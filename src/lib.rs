@@ -0,0 +1,156 @@
+//! Rust client for the `MyToken` ERC20 contract.
+
+mod account;
+mod airdrop;
+mod api;
+mod backoff;
+mod cache;
+mod chain;
+mod client;
+mod config;
+mod cursor;
+mod deploy;
+mod dry_run;
+mod error;
+mod events;
+mod faucet;
+mod format;
+mod gas;
+mod hooks;
+mod idempotency;
+mod impersonate;
+mod indexer;
+mod metadata;
+mod offline;
+mod permit;
+mod permit2;
+mod preflight;
+mod provider;
+mod receipts;
+mod relay;
+mod sender;
+#[cfg(feature = "server")]
+mod server;
+mod simulate;
+mod smoke;
+mod stream;
+mod summary;
+mod telemetry;
+mod units;
+mod user_op;
+mod vault;
+mod verify;
+
+pub use account::{AccountSummary, HolderBalance};
+pub use airdrop::{AirdropLog, AirdropProgress, airdrop};
+pub use api::TokenApi;
+pub use chain::Chain;
+pub use client::{MyTokenClient, ZeroAmount};
+pub use config::{Config, GasStrategy, NetworkConfig, TxType, VerifyConfig};
+pub use cursor::ScanCursor;
+pub use deploy::{DEFAULT_ARTIFACT, Deployment, TokenDeployer};
+pub use dry_run::SimulatedTx;
+pub use error::{MyTokenError, Result};
+pub use events::{
+    EnrichedTransfer, SupplyChange, SupplyChangeKind, TRANSFER_TOPIC, TransferFromEvents,
+    decode_transfer_log, format_transfer,
+};
+pub use faucet::{Faucet, TEST_CHAIN_ID};
+pub use format::checksum;
+pub use hooks::TransferHook;
+pub use idempotency::{IdempotencyStore, KeyedTransfer, MemoryStore};
+pub use impersonate::{Impersonated, impersonate};
+pub use indexer::{BalanceMismatch, Indexer, ReconcileReport};
+pub use metadata::ContractMeta;
+pub use offline::LegacyGas;
+pub use permit::{SignedPermit, default_deadline};
+pub use permit2::{PERMIT2_ADDRESS, Permit2, SignedPermit2};
+pub use preflight::Preflight;
+pub use provider::{
+    AnyTransport, FallbackTransport, ReconnectPolicy, TransportKind, connect, connect_fallback,
+    http_provider,
+};
+pub use receipts::{JsonLinesStore, ReceiptRecord, ReceiptStore};
+pub use sender::{MockTxSender, TxSender};
+#[cfg(feature = "server")]
+pub use server::{BalanceResponse, TokenInfo, TokenServer, TransferRequest, TransferResponse};
+pub use simulate::SimCall;
+pub use smoke::{SmokeReport, SmokeStep};
+pub use summary::{TxStatus, TxSummary, wait_all};
+#[cfg(feature = "metrics")]
+pub use telemetry::{LATENCY_METRIC, OPERATIONS_METRIC, REVERTS_METRIC};
+pub use units::{from_token_units, to_token_units};
+pub use user_op::{ENTRY_POINT_V06, EntryPoint, Sponsor, UserOperation};
+pub use vault::{MyVault, VaultClient};
+pub use verify::{ETHERSCAN_API_URL, Verification};
+
+use ethers::prelude::abigen;
+
+abigen!(
+    MyToken,
+    r#"[
+        function name() external view returns (string)
+        function symbol() external view returns (string)
+        function decimals() external view returns (uint8)
+        function setName(string newName) external
+        function setSymbol(string newSymbol) external
+        function totalSupply() external view returns (uint256)
+        function balanceOf(address account) external view returns (uint256)
+        function transfer(address to, uint256 amount) external returns (bool)
+        function transferAndCall(address to, uint256 amount, bytes data) external returns (bool)
+        function batchTransfer(address[] to, uint256[] amounts, bool atomic) external returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+        function approve(address spender, uint256 amount) external returns (bool)
+        function transferFrom(address from, address to, uint256 amount) external returns (bool)
+        function owner() external view returns (address)
+        function mint(address to, uint256 amount) external
+        function safeMint(address to, uint256 amount) external
+        function burn(uint256 amount) external
+        function burnFrom(address account, uint256 amount) external
+        function cap() external view returns (uint256)
+        function remainingMintable() external view returns (uint256)
+        function proposeCapChange(uint256 newCap) external
+        function executeCapChange() external
+        function pendingCap() external view returns (uint256)
+        function capChangeTime() external view returns (uint256)
+        function capChangeDelay() external view returns (uint256)
+        function setCapChangeDelay(uint256 delay) external
+        function pendingCapChangeDelay() external view returns (uint256)
+        function capChangeDelayTime() external view returns (uint256)
+        function freeze(address account) external
+        function unfreeze(address account) external
+        function isFrozen(address account) external view returns (bool)
+        function sweepETH(address to) external
+        function setTransferFee(uint16 bps) external
+        function transferFeeBps() external view returns (uint16)
+        function transferFee(address from, address to, uint256 value) external view returns (uint256)
+        function maxFlashLoan(address token) external view returns (uint256)
+        function flashFee(address token, uint256 amount) external view returns (uint256)
+        function flashLoan(address receiver, address token, uint256 amount, bytes data) external returns (bool)
+        function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external
+        function nonces(address owner) external view returns (uint256)
+        function DOMAIN_SEPARATOR() external view returns (bytes32)
+        function approveWithExpiry(address spender, uint256 amount, uint256 deadline) external returns (bool)
+        function allowanceExpiry(address owner, address spender) external view returns (uint256)
+        event Transfer(address indexed from, address indexed to, uint256 value)
+        event Approval(address indexed owner, address indexed spender, uint256 value)
+        event MetadataUpdated(string name, string symbol)
+        event BatchTransferSkipped(address indexed to, uint256 amount)
+        event TransferFeeUpdated(uint16 bps)
+        event EthReceived(address indexed from, uint256 amount)
+        event EthSwept(address indexed to, uint256 amount)
+        event CapChangeProposed(uint256 newCap, uint256 executableAt)
+        event CapChanged(uint256 oldCap, uint256 newCap)
+        event CapChangeDelayUpdated(uint256 delay, uint256 effectiveAt)
+        error AccountFrozen(address account)
+        error AllowanceExpired(address owner, address spender, uint256 expiry)
+        error InvalidTransferReceiver(address receiver)
+        error BatchLengthMismatch(uint256 recipients, uint256 amounts)
+        error InsufficientBalance(uint256 available, uint256 needed)
+        error TransferFeeTooHigh(uint16 bps)
+        error EthTransferFailed(address to)
+        error CapBelowSupply(uint256 cap, uint256 supply)
+        error CapChangeNotReady(uint256 executableAt)
+        error NoPendingCapChange()
+    ]"#,
+);
@@ -0,0 +1,363 @@
+use clap::{Parser, Subcommand};
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Http, Provider},
+    signers::LocalWallet,
+    types::{Address, U256},
+    utils::{format_ether, parse_ether},
+};
+use eyre::{Result, WrapErr};
+use futures::StreamExt;
+use my_token::{
+    DEFAULT_ARTIFACT, ETHERSCAN_API_URL, Faucet, MyTokenClient, TokenDeployer, TxStatus, TxSummary,
+    Verification, checksum, format_transfer, from_token_units, to_token_units,
+};
+use std::{path::PathBuf, sync::Arc};
+use tokio_util::sync::CancellationToken;
+
+type SignerClient = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+#[derive(Parser)]
+#[command(about = "Command-line client for the MyToken ERC20 contract")]
+struct Cli {
+    /// HTTP RPC endpoint
+    #[arg(long, env = "RPC_URL", default_value = "http://localhost:8545")]
+    rpc_url: String,
+
+    /// WebSocket RPC endpoint, used for subscriptions
+    #[arg(long, env = "WS_URL", default_value = "ws://localhost:8545")]
+    ws_url: String,
+
+    /// Address of the deployed token
+    #[arg(
+        long,
+        env = "MY_TOKEN_ADDRESS",
+        default_value = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+    )]
+    token: Address,
+
+    /// Key to sign transactions with, needed by commands that write
+    #[arg(long, env = "PRIVATE_KEY", hide_env_values = true)]
+    private_key: Option<String>,
+
+    /// Simulate writes and print what would be sent, without sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print transfers as they happen, until Ctrl-C
+    Watch,
+
+    /// Show the account `--private-key` controls and its balances
+    Whoami,
+
+    /// Check transfers, approvals and reverts work on the deployment, spending
+    /// a little gas and a few of the token's smallest units
+    Smoke,
+
+    /// Send tokens to an address
+    Transfer {
+        to: Address,
+
+        /// In whole tokens, such as 1.5
+        amount: String,
+    },
+
+    /// Let a spender move up to an amount of your tokens
+    Approve {
+        spender: Address,
+
+        /// In whole tokens, such as 1.5
+        amount: String,
+    },
+
+    /// Set a spender's allowance over your tokens back to zero
+    Revoke { spender: Address },
+
+    /// Fund an address with ETH and tokens, on a local test chain only
+    Faucet {
+        address: Address,
+
+        /// ETH to send, in ether
+        #[arg(long, default_value = "1")]
+        eth: String,
+
+        /// Tokens to send, in whole tokens
+        #[arg(long, default_value = "1000")]
+        tokens: String,
+    },
+
+    /// Deploy a new token and record it in `deployments/<chain id>.json`
+    Deploy {
+        /// Owner of the new token, the signer if not given
+        #[arg(long)]
+        owner: Option<Address>,
+
+        #[arg(long, default_value_t = 18)]
+        decimals: u8,
+
+        /// Foundry build artifact to deploy
+        #[arg(long, default_value = DEFAULT_ARTIFACT)]
+        artifact: PathBuf,
+
+        /// Directory for the deployment record
+        #[arg(long, default_value = "deployments")]
+        out_dir: PathBuf,
+
+        /// Don't write the deployment record
+        #[arg(long)]
+        no_record: bool,
+
+        /// Verify the source on the chain's block explorer once deployed
+        #[arg(long, requires_all = ["standard_json", "compiler_version", "explorer_api_key"])]
+        verify: bool,
+
+        /// Solidity standard JSON input the token was compiled from, as from
+        /// `forge verify-contract --show-standard-json-input`
+        #[arg(long)]
+        standard_json: Option<PathBuf>,
+
+        /// Full solc version, such as v0.8.24+commit.e11b9ed9
+        #[arg(long)]
+        compiler_version: Option<String>,
+
+        /// Key for the explorer's API
+        #[arg(long, env = "ETHERSCAN_API_KEY", hide_env_values = true)]
+        explorer_api_key: Option<String>,
+
+        /// Etherscan-compatible API to verify with
+        #[arg(long, default_value = ETHERSCAN_API_URL)]
+        explorer_api_url: String,
+    },
+
+    /// Check the balances in an indexer database against the chain
+    Reconcile {
+        /// SQLite database written by the indexer
+        db: PathBuf,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let provider = Provider::<Http>::try_from(cli.rpc_url.as_str())?;
+
+    match cli.command {
+        Command::Watch => {
+            let token = MyTokenClient::new(cli.token, Arc::new(provider));
+            watch(&token, &cli.ws_url).await
+        }
+        Command::Whoami => {
+            let token = signer_token(&cli, provider).await?;
+            let account = token.whoami().await?;
+            let decimals = token.decimals().await?;
+            let symbol = token.symbol().await?;
+            println!("Address  {}", checksum(account.address));
+            println!("ETH      {}", format_ether(account.eth_balance));
+            println!(
+                "{symbol:<8} {}",
+                from_token_units(account.token_balance, decimals)?
+            );
+            Ok(())
+        }
+        Command::Smoke => {
+            let token = signer_token(&cli, provider).await?;
+            let report = token.smoke_test().await?;
+            print!("{report}");
+            if !report.passed() {
+                eyre::bail!("smoke test of {} failed", checksum(cli.token));
+            }
+            Ok(())
+        }
+        Command::Transfer { to, ref amount } => {
+            let token = signer_token(&cli, provider).await?;
+            let raw = token_amount(&token, amount).await?;
+            let summary = token.transfer(to, raw).await?;
+            print_simulated(&token);
+            println!("Sent {amount} to {}: {summary}", checksum(to));
+            print_gas_cost(&summary);
+            Ok(())
+        }
+        Command::Approve {
+            spender,
+            ref amount,
+        } => {
+            let token = signer_token(&cli, provider).await?;
+            let raw = token_amount(&token, amount).await?;
+            let summary = token.approve(spender, raw).await?;
+            print_simulated(&token);
+            println!("Approved {} for {amount}: {summary}", checksum(spender));
+            print_gas_cost(&summary);
+            Ok(())
+        }
+        Command::Revoke { spender } => {
+            let token = signer_token(&cli, provider).await?;
+            let summary = token.revoke_allowance(spender).await?;
+            print_simulated(&token);
+            println!("Revoked allowance of {}: {summary}", checksum(spender));
+            print_gas_cost(&summary);
+            Ok(())
+        }
+        Command::Faucet {
+            address,
+            ref eth,
+            ref tokens,
+        } => {
+            let token = signer_token(&cli, provider).await?;
+            let eth = parse_ether(eth).wrap_err("invalid --eth amount")?;
+            let tokens = token_amount(&token, tokens).await?;
+            let faucet = Faucet::new(token.at(token.address()));
+            let summaries = faucet.drip(address, eth, tokens).await?;
+            print_simulated(&token);
+            for summary in summaries {
+                println!("Funded {}: {summary}", checksum(address));
+                print_gas_cost(&summary);
+            }
+            Ok(())
+        }
+        Command::Deploy {
+            owner,
+            decimals,
+            ref artifact,
+            ref out_dir,
+            no_record,
+            verify,
+            ref standard_json,
+            ref compiler_version,
+            ref explorer_api_key,
+            ref explorer_api_url,
+        } => {
+            if cli.dry_run {
+                eyre::bail!("deploy has no dry run; leave out --dry-run to deploy");
+            }
+            let client = signer_client(&cli, provider).await?;
+            let mut deployer = TokenDeployer::new(client, artifact).with_decimals(decimals);
+            if let Some(owner) = owner {
+                deployer = deployer.with_owner(owner);
+            }
+            if !no_record {
+                deployer = deployer.with_output_dir(out_dir);
+            }
+            if verify
+                && let (Some(source), Some(version), Some(key)) =
+                    (standard_json, compiler_version, explorer_api_key)
+            {
+                let source = std::fs::read_to_string(source)
+                    .wrap_err_with(|| format!("reading {}", source.display()))?;
+                deployer = deployer.with_verification(
+                    Verification::new(key, source, version).with_api_url(explorer_api_url),
+                );
+            }
+            let deployment = deployer.deploy().await?;
+            println!(
+                "Deployed MyToken at {} in block {} (tx {:?})",
+                checksum(deployment.address),
+                deployment.block,
+                deployment.tx_hash
+            );
+            if verify {
+                println!("Verified its source on the explorer");
+            }
+            Ok(())
+        }
+        Command::Reconcile { ref db } => {
+            let token = MyTokenClient::new(cli.token, Arc::new(provider));
+            let decimals = token.decimals().await?;
+            let report = token.reconcile(db).await?;
+            for mismatch in &report.mismatches {
+                println!(
+                    "{}  indexed {}, on chain {}",
+                    checksum(mismatch.account),
+                    from_token_units(mismatch.indexed, decimals)?,
+                    from_token_units(mismatch.on_chain, decimals)?
+                );
+            }
+            if !report.is_consistent() {
+                eyre::bail!(
+                    "{} of {} holders differ at block {}",
+                    report.mismatches.len(),
+                    report.holders,
+                    report.block
+                );
+            }
+            println!(
+                "All {} holders match at block {}",
+                report.holders, report.block
+            );
+            Ok(())
+        }
+    }
+}
+
+// What a write that went on chain cost, under its summary
+fn print_gas_cost(summary: &TxSummary) {
+    if matches!(summary.status, TxStatus::Success | TxStatus::Reverted) {
+        println!("  gas cost {} ETH", summary.gas_cost_eth_string());
+    }
+}
+
+// What `--dry-run` simulated rather than sent
+fn print_simulated(token: &MyTokenClient<SignerClient>) {
+    for tx in token.simulated_txs() {
+        eprintln!("dry run: {tx}");
+    }
+}
+
+// `amount` in whole tokens as raw units, by the token's decimals. Anything
+// malformed or finer than the token's smallest unit is refused.
+async fn token_amount(token: &MyTokenClient<SignerClient>, amount: &str) -> Result<U256> {
+    let decimals = token.decimals().await?;
+    to_token_units(amount, decimals)
+        .wrap_err_with(|| format!("{amount:?} isn't an amount of a token with {decimals} decimals"))
+}
+
+// Client signing with `--private-key`, on the chain the provider reports
+async fn signer_token(cli: &Cli, provider: Provider<Http>) -> Result<MyTokenClient<SignerClient>> {
+    let client = signer_client(cli, provider).await?;
+    Ok(MyTokenClient::new(cli.token, client).with_dry_run(cli.dry_run))
+}
+
+async fn signer_client(cli: &Cli, provider: Provider<Http>) -> Result<Arc<SignerClient>> {
+    let key = cli
+        .private_key
+        .as_deref()
+        .ok_or_else(|| eyre::eyre!("this command needs --private-key or PRIVATE_KEY"))?;
+    let wallet: LocalWallet = key.parse().wrap_err("invalid private key")?;
+
+    let client = SignerMiddleware::new_with_provider_chain(provider, wallet).await?;
+    Ok(Arc::new(client))
+}
+
+async fn watch(token: &MyTokenClient<Provider<Http>>, ws_url: &str) -> Result<()> {
+    let decimals = token.decimals().await?;
+    // Ctrl-C ends the stream, which then ends the loop below
+    let cancel = CancellationToken::new();
+    tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            let _ = tokio::signal::ctrl_c().await;
+            cancel.cancel();
+        }
+    });
+    let transfers = token.watch_transfers(ws_url, cancel);
+    tokio::pin!(transfers);
+
+    println!(
+        "Watching transfers of {}, Ctrl-C to stop",
+        checksum(token.address())
+    );
+    while let Some(event) = transfers.next().await {
+        match event {
+            Ok(event) => println!("{}", format_transfer(&event, decimals)?),
+            // The stream reconnects by itself, so keep watching
+            Err(err) => eprintln!("Warning: {err}"),
+        }
+    }
+
+    Ok(())
+}
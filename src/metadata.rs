@@ -0,0 +1,113 @@
+use crate::{MyTokenClient, MyTokenError, Result, TxSummary};
+use ethers::{
+    prelude::abigen,
+    providers::Middleware,
+    types::{Address, H256, U64},
+};
+
+// Only `MyTokenUpgradeable` has this, so it's kept out of the `MyToken` binding
+abigen!(
+    MyTokenUpgradeable,
+    r#"[
+        function upgradeTo(address newImplementation) external
+    ]"#,
+);
+
+// EIP-1967 storage slots, `keccak256("eip1967.proxy.implementation") - 1` and
+// `keccak256("eip1967.proxy.beacon") - 1`
+const IMPLEMENTATION_SLOT: H256 = H256([
+    0x36, 0x08, 0x94, 0xa1, 0x3b, 0xa1, 0xa3, 0x21, 0x06, 0x67, 0xc8, 0x28, 0x49, 0x2d, 0xb9, 0x8d,
+    0xca, 0x3e, 0x20, 0x76, 0xcc, 0x37, 0x35, 0xa9, 0x20, 0xa3, 0xca, 0x50, 0x5d, 0x38, 0x2b, 0xbc,
+]);
+const BEACON_SLOT: H256 = H256([
+    0xa3, 0xf0, 0xad, 0x74, 0xe5, 0x42, 0x3a, 0xeb, 0xfd, 0x80, 0xd3, 0xef, 0x43, 0x46, 0x57, 0x83,
+    0x35, 0xa9, 0xa7, 0x2a, 0xea, 0xee, 0x59, 0xff, 0x6c, 0xb3, 0x58, 0x2b, 0x35, 0x13, 0x3d, 0x50,
+]);
+
+/// Facts about the deployed contract itself, for audits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractMeta {
+    /// Length of the runtime bytecode in bytes.
+    pub code_size: usize,
+    /// Whether an EIP-1967 implementation or beacon slot is set. Proxies
+    /// using other layouts aren't detected.
+    pub is_proxy: bool,
+    /// First block at which the address had code.
+    pub deployment_block: u64,
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// The logic contract behind the token, read from the EIP-1967
+    /// implementation slot, or `None` when the token isn't such a proxy.
+    pub async fn implementation(&self) -> Result<Option<Address>> {
+        let value = self
+            .client()
+            .get_storage_at(self.address(), IMPLEMENTATION_SLOT, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+        Ok((!value.is_zero()).then(|| Address::from(value)))
+    }
+
+    /// Owner-only switch of the implementation, for tokens deployed behind
+    /// `MyTokenProxy`. Other deployments, `MyToken` included, have no
+    /// `upgradeTo` and revert.
+    pub async fn upgrade_to(&self, new_implementation: Address) -> Result<TxSummary> {
+        let proxy = MyTokenUpgradeable::new(self.address(), self.client());
+        self.send(proxy.upgrade_to(new_implementation)).await
+    }
+
+    /// Code size, proxy status and deployment block of the token contract.
+    ///
+    /// The deployment block is found by binary search over historical
+    /// `eth_getCode`, so it needs a node that keeps old state, such as an
+    /// archive node or Anvil.
+    pub async fn contract_metadata(&self) -> Result<ContractMeta> {
+        let client = self.client();
+        let address = self.address();
+
+        let code = client
+            .get_code(address, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+        if code.is_empty() {
+            return Err(MyTokenError::NoContract(address));
+        }
+
+        let mut is_proxy = false;
+        for slot in [IMPLEMENTATION_SLOT, BEACON_SLOT] {
+            let value = client
+                .get_storage_at(address, slot, None)
+                .await
+                .map_err(MyTokenError::middleware)?;
+            is_proxy |= !value.is_zero();
+        }
+
+        // Smallest block with code, knowing the latest one has it
+        let (mut low, mut high) = (
+            0u64,
+            client
+                .get_block_number()
+                .await
+                .map_err(MyTokenError::middleware)?
+                .as_u64(),
+        );
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let code = client
+                .get_code(address, Some(U64::from(middle).into()))
+                .await
+                .map_err(MyTokenError::middleware)?;
+            if code.is_empty() {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        Ok(ContractMeta {
+            code_size: code.len(),
+            is_proxy,
+            deployment_block: low,
+        })
+    }
+}
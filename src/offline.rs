@@ -0,0 +1,73 @@
+use crate::client::Broadcast;
+use crate::{MyTokenClient, MyTokenError, Result, TxSummary};
+use ethers::{
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, TransactionRequest, U256, transaction::eip2718::TypedTransaction},
+    utils::rlp::Rlp,
+};
+
+/// Gas settings for a transaction signed without a node to ask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegacyGas {
+    pub limit: U256,
+    pub price: U256,
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Broadcast a transaction signed elsewhere, e.g. by
+    /// [`sign_transfer_offline`](Self::sign_transfer_offline), and wait for it
+    /// like any other write.
+    pub async fn send_raw(&self, signed: Bytes) -> Result<TxSummary> {
+        if self.is_dry_run() {
+            let (mut tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&signed))
+                .map_err(|err| MyTokenError::Signing(err.to_string()))?;
+            let from = signature
+                .recover(tx.sighash())
+                .map_err(|err| MyTokenError::Signing(err.to_string()))?;
+            tx.set_from(from);
+            return self.dry_run(&tx).await;
+        }
+        let tx_hash = *self
+            .client()
+            .send_raw_transaction(signed.clone())
+            .await
+            .map_err(MyTokenError::middleware)?;
+        self.confirm(tx_hash, Broadcast::Raw(signed)).await
+    }
+
+    /// Sign a legacy transfer of `amount` to `to` without touching the network,
+    /// returning the bytes to hand to [`send_raw`](Self::send_raw).
+    ///
+    /// Nothing can be looked up, so the nonce, chain id and gas are the
+    /// caller's to get right.
+    pub fn sign_transfer_offline(
+        &self,
+        wallet: &LocalWallet,
+        nonce: U256,
+        chain_id: u64,
+        to: Address,
+        amount: U256,
+        gas: LegacyGas,
+    ) -> Result<Bytes> {
+        if to.is_zero() {
+            return Err(MyTokenError::InvalidRecipient(to));
+        }
+
+        let calldata = self.contract().transfer(to, amount).calldata();
+        let tx: TypedTransaction = TransactionRequest::new()
+            .from(wallet.address())
+            .to(self.address())
+            .data(calldata.unwrap_or_default())
+            .nonce(nonce)
+            .chain_id(chain_id)
+            .gas(gas.limit)
+            .gas_price(gas.price)
+            .into();
+
+        let signature = wallet
+            .sign_transaction_sync(&tx)
+            .map_err(|err| MyTokenError::Signing(err.to_string()))?;
+        Ok(tx.rlp_signed(&signature))
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{MyTokenClient, MyTokenError, Result, TxSummary};
+use ethers::{
+    abi::{self, Token},
+    contract::{
+        MULTICALL_ADDRESS,
+        multicall_contract::{Call3, Multicall3},
+    },
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, H256, U256},
+    utils::keccak256,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PERMIT_TYPE: &str =
+    "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)";
+
+/// An EIP-2612 permit signed by `owner`, ready to be submitted by anyone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedPermit {
+    pub owner: Address,
+    pub spender: Address,
+    pub value: U256,
+    pub nonce: U256,
+    pub deadline: U256,
+    pub v: u8,
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+}
+
+/// A permit deadline `minutes` from now, by the local clock.
+pub fn default_deadline(minutes: u64) -> U256 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    U256::from(now + minutes * 60)
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// The EIP-712 domain separator permits are signed against, read from the
+    /// contract once per chain.
+    ///
+    /// The separator commits to the chain id, so the node is asked for it on
+    /// every call, unlike [`chain_id`](Self::chain_id). Should it change, as
+    /// when a fork is given its own id, the separator is read again rather
+    /// than signing permits that would be replayable on the old chain.
+    pub async fn domain_separator(&self) -> Result<H256> {
+        let chain_id = self
+            .client()
+            .get_chainid()
+            .await
+            .map_err(MyTokenError::middleware)?
+            .as_u64();
+        if let Some((cached_for, separator)) = *self.domain_separator_cache().lock().unwrap()
+            && cached_for == chain_id
+        {
+            return Ok(separator);
+        }
+
+        let separator = H256(self.contract().domain_separator().call().await?);
+        *self.domain_separator_cache().lock().unwrap() = Some((chain_id, separator));
+        Ok(separator)
+    }
+
+    /// The permit nonce `owner` must sign next.
+    pub async fn current_nonce(&self, owner: Address) -> Result<U256> {
+        Ok(self.contract().nonces(owner).call().await?)
+    }
+
+    /// Sign a permit letting `spender` move `value` of `owner`'s tokens until
+    /// `deadline`.
+    ///
+    /// The nonce is read from the contract unless given, which is only needed
+    /// when signing several permits ahead of submitting them.
+    pub async fn sign_permit(
+        &self,
+        owner: &LocalWallet,
+        spender: Address,
+        value: U256,
+        deadline: U256,
+        nonce: Option<U256>,
+    ) -> Result<SignedPermit> {
+        let owner_address = owner.address();
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => self.current_nonce(owner_address).await?,
+        };
+
+        let struct_hash = keccak256(abi::encode(&[
+            Token::FixedBytes(keccak256(PERMIT_TYPE).to_vec()),
+            Token::Address(owner_address),
+            Token::Address(spender),
+            Token::Uint(value),
+            Token::Uint(nonce),
+            Token::Uint(deadline),
+        ]));
+        let domain_separator = self.domain_separator().await?;
+        let digest =
+            keccak256([&[0x19, 0x01], domain_separator.as_bytes(), &struct_hash[..]].concat());
+
+        let signature = owner
+            .sign_hash(H256(digest))
+            .map_err(|err| MyTokenError::Signing(err.to_string()))?;
+
+        let mut r = [0u8; 32];
+        let mut s = [0u8; 32];
+        signature.r.to_big_endian(&mut r);
+        signature.s.to_big_endian(&mut s);
+
+        Ok(SignedPermit {
+            owner: owner_address,
+            spender,
+            value,
+            nonce,
+            deadline,
+            v: signature.v as u8,
+            r,
+            s,
+        })
+    }
+
+    /// Submit a signed permit, setting the owner's allowance for the spender.
+    pub async fn permit(&self, permit: &SignedPermit) -> Result<TxSummary> {
+        self.send(self.contract().permit(
+            permit.owner,
+            permit.spender,
+            permit.value,
+            permit.deadline,
+            permit.v,
+            permit.r,
+            permit.s,
+        ))
+        .await
+    }
+
+    /// Approve and move `amount` of `owner`'s tokens to `to` in one
+    /// transaction, so no allowance is left standing in between.
+    ///
+    /// Multicall3 is `msg.sender` for every call it batches, so the approval
+    /// is a permit from `owner` to the Multicall3 contract itself, spent by the
+    /// `transferFrom` that follows. Neither call may fail, so a failing pull
+    /// reverts the permit with it. This client's signer submits and pays gas.
+    pub async fn approve_and_pull(
+        &self,
+        owner: &LocalWallet,
+        to: Address,
+        amount: U256,
+    ) -> Result<TxSummary> {
+        let multicall = self.multicall_address().unwrap_or(MULTICALL_ADDRESS);
+        let deadline = default_deadline(10);
+        let permit = self
+            .sign_permit(owner, multicall, amount, deadline, None)
+            .await?;
+
+        let permit_call = self.contract().permit(
+            permit.owner,
+            permit.spender,
+            permit.value,
+            permit.deadline,
+            permit.v,
+            permit.r,
+            permit.s,
+        );
+        let pull_call = self.contract().transfer_from(permit.owner, to, amount);
+        let calls = [permit_call.calldata(), pull_call.calldata()]
+            .into_iter()
+            .map(|call_data| Call3 {
+                target: self.address(),
+                allow_failure: false,
+                call_data: call_data.unwrap_or_default(),
+            })
+            .collect();
+
+        let aggregate = Multicall3::new(multicall, self.client()).aggregate_3(calls);
+        self.send(aggregate).await
+    }
+}
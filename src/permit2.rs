@@ -0,0 +1,124 @@
+use crate::{MyTokenClient, MyTokenError, Result, TxSummary};
+use ethers::{
+    abi::{self, Token},
+    prelude::abigen,
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, H160, H256, U256},
+    utils::keccak256,
+};
+
+abigen!(
+    Permit2,
+    r#"[
+        struct TokenPermissions { address token; uint256 amount; }
+        struct PermitTransferFrom { TokenPermissions permitted; uint256 nonce; uint256 deadline; }
+        struct SignatureTransferDetails { address to; uint256 requestedAmount; }
+        function DOMAIN_SEPARATOR() external view returns (bytes32)
+        function permitTransferFrom(PermitTransferFrom permit, SignatureTransferDetails transferDetails, address owner, bytes signature) external
+    ]"#,
+);
+
+/// Uniswap's Permit2, deployed at this address on mainnet and most other
+/// chains. Local chains and forks of chains without it need it deployed first.
+pub const PERMIT2_ADDRESS: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x22, 0xd4, 0x73, 0x03, 0x0f, 0x11, 0x6d, 0xde, 0xe9, 0xf6, 0xb4,
+    0x3a, 0xc7, 0x8b, 0xa3,
+]);
+
+const TOKEN_PERMISSIONS_TYPE: &str = "TokenPermissions(address token,uint256 amount)";
+const PERMIT_TRANSFER_FROM_TYPE: &str = "PermitTransferFrom(TokenPermissions permitted,address spender,uint256 nonce,uint256 deadline)TokenPermissions(address token,uint256 amount)";
+
+/// A Permit2 signature transfer signed by `owner`, letting `spender` move up
+/// to `amount` of the token once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedPermit2 {
+    pub permit2: Address,
+    pub owner: Address,
+    pub spender: Address,
+    pub amount: U256,
+    pub nonce: U256,
+    pub deadline: U256,
+    /// `r`, `s` and `v`, packed as Permit2 expects.
+    pub signature: Bytes,
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Sign a Permit2 transfer of up to `amount` of `owner`'s tokens by
+    /// `spender`, valid until `deadline`, through the Permit2 contract at
+    /// `permit2`, usually [`PERMIT2_ADDRESS`].
+    ///
+    /// Permit2 moves tokens with its own allowance, so `owner` must have
+    /// approved `permit2` once beforehand; after that no approval per spender
+    /// is needed. Nonces are unordered in Permit2, so a random one is picked.
+    pub async fn sign_permit2_transfer(
+        &self,
+        permit2: Address,
+        owner: &LocalWallet,
+        spender: Address,
+        amount: U256,
+        deadline: U256,
+    ) -> Result<SignedPermit2> {
+        let nonce = U256::from(rand::random::<u128>());
+
+        let permissions_hash = keccak256(abi::encode(&[
+            Token::FixedBytes(keccak256(TOKEN_PERMISSIONS_TYPE).to_vec()),
+            Token::Address(self.address()),
+            Token::Uint(amount),
+        ]));
+        let struct_hash = keccak256(abi::encode(&[
+            Token::FixedBytes(keccak256(PERMIT_TRANSFER_FROM_TYPE).to_vec()),
+            Token::FixedBytes(permissions_hash.to_vec()),
+            Token::Address(spender),
+            Token::Uint(nonce),
+            Token::Uint(deadline),
+        ]));
+        let domain_separator = Permit2::new(permit2, self.client())
+            .domain_separator()
+            .call()
+            .await?;
+        let digest = keccak256([&[0x19, 0x01], &domain_separator[..], &struct_hash[..]].concat());
+
+        let signature = owner
+            .sign_hash(H256(digest))
+            .map_err(|err| MyTokenError::Signing(err.to_string()))?;
+
+        Ok(SignedPermit2 {
+            permit2,
+            owner: owner.address(),
+            spender,
+            amount,
+            nonce,
+            deadline,
+            signature: signature.to_vec().into(),
+        })
+    }
+
+    /// Move `amount` of the owner's tokens to `to` with a signed Permit2
+    /// transfer. Permit2 checks the caller, so this client's signer must be
+    /// the permit's spender.
+    pub async fn permit2_transfer_from(
+        &self,
+        permit: &SignedPermit2,
+        to: Address,
+        amount: U256,
+    ) -> Result<TxSummary> {
+        let call = Permit2::new(permit.permit2, self.client()).permit_transfer_from(
+            PermitTransferFrom {
+                permitted: TokenPermissions {
+                    token: self.address(),
+                    amount: permit.amount,
+                },
+                nonce: permit.nonce,
+                deadline: permit.deadline,
+            },
+            SignatureTransferDetails {
+                to,
+                requested_amount: amount,
+            },
+            permit.owner,
+            permit.signature.clone(),
+        );
+        self.send(call).await
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{MyTokenClient, MyTokenError, Result};
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+
+/// What a `transferFrom` would run into, checked without sending it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preflight {
+    pub balance: U256,
+    pub allowance: U256,
+    pub balance_sufficient: bool,
+    /// Large enough and not expired.
+    pub allowance_sufficient: bool,
+    /// Whether the allowance's expiry is already behind the latest block.
+    pub allowance_expired: bool,
+    /// Gas the node estimates for the call, or `None` if it would revert.
+    pub estimated_gas: Option<U256>,
+}
+
+impl Preflight {
+    /// Whether the transfer is expected to go through.
+    pub fn is_ok(&self) -> bool {
+        self.balance_sufficient && self.allowance_sufficient && self.estimated_gas.is_some()
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Check balance, allowance and gas for this client's sender moving
+    /// `amount` from `from` to `to`, reporting each problem separately.
+    pub async fn preflight_transfer_from(
+        &self,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<Preflight> {
+        let sender = self.sender()?;
+        let balance = self.balance_of(from).await?;
+        let allowance = self.allowance(from, sender).await?;
+        let expiry = self.allowance_expiry(from, sender).await?;
+        let allowance_expired = match expiry {
+            Some(expiry) => {
+                let latest = self
+                    .client()
+                    .get_block_number()
+                    .await
+                    .map_err(MyTokenError::middleware)?;
+                expiry < self.block_timestamp(latest.as_u64()).await?.into()
+            }
+            None => false,
+        };
+        let estimated_gas = self
+            .contract()
+            .transfer_from(from, to, amount)
+            .estimate_gas()
+            .await
+            .ok();
+
+        Ok(Preflight {
+            balance,
+            allowance,
+            balance_sufficient: balance >= amount,
+            // The token lets holders move their own tokens without one
+            allowance_sufficient: from == sender || (allowance >= amount && !allowance_expired),
+            allowance_expired,
+            estimated_gas,
+        })
+    }
+}
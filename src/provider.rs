@@ -0,0 +1,248 @@
+use std::{
+    error::Error,
+    fmt::Debug,
+    io::ErrorKind,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use ethers::providers::{
+    Http, HttpClientError, Ipc, JsonRpcClient, Provider, ProviderError, RetryClient, RetryPolicy,
+    RpcError, Ws,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{MyTokenError, Result};
+
+/// How many times a request is replayed on a fresh connection before giving up.
+const RECONNECT_RETRIES: u32 = 3;
+const RECONNECT_BACKOFF_MS: u64 = 100;
+
+/// An HTTP provider that transparently reconnects when a pooled connection
+/// has been reset by the node, instead of failing the call that hit it.
+pub fn http_provider(url: &str) -> Result<Provider<RetryClient<Http>>> {
+    Ok(Provider::new(reconnecting_http(url)?))
+}
+
+fn reconnecting_http(url: &str) -> Result<RetryClient<Http>> {
+    let http: Http = url
+        .parse()
+        .map_err(|err| MyTokenError::Middleware(format!("invalid RPC url {url}: {err}")))?;
+    Ok(RetryClient::new(
+        http,
+        Box::new(ReconnectPolicy),
+        RECONNECT_RETRIES,
+        RECONNECT_BACKOFF_MS,
+    ))
+}
+
+/// The transport [`connect`] picked for a URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    Http,
+    Ws,
+    Ipc,
+}
+
+impl TransportKind {
+    /// `http(s)://` and `ws(s)://` URLs by their scheme, and IPC sockets by an
+    /// `ipc://` scheme or an absolute path. Any other scheme, or a relative
+    /// path, is a [`MyTokenError::Config`] error rather than a guess.
+    pub fn from_url(url: &str) -> Result<Self> {
+        let Some((scheme, _)) = url.split_once("://") else {
+            if Path::new(url).is_absolute() {
+                return Ok(TransportKind::Ipc);
+            }
+            return Err(MyTokenError::Config(format!(
+                "RPC url {url:?} has no scheme and isn't an absolute IPC socket path"
+            )));
+        };
+        match scheme.to_ascii_lowercase().as_str() {
+            "http" | "https" => Ok(TransportKind::Http),
+            "ws" | "wss" => Ok(TransportKind::Ws),
+            "ipc" => Ok(TransportKind::Ipc),
+            _ => Err(MyTokenError::Config(format!(
+                "unsupported RPC url scheme {scheme:?} in {url}"
+            ))),
+        }
+    }
+}
+
+/// Whichever transport [`connect`] opened, so one client type serves them all.
+#[derive(Debug)]
+pub enum AnyTransport {
+    Http(RetryClient<Http>),
+    Ws(Ws),
+    Ipc(Ipc),
+}
+
+impl AnyTransport {
+    pub fn kind(&self) -> TransportKind {
+        match self {
+            AnyTransport::Http(_) => TransportKind::Http,
+            AnyTransport::Ws(_) => TransportKind::Ws,
+            AnyTransport::Ipc(_) => TransportKind::Ipc,
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for AnyTransport {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> std::result::Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        match self {
+            AnyTransport::Http(http) => http.request(method, params).await.map_err(Into::into),
+            AnyTransport::Ws(ws) => ws.request(method, params).await.map_err(Into::into),
+            AnyTransport::Ipc(ipc) => ipc.request(method, params).await.map_err(Into::into),
+        }
+    }
+}
+
+/// A provider over HTTP, WebSocket or IPC, chosen by [`TransportKind::from_url`].
+/// HTTP gets the same reconnecting client as [`http_provider`].
+pub async fn connect(url: &str) -> Result<Provider<AnyTransport>> {
+    Ok(Provider::new(open(url).await?))
+}
+
+async fn open(url: &str) -> Result<AnyTransport> {
+    let transport = match TransportKind::from_url(url)? {
+        TransportKind::Http => AnyTransport::Http(reconnecting_http(url)?),
+        TransportKind::Ws => AnyTransport::Ws(Ws::connect(url).await.map_err(ProviderError::from)?),
+        TransportKind::Ipc => {
+            let path = ipc_path(url);
+            AnyTransport::Ipc(Ipc::connect(path).await.map_err(ProviderError::from)?)
+        }
+    };
+    Ok(transport)
+}
+
+// The socket path of an IPC url, with any `ipc://` scheme taken off
+fn ipc_path(url: &str) -> &str {
+    url.split_once("://").map_or(url, |(_, path)| path)
+}
+
+/// Methods that submit a transaction, sent to one endpoint only so the nonces
+/// of consecutive writes come from the same node.
+const WRITE_METHODS: [&str; 2] = ["eth_sendTransaction", "eth_sendRawTransaction"];
+
+/// Several endpoints in order of preference, moving on to the next when one
+/// can't be reached.
+///
+/// A read that fails to reach the current endpoint is retried on the next,
+/// which then becomes current. JSON-RPC errors such as reverts come from a
+/// working node, so they're returned as they are. Writes go to the current
+/// endpoint only and aren't retried elsewhere.
+#[derive(Debug)]
+pub struct FallbackTransport {
+    transports: Vec<AnyTransport>,
+    current: AtomicUsize,
+}
+
+impl FallbackTransport {
+    /// Index into the URLs given to [`connect_fallback`] of the endpoint in
+    /// use, counting only those that could be opened.
+    pub fn current(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FallbackTransport {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> std::result::Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let start = self.current();
+        if WRITE_METHODS.contains(&method) {
+            return self.transports[start].request(method, params).await;
+        }
+
+        let mut last_err = None;
+        for offset in 0..self.transports.len() {
+            let index = (start + offset) % self.transports.len();
+            match self.transports[index].request(method, &params).await {
+                Ok(result) => {
+                    self.current.store(index, Ordering::Relaxed);
+                    return Ok(result);
+                }
+                Err(err) if err.as_error_response().is_some() => return Err(err),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.expect("at least one transport"))
+    }
+}
+
+/// A provider over the first of `urls` that can be reached, falling back to
+/// the rest in order, as [`FallbackTransport`] describes. Each URL is opened
+/// as by [`connect`]; WebSocket and IPC URLs that can't be opened now are left
+/// out, and if none can be, the first error is returned. An empty `urls` is
+/// a [`MyTokenError::Config`] error.
+pub async fn connect_fallback(urls: &[&str]) -> Result<Provider<FallbackTransport>> {
+    if urls.is_empty() {
+        return Err(MyTokenError::Config(
+            "no RPC urls to fall back between".to_owned(),
+        ));
+    }
+    let mut transports = Vec::with_capacity(urls.len());
+    let mut first_err = None;
+    for url in urls {
+        match open(url).await {
+            Ok(transport) => transports.push(transport),
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    if let Some(err) = first_err.filter(|_| transports.is_empty()) {
+        return Err(err);
+    }
+    Ok(Provider::new(FallbackTransport {
+        transports,
+        current: AtomicUsize::new(0),
+    }))
+}
+
+/// Retries only requests that failed to reach the node, so reverts and other
+/// JSON-RPC errors still surface on the first attempt.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy;
+
+impl RetryPolicy<HttpClientError> for ReconnectPolicy {
+    fn should_retry(&self, error: &HttpClientError) -> bool {
+        match error {
+            HttpClientError::ReqwestError(err) => err.is_connect() || is_connection_reset(err),
+            _ => false,
+        }
+    }
+
+    fn backoff_hint(&self, _error: &HttpClientError) -> Option<Duration> {
+        None
+    }
+}
+
+fn is_connection_reset(err: &(dyn Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>()
+            && matches!(
+                io.kind(),
+                ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe
+            )
+        {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
@@ -0,0 +1,177 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ethers::{
+    abi::Token,
+    providers::Middleware,
+    types::{Address, Bytes, I256, TransactionReceipt, TxHash, U256},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    MyTokenClient, MyTokenError, Result, TxStatus, TxSummary, checksum, client::Broadcast,
+};
+
+/// A write the client sent and saw mined, as kept by a [`ReceiptStore`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptRecord {
+    /// The token function called, such as `transfer`, or the selector in hex
+    /// for a call the token's ABI doesn't have, or `""` for a plain ETH
+    /// transfer.
+    pub operation: String,
+    /// The call's arguments by name. Addresses are checksummed and numbers
+    /// written out in decimal.
+    pub args: Map<String, Value>,
+    /// The contract called.
+    pub to: Option<Address>,
+    pub tx_hash: TxHash,
+    pub block_number: u64,
+    pub gas_used: U256,
+    pub effective_gas_price: U256,
+    pub status: TxStatus,
+    pub chain_id: Option<u64>,
+}
+
+/// Where a client configured with
+/// [`with_receipt_store`](MyTokenClient::with_receipt_store) records each
+/// write once mined, reverted or not.
+pub trait ReceiptStore: Send + Sync {
+    fn append(&self, record: &ReceiptRecord) -> Result<()>;
+}
+
+/// A [`ReceiptStore`] writing one JSON [`ReceiptRecord`] per line to a file,
+/// synced after each.
+#[derive(Debug)]
+pub struct JsonLinesStore {
+    path: PathBuf,
+    // Held while appending, so lines from concurrent writes don't interleave
+    writing: Mutex<()>,
+}
+
+impl JsonLinesStore {
+    /// Appends to `path`, creating it on the first record.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            writing: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every record in the file at `path`, oldest first.
+    pub fn read(path: &Path) -> Result<Vec<ReceiptRecord>> {
+        let storage = |err: &dyn std::fmt::Display| {
+            MyTokenError::Storage(format!("{}: {err}", path.display()))
+        };
+        let contents = std::fs::read_to_string(path).map_err(|err| storage(&err))?;
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|err| storage(&err)))
+            .collect()
+    }
+}
+
+impl ReceiptStore for JsonLinesStore {
+    fn append(&self, record: &ReceiptRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record).map_err(MyTokenError::storage)?;
+        line.push('\n');
+
+        let _writing = self.writing.lock().unwrap();
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| {
+                file.write_all(line.as_bytes())?;
+                file.sync_data()
+            })
+            .map_err(|err| MyTokenError::Storage(format!("{}: {err}", self.path.display())))
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    // Add a mined write to the receipt store, if there is one
+    pub(crate) async fn record_receipt(
+        &self,
+        receipt: &TransactionReceipt,
+        summary: &TxSummary,
+        broadcast: &Broadcast,
+    ) -> Result<()> {
+        let Some(store) = self.receipt_store() else {
+            return Ok(());
+        };
+
+        let data = match broadcast.data() {
+            Some(data) => data,
+            None => {
+                let tx = self
+                    .client()
+                    .get_transaction(receipt.transaction_hash)
+                    .await
+                    .map_err(MyTokenError::middleware)?;
+                tx.map(|tx| tx.input).unwrap_or_default()
+            }
+        };
+        let (operation, args) = self.describe_call(&data);
+
+        store.append(&ReceiptRecord {
+            operation,
+            args,
+            to: receipt.to,
+            tx_hash: summary.tx_hash,
+            block_number: summary.block_number,
+            gas_used: summary.gas_used,
+            effective_gas_price: summary.effective_gas_price,
+            status: summary.status,
+            chain_id: summary.chain.map(|chain| chain.id()),
+        })
+    }
+
+    // The function `data` calls on the token and its arguments by name
+    pub(crate) fn describe_call(&self, data: &Bytes) -> (String, Map<String, Value>) {
+        let Some(selector) = data.get(..4) else {
+            return (String::new(), Map::new());
+        };
+        let function = self
+            .contract()
+            .abi()
+            .functions()
+            .find(|function| function.short_signature() == selector);
+        let Some(function) = function else {
+            return (Bytes::from(selector.to_vec()).to_string(), Map::new());
+        };
+
+        let tokens = function.decode_input(&data[4..]).unwrap_or_default();
+        let args = function
+            .inputs
+            .iter()
+            .zip(tokens)
+            .map(|(input, token)| (input.name.clone(), token_json(token)))
+            .collect();
+        (function.name.clone(), args)
+    }
+}
+
+fn token_json(token: Token) -> Value {
+    match token {
+        Token::Address(address) => Value::String(checksum(address)),
+        Token::Uint(value) => Value::String(value.to_string()),
+        Token::Int(value) => Value::String(I256::from_raw(value).to_string()),
+        Token::Bool(value) => Value::Bool(value),
+        Token::String(value) => Value::String(value),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => {
+            Value::String(Bytes::from(bytes).to_string())
+        }
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.into_iter().map(token_json).collect())
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use ethers::{
+    providers::Middleware,
+    types::{Bytes, TxHash, transaction::eip2718::TypedTransaction},
+};
+
+use crate::{MyTokenClient, MyTokenError, Result, http_provider};
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    // Sign `tx` with the middleware's signer and hand it to the relay at
+    // `url` rather than the node, keeping it out of the public mempool.
+    // Returns its hash and the signed bytes.
+    pub(crate) async fn relay(&self, url: &str, tx: &TypedTransaction) -> Result<(TxHash, Bytes)> {
+        let from = match tx.from() {
+            Some(from) => *from,
+            None => self.sender()?,
+        };
+        let signature = self
+            .client()
+            .sign_transaction(tx, from)
+            .await
+            .map_err(MyTokenError::middleware)?;
+        let raw = tx.rlp_signed(&signature);
+        let tx_hash = self.relay_raw(url, raw.clone()).await?;
+        Ok((tx_hash, raw))
+    }
+
+    // Submit an already signed transaction to the relay at `url`
+    pub(crate) async fn relay_raw(&self, url: &str, raw: Bytes) -> Result<TxHash> {
+        let relay = http_provider(url)?;
+        Ok(*relay.send_raw_transaction(raw).await?)
+    }
+
+    // Whether the relay at `url` still has `tx_hash` pending. A relay that
+    // can't be asked is taken to have it, so the transaction is waited for
+    // rather than sent again
+    pub(crate) async fn relay_knows(&self, url: &str, tx_hash: TxHash) -> bool {
+        let Ok(relay) = http_provider(url) else {
+            return true;
+        };
+        relay
+            .get_transaction(tx_hash)
+            .await
+            .map_or(true, |tx| tx.is_some())
+    }
+}
@@ -0,0 +1,121 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    types::{TxHash, transaction::eip2718::TypedTransaction},
+};
+
+use crate::{MyTokenClient, MyTokenError, Result, TxStatus, TxSummary, client::Broadcast};
+
+/// Submits transactions and waits for them, so flows built on top can run
+/// against [`MockTxSender`] instead of a node.
+///
+/// [`MyTokenClient`] implements it with its gas strategy and usual receipt
+/// handling.
+#[async_trait]
+pub trait TxSender: Send + Sync {
+    /// Broadcast `tx`, returning its hash without waiting for it to be mined.
+    async fn send(&self, tx: TypedTransaction) -> Result<TxHash>;
+
+    /// Wait for a transaction returned by [`send`](Self::send) to be mined.
+    async fn wait(&self, tx_hash: TxHash) -> Result<TxSummary>;
+
+    /// Whether `send` only simulates transactions, so nothing it returns is
+    /// ever mined. False unless implemented.
+    fn is_dry_run(&self) -> bool {
+        false
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> TxSender for MyTokenClient<M> {
+    async fn send(&self, tx: TypedTransaction) -> Result<TxHash> {
+        if self.is_dry_run() {
+            return Ok(self.dry_run(&tx).await?.tx_hash);
+        }
+        let mut tx = tx;
+        self.prepare(&mut tx).await?;
+        if let Some(relay) = self.network().relay_url.as_deref() {
+            return Ok(self.relay(relay, &tx).await?.0);
+        }
+
+        let client = self.client();
+        let pending = client
+            .send_transaction(tx, None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+        Ok(*pending)
+    }
+
+    async fn wait(&self, tx_hash: TxHash) -> Result<TxSummary> {
+        // What `send` returns for a write it only simulated
+        if let Some(summary) = self.simulated_summary(tx_hash) {
+            return Ok(summary);
+        }
+        self.confirm(tx_hash, Broadcast::Untracked).await
+    }
+
+    fn is_dry_run(&self) -> bool {
+        MyTokenClient::is_dry_run(self)
+    }
+}
+
+/// A [`TxSender`] that records what it is given and mines nothing.
+///
+/// Each `wait` answers with the next summary queued by
+/// [`push_summary`](Self::push_summary), or a successful one for the awaited
+/// hash once the queue is empty. A queued [`TxStatus::Reverted`] summary is
+/// answered with [`MyTokenError::Reverted`] for the awaited hash instead, as
+/// [`MyTokenClient`]'s `wait` fails on a revert.
+#[derive(Debug, Default)]
+pub struct MockTxSender {
+    sent: Mutex<Vec<TypedTransaction>>,
+    waited: Mutex<Vec<TxHash>>,
+    summaries: Mutex<VecDeque<TxSummary>>,
+}
+
+impl MockTxSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_summary(&self, summary: TxSummary) {
+        self.summaries.lock().unwrap().push_back(summary);
+    }
+
+    /// Every transaction passed to `send`, in order.
+    pub fn sent(&self) -> Vec<TypedTransaction> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Every hash passed to `wait`, in order.
+    pub fn waited(&self) -> Vec<TxHash> {
+        self.waited.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl TxSender for MockTxSender {
+    async fn send(&self, tx: TypedTransaction) -> Result<TxHash> {
+        let tx_hash = tx.sighash();
+        self.sent.lock().unwrap().push(tx);
+        Ok(tx_hash)
+    }
+
+    async fn wait(&self, tx_hash: TxHash) -> Result<TxSummary> {
+        self.waited.lock().unwrap().push(tx_hash);
+        let canned = self.summaries.lock().unwrap().pop_front();
+        if canned
+            .as_ref()
+            .is_some_and(|summary| summary.status == TxStatus::Reverted)
+        {
+            return Err(MyTokenError::Reverted(tx_hash));
+        }
+        Ok(canned.unwrap_or_else(|| TxSummary {
+            tx_hash,
+            status: TxStatus::Success,
+            ..TxSummary::skipped()
+        }))
+    }
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use ethers::types::{Address, TxHash, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{MyTokenError, TokenApi, TxStatus, checksum};
+
+/// The token's metadata and supply, as served at `GET /info`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenInfo {
+    /// Checksummed.
+    pub address: String,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    /// In raw units, written out in decimal.
+    pub total_supply: String,
+}
+
+/// An account's balance, as served at `GET /balance/{address}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceResponse {
+    /// Checksummed.
+    pub address: String,
+    /// In raw units, written out in decimal.
+    pub balance: String,
+}
+
+/// The body of `POST /transfer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferRequest {
+    pub to: Address,
+    /// In raw units, written out in decimal.
+    pub amount: String,
+}
+
+/// A mined transfer, as answered by `POST /transfer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferResponse {
+    pub tx_hash: TxHash,
+    pub block_number: u64,
+    pub status: TxStatus,
+}
+
+/// Serves a token over HTTP as JSON, for frontends that aren't written in
+/// Rust:
+///
+/// - `GET /info` answers a [`TokenInfo`]
+/// - `GET /balance/{address}` answers a [`BalanceResponse`]
+/// - `POST /transfer` takes a [`TransferRequest`], sends it with the
+///   client's signer and answers a [`TransferResponse`] once mined
+///
+/// Transfers spend the signer's tokens, so they need an
+/// `Authorization: Bearer <token>` header matching the token set with
+/// [`with_write_token`](Self::with_write_token), and are refused outright
+/// without one. Failures answer `{"error": "..."}`.
+pub struct TokenServer {
+    token: Arc<dyn TokenApi>,
+    write_token: Option<String>,
+}
+
+impl TokenServer {
+    pub fn new(token: Arc<dyn TokenApi>) -> Self {
+        Self {
+            token,
+            write_token: None,
+        }
+    }
+
+    /// Bearer token that `POST /transfer` must carry.
+    pub fn with_write_token(mut self, token: impl Into<String>) -> Self {
+        self.write_token = Some(token.into());
+        self
+    }
+
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/info", get(info))
+            .route("/balance/{address}", get(balance))
+            .route("/transfer", post(transfer))
+            .with_state(Arc::new(self))
+    }
+
+    /// Serve on `listener` until the task is dropped or accepting fails.
+    pub async fn serve(self, listener: tokio::net::TcpListener) -> std::io::Result<()> {
+        axum::serve(listener, self.router()).await
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = &self.write_token else {
+            return false;
+        };
+        let given = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        given.is_some_and(|given| constant_time_eq(given.as_bytes(), expected.as_bytes()))
+    }
+}
+
+// Compare without returning early, so response timing doesn't leak how much
+// of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+struct ApiError(StatusCode, String);
+
+impl From<MyTokenError> for ApiError {
+    fn from(err: MyTokenError) -> Self {
+        let status = match &err {
+            MyTokenError::InvalidRecipient(_)
+            | MyTokenError::ZeroAmount
+            | MyTokenError::InvalidAmount(_)
+            | MyTokenError::AmountOverflow => StatusCode::BAD_REQUEST,
+            err if err.is_revert() || matches!(err, MyTokenError::Reverted(_)) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        Self(status, err.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.1 });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+async fn info(State(server): State<Arc<TokenServer>>) -> ApiResult<TokenInfo> {
+    let token = &server.token;
+    let (name, symbol, decimals, total_supply) = futures::try_join!(
+        token.name(),
+        token.symbol(),
+        token.decimals(),
+        token.total_supply(),
+    )?;
+    Ok(Json(TokenInfo {
+        address: checksum(token.address()),
+        name,
+        symbol,
+        decimals,
+        total_supply: total_supply.to_string(),
+    }))
+}
+
+async fn balance(
+    State(server): State<Arc<TokenServer>>,
+    Path(address): Path<String>,
+) -> ApiResult<BalanceResponse> {
+    let account: Address = address.parse().map_err(|_| {
+        ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("{address:?} isn't an address"),
+        )
+    })?;
+    let balance = server.token.balance_of(account).await?;
+    Ok(Json(BalanceResponse {
+        address: checksum(account),
+        balance: balance.to_string(),
+    }))
+}
+
+async fn transfer(
+    State(server): State<Arc<TokenServer>>,
+    headers: HeaderMap,
+    Json(request): Json<TransferRequest>,
+) -> ApiResult<TransferResponse> {
+    if !server.authorized(&headers) {
+        return Err(ApiError(
+            StatusCode::UNAUTHORIZED,
+            "transfers need a valid bearer token".to_owned(),
+        ));
+    }
+    let amount = U256::from_dec_str(&request.amount)
+        .map_err(|_| MyTokenError::InvalidAmount(request.amount.clone()))?;
+    let summary = server.token.transfer(request.to, amount).await?;
+    Ok(Json(TransferResponse {
+        tx_hash: summary.tx_hash,
+        block_number: summary.block_number,
+        status: summary.status,
+    }))
+}
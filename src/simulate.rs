@@ -0,0 +1,206 @@
+use crate::{MyTokenClient, Result};
+use ethers::{
+    abi::{self, Token},
+    providers::{Middleware, call_raw::RawCall, call_raw::spoof},
+    types::{Address, H256, U256, transaction::eip2718::TypedTransaction},
+    utils::keccak256,
+};
+
+// Storage slots of OpenZeppelin's ERC20 `_balances` and `_allowances`, which
+// come first in the token's layout
+const BALANCES_SLOT: u64 = 0;
+const ALLOWANCES_SLOT: u64 = 1;
+
+/// One step of a [`simulate_bundle`](MyTokenClient::simulate_bundle) run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimCall {
+    Transfer {
+        from: Address,
+        to: Address,
+        amount: U256,
+    },
+    Approve {
+        owner: Address,
+        spender: Address,
+        amount: U256,
+    },
+    TransferFrom {
+        spender: Address,
+        from: Address,
+        to: Address,
+        amount: U256,
+    },
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Dry-run `calls` in order against the latest state, reporting whether
+    /// each would succeed. Nothing is broadcast.
+    ///
+    /// Every call sees the balances and allowances left by the successful calls
+    /// before it, carried forward as `eth_call` state overrides, so the node
+    /// must support the `stateOverride` parameter. Transfers take the token's
+    /// transfer fee for the owner as they would if sent. Effects other than
+    /// balances and allowances, such as an approval clearing an allowance's
+    /// expiry, aren't carried forward.
+    pub async fn simulate_bundle(&self, calls: Vec<SimCall>) -> Result<Vec<bool>> {
+        let mut state = spoof::state();
+        let mut results = Vec::with_capacity(calls.len());
+
+        for call in calls {
+            let succeeded = self.succeeds(call, &state).await?;
+            if succeeded {
+                self.apply(call, &mut state).await?;
+            }
+            results.push(succeeded);
+        }
+
+        Ok(results)
+    }
+
+    /// Dry-run `call` as if `account` held `balance` tokens, reporting whether
+    /// it would succeed. Nothing is broadcast.
+    ///
+    /// The balance is written straight into the token's storage with an
+    /// `eth_call` state override, which assumes OpenZeppelin's `_balances`
+    /// mapping sits in storage slot 0, as it does in `MyToken`. The node must
+    /// support the `stateOverride` parameter.
+    pub async fn simulate_with_balance(
+        &self,
+        account: Address,
+        balance: U256,
+        call: SimCall,
+    ) -> Result<bool> {
+        let mut state = spoof::state();
+        self.store(&mut state, balance_slot(account), balance);
+        self.succeeds(call, &state).await
+    }
+
+    async fn succeeds(&self, call: SimCall, state: &spoof::State) -> Result<bool> {
+        let (sender, mut tx) = match call {
+            SimCall::Transfer { from, to, amount } => {
+                (from, self.contract().transfer(to, amount).tx)
+            }
+            SimCall::Approve {
+                owner,
+                spender,
+                amount,
+            } => (owner, self.contract().approve(spender, amount).tx),
+            SimCall::TransferFrom {
+                spender,
+                from,
+                to,
+                amount,
+            } => (spender, self.contract().transfer_from(from, to, amount).tx),
+        };
+        tx.set_from(sender);
+
+        match self.call_with(&tx, state).await {
+            // A `false` return fails the call as much as a revert does
+            Ok(returned) => Ok(returned == abi::encode(&[Token::Bool(true)])),
+            Err(err) if err.is_revert() => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Carry the balance and allowance changes of a successful call into `state`
+    async fn apply(&self, call: SimCall, state: &mut spoof::State) -> Result<()> {
+        let (from, to, amount) = match call {
+            SimCall::Approve {
+                owner,
+                spender,
+                amount,
+            } => {
+                self.store(state, allowance_slot(owner, spender), amount);
+                return Ok(());
+            }
+            SimCall::TransferFrom {
+                spender,
+                from,
+                to,
+                amount,
+            } => {
+                // Spending your own tokens leaves your allowances alone
+                let allowance = self.allowance_in(state, from, spender).await?;
+                if spender != from && allowance != U256::MAX {
+                    self.store(state, allowance_slot(from, spender), allowance - amount);
+                }
+                (from, to, amount)
+            }
+            SimCall::Transfer { from, to, amount } => (from, to, amount),
+        };
+
+        // As in the token's `_update`, the recipient gets `amount` less the
+        // transfer fee and the owner gets the fee
+        if from != to {
+            let fee = self.fee_in(state, from, to, amount).await?;
+            let from_balance = self.balance_in(state, from).await?;
+            let to_balance = self.balance_in(state, to).await?;
+            self.store(state, balance_slot(from), from_balance - amount);
+            self.store(state, balance_slot(to), to_balance + amount - fee);
+            if !fee.is_zero() {
+                let owner = self.owner().await?;
+                let owner_balance = self.balance_in(state, owner).await?;
+                self.store(state, balance_slot(owner), owner_balance + fee);
+            }
+        }
+        Ok(())
+    }
+
+    async fn fee_in(
+        &self,
+        state: &spoof::State,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<U256> {
+        let tx = self.contract().transfer_fee(from, to, amount).tx;
+        let returned = self.call_with(&tx, state).await?;
+        Ok(U256::from_big_endian(&returned))
+    }
+
+    async fn balance_in(&self, state: &spoof::State, account: Address) -> Result<U256> {
+        let tx = self.contract().balance_of(account).tx;
+        let returned = self.call_with(&tx, state).await?;
+        Ok(U256::from_big_endian(&returned))
+    }
+
+    async fn allowance_in(
+        &self,
+        state: &spoof::State,
+        owner: Address,
+        spender: Address,
+    ) -> Result<U256> {
+        let tx = self.contract().allowance(owner, spender).tx;
+        let returned = self.call_with(&tx, state).await?;
+        Ok(U256::from_big_endian(&returned))
+    }
+
+    async fn call_with(&self, tx: &TypedTransaction, state: &spoof::State) -> Result<Vec<u8>> {
+        let client = self.client();
+        let returned = client.provider().call_raw(tx).state(state).await?;
+        Ok(returned.to_vec())
+    }
+
+    fn store(&self, state: &mut spoof::State, slot: H256, value: U256) {
+        let mut word = [0u8; 32];
+        value.to_big_endian(&mut word);
+        state.account(self.address()).store(slot, H256(word));
+    }
+}
+
+// Solidity puts `mapping[key]` at `keccak256(key . slot)`
+fn mapping_slot(key: Address, slot: H256) -> H256 {
+    H256(keccak256(abi::encode(&[
+        Token::Address(key),
+        Token::FixedBytes(slot.as_bytes().to_vec()),
+    ])))
+}
+
+fn balance_slot(account: Address) -> H256 {
+    mapping_slot(account, H256::from_low_u64_be(BALANCES_SLOT))
+}
+
+fn allowance_slot(owner: Address, spender: Address) -> H256 {
+    let inner = mapping_slot(owner, H256::from_low_u64_be(ALLOWANCES_SLOT));
+    mapping_slot(spender, inner)
+}
@@ -0,0 +1,197 @@
+use std::{fmt, future::Future};
+
+use ethers::{
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, TransactionRequest, U256, transaction::eip2718::TypedTransaction},
+};
+
+use crate::{MyTokenClient, MyTokenError, Result, TxSender, TxStatus};
+
+/// Raw units moved by each write of a [`smoke_test`](MyTokenClient::smoke_test).
+const SMOKE_AMOUNT: u64 = 1;
+
+/// One check of a [`smoke_test`](MyTokenClient::smoke_test), with why it
+/// failed if it did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmokeStep {
+    pub name: &'static str,
+    pub failure: Option<String>,
+}
+
+impl SmokeStep {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Every check a [`smoke_test`](MyTokenClient::smoke_test) ran, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SmokeReport {
+    pub steps: Vec<SmokeStep>,
+}
+
+impl SmokeReport {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(SmokeStep::passed)
+    }
+
+    // Run `check`, recording how it went under `name`
+    async fn run(&mut self, name: &'static str, check: impl Future<Output = Result<()>>) {
+        let failure = check.await.err().map(|err| err.to_string());
+        self.steps.push(SmokeStep { name, failure });
+    }
+}
+
+impl fmt::Display for SmokeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            match &step.failure {
+                None => writeln!(f, "PASS {}", step.name)?,
+                Some(failure) => writeln!(f, "FAIL {}: {failure}", step.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// A step failing for a reason other than an error, such as a wrong balance
+fn check(ok: bool, failure: impl FnOnce() -> String) -> Result<()> {
+    if ok {
+        Ok(())
+    } else {
+        Err(MyTokenError::Contract(failure()))
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Exercise the token's ERC-20 basics against whatever deployment this
+    /// client points at: metadata reads, a transfer, an approval spent with
+    /// `transferFrom`, and calls that must revert for want of balance or
+    /// allowance. A step that fails is recorded in the report and the rest
+    /// still run.
+    ///
+    /// The allowance is given to a throwaway account, funded by the signer
+    /// with enough ETH for one `transferFrom` signed with its key, so the
+    /// allowance really is spent rather than skipped as it is for an owner
+    /// moving its own tokens. It costs gas, and leaves two of the token's
+    /// smallest units with a random address and any ETH the spender didn't use
+    /// with the spender. The reverts are only checked with `eth_call`.
+    pub async fn smoke_test(&self) -> Result<SmokeReport> {
+        let sender = self.sender()?;
+        let recipient = Address::random();
+        let spender =
+            LocalWallet::new(&mut rand::thread_rng()).with_chain_id(self.chain_id().await?);
+        let amount = U256::from(SMOKE_AMOUNT);
+        let mut report = SmokeReport::default();
+
+        report
+            .run("metadata", async {
+                let name = self.name().await?;
+                let symbol = self.symbol().await?;
+                self.decimals().await?;
+                check(!name.is_empty() && !symbol.is_empty(), || {
+                    format!("name {name:?} or symbol {symbol:?} is empty")
+                })
+            })
+            .await;
+
+        report
+            .run("transfer", async {
+                self.transfer(recipient, amount).await?;
+                let received = self.balance_of(recipient).await?;
+                check(received == amount, || {
+                    format!("recipient holds {received} after a transfer of {amount}")
+                })
+            })
+            .await;
+
+        report
+            .run("approve", async {
+                self.approve(spender.address(), amount).await?;
+                let allowance = self.allowance(sender, spender.address()).await?;
+                check(allowance == amount, || {
+                    format!("allowance is {allowance} after approving {amount}")
+                })
+            })
+            .await;
+
+        report
+            .run("transferFrom", async {
+                let signed = self
+                    .sign_as(
+                        &spender,
+                        self.contract().transfer_from(sender, recipient, amount).tx,
+                    )
+                    .await?;
+                self.send_raw(signed).await?;
+                let received = self.balance_of(recipient).await?;
+                check(received == amount * 2, || {
+                    format!("recipient holds {received} after a second transfer of {amount}")
+                })?;
+                let allowance = self.allowance(sender, spender.address()).await?;
+                check(allowance.is_zero(), || {
+                    format!("allowance is {allowance} after spending all {amount} of it")
+                })
+            })
+            .await;
+
+        report
+            .run("insufficient balance reverts", async {
+                let balance = self.balance_of(sender).await?;
+                let call = self.contract().transfer(recipient, balance + 1);
+                check(call.call().await.is_err(), || {
+                    "transfer of more than the balance would succeed".to_owned()
+                })
+            })
+            .await;
+
+        report
+            .run("insufficient allowance reverts", async {
+                // Nothing has been approved by the random recipient
+                let call = self.contract().transfer_from(recipient, sender, amount);
+                check(call.call().await.is_err(), || {
+                    "transferFrom without an allowance would succeed".to_owned()
+                })
+            })
+            .await;
+
+        Ok(report)
+    }
+
+    // Sign `tx` with `wallet`, first sending the wallet the ETH it needs for
+    // gas from this client's signer
+    async fn sign_as(&self, wallet: &LocalWallet, mut tx: TypedTransaction) -> Result<Bytes> {
+        let client = self.client();
+        tx.set_from(wallet.address());
+        tx.set_chain_id(wallet.chain_id());
+        let (gas, gas_price, nonce) = futures::try_join!(
+            client.estimate_gas(&tx, None),
+            client.get_gas_price(),
+            client.get_transaction_count(wallet.address(), None),
+        )
+        .map_err(MyTokenError::middleware)?;
+        let tx: TypedTransaction = TransactionRequest::new()
+            .from(wallet.address())
+            .to(self.address())
+            .data(tx.data().cloned().unwrap_or_default())
+            .chain_id(wallet.chain_id())
+            .nonce(nonce)
+            .gas(gas)
+            .gas_price(gas_price)
+            .into();
+
+        let funding = TransactionRequest::new()
+            .to(wallet.address())
+            .value(gas * gas_price);
+        let funded = TxSender::wait(self, TxSender::send(self, funding.into()).await?).await?;
+        if funded.status == TxStatus::Reverted {
+            return Err(MyTokenError::Reverted(funded.tx_hash));
+        }
+
+        let signature = wallet
+            .sign_transaction_sync(&tx)
+            .map_err(|err| MyTokenError::Signing(err.to_string()))?;
+        Ok(tx.rlp_signed(&signature))
+    }
+}
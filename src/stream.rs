@@ -0,0 +1,63 @@
+use crate::{MyToken, MyTokenError, Result};
+use ethers::{
+    contract::{EthLogDecode, builders::Event},
+    providers::{Provider, Ws},
+    types::Address,
+};
+use futures::{Stream, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Pause before resubscribing after the WebSocket drops.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+type WsEvent<D> = Event<Arc<Provider<Ws>>, Provider<Ws>, D>;
+
+/// Live events of the token at `token`, subscribed over `ws_url` with the
+/// filter `select` builds.
+///
+/// The stream only ends once `cancel` fires: a failed or dropped subscription
+/// is reopened after [`RECONNECT_DELAY`]. Failures to connect or subscribe, and
+/// events that can't be decoded, are yielded as errors along the way.
+pub(crate) fn events<D, F>(
+    ws_url: String,
+    token: Address,
+    select: F,
+    cancel: CancellationToken,
+) -> impl Stream<Item = Result<D>>
+where
+    D: EthLogDecode,
+    F: Fn(&MyToken<Provider<Ws>>) -> WsEvent<D>,
+{
+    async_stream::stream! {
+        'reconnect: loop {
+            match Provider::<Ws>::connect(&ws_url).await {
+                Ok(provider) => {
+                    let contract = MyToken::new(token, Arc::new(provider));
+                    let filter = select(&contract);
+                    match filter.subscribe().await {
+                        Ok(mut events) => {
+                            loop {
+                                let event = tokio::select! {
+                                    _ = cancel.cancelled() => break 'reconnect,
+                                    event = events.next() => event,
+                                };
+                                let Some(event) = event else { break };
+                                yield event.map_err(MyTokenError::from);
+                            }
+                            // Closed by the node, so subscribe again
+                        }
+                        Err(err) => yield Err(err.into()),
+                    }
+                }
+                Err(err) => yield Err(err.into()),
+            }
+
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{Chain, MyTokenError, Result, from_token_units};
+use ethers::{
+    providers::{JsonRpcClient, PendingTransaction},
+    types::{TransactionReceipt, TxHash, U64, U256},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Outcome of a mined transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    Success,
+    Reverted,
+    /// Nothing was sent, e.g. a zero-amount transfer the client skipped.
+    Skipped,
+    /// Nothing was sent, as the client is in dry-run mode, but the write
+    /// would have succeeded when simulated.
+    Simulated,
+}
+
+/// The parts of a transaction receipt callers usually care about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxSummary {
+    pub tx_hash: TxHash,
+    pub block_number: u64,
+    pub gas_used: U256,
+    pub effective_gas_price: U256,
+    pub status: TxStatus,
+    /// The chain it was sent on, when the client could tell.
+    pub chain: Option<Chain>,
+}
+
+impl TxSummary {
+    /// Summary for a write the client decided not to send.
+    pub fn skipped() -> Self {
+        Self {
+            tx_hash: TxHash::zero(),
+            block_number: 0,
+            gas_used: U256::zero(),
+            effective_gas_price: U256::zero(),
+            status: TxStatus::Skipped,
+            chain: None,
+        }
+    }
+
+    /// Summary for a write simulated in dry-run mode instead of sent, before
+    /// the dry run sets `tx_hash` to the hash of the unsigned transaction.
+    pub fn simulated() -> Self {
+        Self {
+            status: TxStatus::Simulated,
+            ..Self::skipped()
+        }
+    }
+}
+
+impl TxSummary {
+    /// Explorer page for the transaction, when its chain has an explorer.
+    pub fn explorer_url(&self) -> Option<String> {
+        self.chain?.explorer_tx_url(self.tx_hash)
+    }
+
+    /// What the transaction cost its sender in wei, `gas_used` times
+    /// `effective_gas_price`. Zero for writes that were never sent.
+    pub fn gas_cost_wei(&self) -> U256 {
+        self.gas_used.saturating_mul(self.effective_gas_price)
+    }
+
+    /// [`gas_cost_wei`](Self::gas_cost_wei) in ETH without trailing zeros,
+    /// e.g. `"0.000021"` for 21,000 gas at 1 gwei.
+    pub fn gas_cost_eth_string(&self) -> String {
+        from_token_units(self.gas_cost_wei(), 18).expect("18 decimals is supported")
+    }
+}
+
+impl fmt::Display for TxSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            TxStatus::Skipped => return write!(f, "skipped, nothing sent"),
+            TxStatus::Simulated => return write!(f, "simulated, nothing sent"),
+            TxStatus::Success => write!(
+                f,
+                "tx {:?} mined in block {}",
+                self.tx_hash, self.block_number
+            )?,
+            TxStatus::Reverted => write!(
+                f,
+                "tx {:?} reverted in block {}",
+                self.tx_hash, self.block_number
+            )?,
+        }
+        if let Some(url) = self.explorer_url() {
+            write!(f, " ({url})")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&TransactionReceipt> for TxSummary {
+    fn from(receipt: &TransactionReceipt) -> Self {
+        let status = if receipt.status == Some(U64::one()) {
+            TxStatus::Success
+        } else {
+            TxStatus::Reverted
+        };
+
+        Self {
+            tx_hash: receipt.transaction_hash,
+            block_number: receipt.block_number.unwrap_or_default().as_u64(),
+            gas_used: receipt.gas_used.unwrap_or_default(),
+            effective_gas_price: receipt.effective_gas_price.unwrap_or_default(),
+            status,
+            chain: None,
+        }
+    }
+}
+
+/// Wait for already-broadcast transactions together, instead of one after the
+/// other, and summarize them in the order given.
+///
+/// Every transaction is awaited even when some fail; the error is then the
+/// first failure in submission order, saying which transaction it was.
+pub async fn wait_all<P: JsonRpcClient>(
+    pendings: Vec<PendingTransaction<'_, P>>,
+) -> Result<Vec<TxSummary>> {
+    let waits = pendings.into_iter().map(|pending| async move {
+        let tx_hash = pending.tx_hash();
+        let result = match pending.await {
+            Ok(Some(receipt)) if receipt.status == Some(U64::one()) => {
+                Ok(TxSummary::from(&receipt))
+            }
+            Ok(Some(_)) => Err(MyTokenError::Reverted(tx_hash)),
+            Ok(None) => Err(MyTokenError::NoReceipt(tx_hash)),
+            Err(err) => Err(err.into()),
+        };
+        (tx_hash, result)
+    });
+
+    futures::future::join_all(waits)
+        .await
+        .into_iter()
+        .enumerate()
+        .map(|(index, (tx_hash, result))| {
+            result.map_err(|source| MyTokenError::Batch {
+                index,
+                tx_hash,
+                source: Box::new(source),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,77 @@
+use std::future::Future;
+
+use crate::Result;
+
+/// Counter of reads and writes, labelled by `operation`, the token function
+/// called.
+#[cfg(feature = "metrics")]
+pub const OPERATIONS_METRIC: &str = "my_token_operations_total";
+
+/// Histogram of how long each read or write took in seconds, from the first
+/// request to the result, labelled by `operation`.
+#[cfg(feature = "metrics")]
+pub const LATENCY_METRIC: &str = "my_token_operation_seconds";
+
+/// Counter of reads and writes that reverted, labelled by `operation` and
+/// `reason`: the name of the [`MyTokenError`](crate::MyTokenError) variant
+/// the revert became, such as `InsufficientBalance` or `ExecutionReverted`
+/// for a revert string, or `other`. Revert strings themselves are left out
+/// so the labels stay few.
+#[cfg(feature = "metrics")]
+pub const REVERTS_METRIC: &str = "my_token_reverts_total";
+
+// Run `work` as `operation`, counting and timing it, and counting it again by
+// reason should it revert. Recorded with whatever `metrics` recorder is
+// installed
+#[cfg(feature = "metrics")]
+pub(crate) async fn observe<T>(
+    operation: &str,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let started = std::time::Instant::now();
+    let result = work.await;
+
+    let operation = operation.to_owned();
+    metrics::counter!(OPERATIONS_METRIC, "operation" => operation.clone()).increment(1);
+    metrics::histogram!(LATENCY_METRIC, "operation" => operation.clone())
+        .record(started.elapsed().as_secs_f64());
+    if let Err(err) = &result
+        && let Some(reason) = revert_reason(err)
+    {
+        metrics::counter!(REVERTS_METRIC, "operation" => operation, "reason" => reason)
+            .increment(1);
+    }
+    result
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) async fn observe<T>(
+    _operation: &str,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    work.await
+}
+
+#[cfg(feature = "metrics")]
+fn revert_reason(err: &crate::MyTokenError) -> Option<&'static str> {
+    use crate::MyTokenError;
+
+    if !err.is_revert() && !matches!(err, MyTokenError::Reverted(_)) {
+        return None;
+    }
+    let reason = match err {
+        MyTokenError::ExecutionReverted(_) => "ExecutionReverted",
+        MyTokenError::Reverted(_) => "Reverted",
+        MyTokenError::InsufficientBalance { .. } => "InsufficientBalance",
+        MyTokenError::AccountFrozen(_) => "AccountFrozen",
+        MyTokenError::AllowanceExpired { .. } => "AllowanceExpired",
+        MyTokenError::BatchLengthMismatch { .. } => "BatchLengthMismatch",
+        MyTokenError::TransferFeeTooHigh(_) => "TransferFeeTooHigh",
+        MyTokenError::EthTransferFailed(_) => "EthTransferFailed",
+        MyTokenError::CapBelowSupply { .. } => "CapBelowSupply",
+        MyTokenError::CapChangeNotReady(_) => "CapChangeNotReady",
+        MyTokenError::NoPendingCapChange => "NoPendingCapChange",
+        _ => "other",
+    };
+    Some(reason)
+}
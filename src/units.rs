@@ -0,0 +1,63 @@
+use crate::{MyTokenError, Result};
+use ethers::{types::U256, utils::format_units};
+
+// 10^77 is the largest power of ten a U256 can hold, so a token reporting
+// more decimals than this can't have even one whole token
+const MAX_DECIMALS: u8 = 77;
+
+fn check_decimals(decimals: u8) -> Result<()> {
+    if decimals > MAX_DECIMALS {
+        return Err(MyTokenError::UnsupportedDecimals(decimals));
+    }
+    Ok(())
+}
+
+/// Render a raw token amount in whole-token units, without trailing zeros,
+/// e.g. `1500000000000000000` with 18 decimals is `"1.5"`.
+///
+/// A broken or malicious token can report any `decimals` up to 255; more than
+/// 77 is an [`UnsupportedDecimals`](MyTokenError::UnsupportedDecimals).
+pub fn from_token_units(raw: U256, decimals: u8) -> Result<String> {
+    check_decimals(decimals)?;
+    let formatted = format_units(raw, u32::from(decimals))
+        .map_err(|_| MyTokenError::UnsupportedDecimals(decimals))?;
+    if formatted.contains('.') {
+        Ok(formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string())
+    } else {
+        Ok(formatted)
+    }
+}
+
+/// Parse a whole-token amount such as `"1.5"` into raw units, the inverse of
+/// [`from_token_units`].
+///
+/// The conversion is exact: more fractional digits than `decimals` is an
+/// [`InvalidAmount`](MyTokenError::InvalidAmount) rather than being rounded,
+/// and a result above `U256::MAX` is an
+/// [`AmountOverflow`](MyTokenError::AmountOverflow). As there, more than 77
+/// `decimals` is an [`UnsupportedDecimals`](MyTokenError::UnsupportedDecimals).
+pub fn to_token_units(amount: &str, decimals: u8) -> Result<U256> {
+    check_decimals(decimals)?;
+    let invalid = || MyTokenError::InvalidAmount(amount.to_owned());
+    let trimmed = amount.trim();
+    let (whole, fraction) = trimmed.split_once('.').unwrap_or((trimmed, ""));
+
+    let all_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty())
+        || !all_digits(whole)
+        || !all_digits(fraction)
+        || fraction.len() > usize::from(decimals)
+    {
+        return Err(invalid());
+    }
+
+    let digits = format!("{whole}{fraction:0<width$}", width = usize::from(decimals));
+    digits.bytes().try_fold(U256::zero(), |raw, digit| {
+        raw.checked_mul(U256::from(10))
+            .and_then(|raw| raw.checked_add(U256::from(digit - b'0')))
+            .ok_or(MyTokenError::AmountOverflow)
+    })
+}
@@ -0,0 +1,227 @@
+use crate::{MyTokenClient, MyTokenError, Result, TxStatus, TxSummary};
+use ethers::{
+    abi::{self, AbiEncode, Token},
+    contract::parse_log,
+    prelude::abigen,
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, H160, H256, U256},
+    utils::keccak256,
+};
+
+abigen!(
+    EntryPoint,
+    r#"[
+        struct UserOperation { address sender; uint256 nonce; bytes initCode; bytes callData; uint256 callGasLimit; uint256 verificationGasLimit; uint256 preVerificationGas; uint256 maxFeePerGas; uint256 maxPriorityFeePerGas; bytes paymasterAndData; bytes signature; }
+        function handleOps(UserOperation[] ops, address beneficiary) external
+        function getNonce(address sender, uint192 key) external view returns (uint256)
+        function balanceOf(address account) external view returns (uint256)
+        event UserOperationEvent(bytes32 indexed userOpHash, address indexed sender, address indexed paymaster, uint256 nonce, bool success, uint256 actualGasCost, uint256 actualGasUsed)
+    ]"#,
+);
+
+abigen!(
+    SimpleAccount,
+    r#"[
+        function execute(address dest, uint256 value, bytes func) external
+    ]"#,
+);
+
+/// The ERC-4337 v0.6 EntryPoint, deployed at this address on mainnet and most
+/// other chains. Local chains and forks of chains without it need it deployed
+/// first.
+pub const ENTRY_POINT_V06: Address = H160([
+    0x5f, 0xf1, 0x37, 0xd4, 0xb0, 0xfd, 0xcd, 0x49, 0xdc, 0xa3, 0x0c, 0x7c, 0xf5, 0x7e, 0x57, 0x8a,
+    0x02, 0x6d, 0x27, 0x89,
+]);
+
+// Gas for a token transfer run through a smart account, and for validating
+// an ECDSA-signed account plus a paymaster, with some headroom
+const DEFAULT_CALL_GAS_LIMIT: u64 = 100_000;
+const DEFAULT_VERIFICATION_GAS_LIMIT: u64 = 200_000;
+const DEFAULT_PRE_VERIFICATION_GAS: u64 = 60_000;
+
+/// A paymaster paying for user operations through an EntryPoint, and the gas
+/// those operations are given.
+///
+/// Sponsored transfers need three contracts on the chain:
+///
+/// - an ERC-4337 v0.6 EntryPoint, usually [`ENTRY_POINT_V06`]
+/// - a smart account holding the tokens, already deployed, that runs calls
+///   with SimpleAccount's `execute(dest, value, func)` once its owner has
+///   signed the user operation hash as an Ethereum signed message
+/// - a paymaster with ETH deposited in the EntryPoint, willing to pay for the
+///   account's operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sponsor {
+    pub entry_point: Address,
+    pub paymaster: Address,
+    /// Appended to the paymaster's address in `paymasterAndData`, for
+    /// paymasters that want, say, their own signature over the operation.
+    pub paymaster_data: Bytes,
+    pub call_gas_limit: U256,
+    pub verification_gas_limit: U256,
+    pub pre_verification_gas: U256,
+}
+
+impl Sponsor {
+    pub fn new(entry_point: Address, paymaster: Address) -> Self {
+        Self {
+            entry_point,
+            paymaster,
+            paymaster_data: Bytes::new(),
+            call_gas_limit: DEFAULT_CALL_GAS_LIMIT.into(),
+            verification_gas_limit: DEFAULT_VERIFICATION_GAS_LIMIT.into(),
+            pre_verification_gas: DEFAULT_PRE_VERIFICATION_GAS.into(),
+        }
+    }
+
+    pub fn with_paymaster_data(mut self, data: impl Into<Bytes>) -> Self {
+        self.paymaster_data = data.into();
+        self
+    }
+
+    pub fn with_gas_limits(
+        mut self,
+        call: U256,
+        verification: U256,
+        pre_verification: U256,
+    ) -> Self {
+        self.call_gas_limit = call;
+        self.verification_gas_limit = verification;
+        self.pre_verification_gas = pre_verification;
+        self
+    }
+
+    fn paymaster_and_data(&self) -> Bytes {
+        [self.paymaster.as_bytes(), &self.paymaster_data[..]]
+            .concat()
+            .into()
+    }
+
+    /// The hash an account signs and the EntryPoint reports `op` by, binding
+    /// it to this EntryPoint and `chain_id`.
+    pub fn user_op_hash(&self, op: &UserOperation, chain_id: u64) -> H256 {
+        let packed = abi::encode(&[
+            Token::Address(op.sender),
+            Token::Uint(op.nonce),
+            Token::FixedBytes(keccak256(&op.init_code).to_vec()),
+            Token::FixedBytes(keccak256(&op.call_data).to_vec()),
+            Token::Uint(op.call_gas_limit),
+            Token::Uint(op.verification_gas_limit),
+            Token::Uint(op.pre_verification_gas),
+            Token::Uint(op.max_fee_per_gas),
+            Token::Uint(op.max_priority_fee_per_gas),
+            Token::FixedBytes(keccak256(&op.paymaster_and_data).to_vec()),
+        ]);
+        H256(keccak256(abi::encode(&[
+            Token::FixedBytes(keccak256(packed).to_vec()),
+            Token::Address(self.entry_point),
+            Token::Uint(chain_id.into()),
+        ])))
+    }
+}
+
+impl<M: Middleware + 'static> MyTokenClient<M> {
+    /// Sign a user operation moving `amount` of `account`'s tokens to `to`,
+    /// with gas paid by `sponsor`'s paymaster rather than the account.
+    ///
+    /// `account` is a deployed smart account with SimpleAccount's
+    /// `execute(dest, value, func)` and `owner` as its signer, signing the
+    /// user operation hash as an Ethereum signed message. Fees are this
+    /// chain's current EIP-1559 estimate.
+    pub async fn sign_sponsored_transfer(
+        &self,
+        sponsor: &Sponsor,
+        account: Address,
+        owner: &LocalWallet,
+        to: Address,
+        amount: U256,
+    ) -> Result<UserOperation> {
+        let transfer = self
+            .contract()
+            .transfer(to, amount)
+            .calldata()
+            .ok_or_else(|| MyTokenError::Decode("transfer calldata".into()))?;
+        let call_data = ExecuteCall {
+            dest: self.address(),
+            value: U256::zero(),
+            func: transfer,
+        }
+        .encode();
+
+        let nonce = EntryPoint::new(sponsor.entry_point, self.client())
+            .get_nonce(account, U256::zero())
+            .call()
+            .await?;
+        let (max_fee_per_gas, max_priority_fee_per_gas) = self
+            .client()
+            .estimate_eip1559_fees(None)
+            .await
+            .map_err(MyTokenError::middleware)?;
+
+        let mut op = UserOperation {
+            sender: account,
+            nonce,
+            init_code: Bytes::new(),
+            call_data: call_data.into(),
+            call_gas_limit: sponsor.call_gas_limit,
+            verification_gas_limit: sponsor.verification_gas_limit,
+            pre_verification_gas: sponsor.pre_verification_gas,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            paymaster_and_data: sponsor.paymaster_and_data(),
+            signature: Bytes::new(),
+        };
+        let hash = sponsor.user_op_hash(&op, self.chain_id().await?);
+        let signature = owner
+            .sign_message(hash)
+            .await
+            .map_err(|err| MyTokenError::Signing(err.to_string()))?;
+        op.signature = signature.to_vec().into();
+        Ok(op)
+    }
+
+    /// Submit `op` to `sponsor`'s EntryPoint with `handleOps`, acting as the
+    /// bundler: this client's signer sends the transaction and is repaid from
+    /// the paymaster's deposit to `beneficiary`.
+    ///
+    /// The EntryPoint doesn't revert when the operation's own call does, so
+    /// that is checked in its `UserOperationEvent` and reported as
+    /// [`MyTokenError::UserOpFailed`].
+    pub async fn submit_user_op(
+        &self,
+        sponsor: &Sponsor,
+        op: UserOperation,
+        beneficiary: Address,
+    ) -> Result<TxSummary> {
+        let hash = sponsor.user_op_hash(&op, self.chain_id().await?);
+        let entry_point = EntryPoint::new(sponsor.entry_point, self.client());
+        let summary = self
+            .send(entry_point.handle_ops(vec![op], beneficiary))
+            .await?;
+        if summary.status != TxStatus::Success {
+            return Ok(summary);
+        }
+
+        let receipt = self
+            .client()
+            .get_transaction_receipt(summary.tx_hash)
+            .await
+            .map_err(MyTokenError::middleware)?
+            .ok_or(MyTokenError::NoReceipt(summary.tx_hash))?;
+        let event = receipt
+            .logs
+            .into_iter()
+            .filter(|log| log.address == sponsor.entry_point)
+            .filter_map(|log| parse_log::<UserOperationEventFilter>(log).ok())
+            .find(|event| event.user_op_hash == hash.0)
+            .ok_or_else(|| {
+                MyTokenError::Decode(format!("UserOperationEvent in {:?}", summary.tx_hash))
+            })?;
+        if !event.success {
+            return Err(MyTokenError::UserOpFailed(hash));
+        }
+        Ok(summary)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{MyTokenClient, Result, TxSummary};
+use ethers::{
+    prelude::abigen,
+    providers::Middleware,
+    types::{Address, U256},
+};
+
+abigen!(
+    MyVault,
+    r#"[
+        function asset() external view returns (address)
+        function totalAssets() external view returns (uint256)
+        function balanceOf(address account) external view returns (uint256)
+        function convertToShares(uint256 assets) external view returns (uint256)
+        function convertToAssets(uint256 shares) external view returns (uint256)
+        function deposit(uint256 assets, address receiver) external returns (uint256)
+        function withdraw(uint256 assets, address receiver, address owner) external returns (uint256)
+    ]"#,
+);
+
+/// Typed client for a `MyVault` deployment, holding shares of a `MyToken`.
+///
+/// Writes go out with the same gas and confirmation settings as the token
+/// client it was made from.
+pub struct VaultClient<M> {
+    contract: MyVault<M>,
+    token: MyTokenClient<M>,
+}
+
+impl<M: Middleware + 'static> VaultClient<M> {
+    /// Client for the vault at `address`, whose asset is `token`'s token.
+    pub fn new(address: Address, token: &MyTokenClient<M>) -> Self {
+        Self {
+            contract: MyVault::new(address, token.client()),
+            token: token.at(token.address()),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.contract.address()
+    }
+
+    /// The underlying `abigen` binding, for calls the client doesn't wrap.
+    pub fn contract(&self) -> &MyVault<M> {
+        &self.contract
+    }
+
+    pub async fn asset(&self) -> Result<Address> {
+        Ok(self.contract.asset().call().await?)
+    }
+
+    /// Tokens held by the vault on behalf of all shareholders.
+    pub async fn total_assets(&self) -> Result<U256> {
+        Ok(self.contract.total_assets().call().await?)
+    }
+
+    /// Shares held by `account`.
+    pub async fn shares_of(&self, account: Address) -> Result<U256> {
+        Ok(self.contract.balance_of(account).call().await?)
+    }
+
+    pub async fn convert_to_shares(&self, assets: U256) -> Result<U256> {
+        Ok(self.contract.convert_to_shares(assets).call().await?)
+    }
+
+    pub async fn convert_to_assets(&self, shares: U256) -> Result<U256> {
+        Ok(self.contract.convert_to_assets(shares).call().await?)
+    }
+
+    /// Deposit `assets` tokens, minting shares to `receiver`. The vault must
+    /// already be approved to pull the tokens from the sender.
+    pub async fn deposit(&self, assets: U256, receiver: Address) -> Result<TxSummary> {
+        self.token
+            .send(self.contract.deposit(assets, receiver))
+            .await
+    }
+
+    /// Burn `owner`'s shares worth `assets` tokens and send the tokens to
+    /// `receiver`. Burning someone else's shares takes a share allowance.
+    pub async fn withdraw(
+        &self,
+        assets: U256,
+        receiver: Address,
+        owner: Address,
+    ) -> Result<TxSummary> {
+        self.token
+            .send(self.contract.withdraw(assets, receiver, owner))
+            .await
+    }
+}
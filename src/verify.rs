@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use ethers::{types::Address, utils::hex};
+use serde::Deserialize;
+
+use crate::{MyTokenError, Result, backoff::Backoff};
+
+/// Etherscan's multichain API, which verifies on every chain Etherscan runs an
+/// explorer for, picked by chain id.
+pub const ETHERSCAN_API_URL: &str = "https://api.etherscan.io/v2/api";
+
+const DEFAULT_CONTRACT_NAME: &str = "src/MyToken.sol:MyToken";
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_POLLS: u32 = 24;
+// Longest wait between submissions the explorer turned down for not having
+// indexed the contract yet
+const MAX_RESUBMIT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Source verification on an Etherscan-style explorer, submitted once
+/// [`TokenDeployer`](crate::TokenDeployer) has deployed the token when set
+/// with [`with_verification`](crate::TokenDeployer::with_verification).
+///
+/// The source is the Solidity standard JSON input the contract was compiled
+/// from, as printed by `forge verify-contract --show-standard-json-input`,
+/// and `compiler_version` the full solc version it was compiled with, such as
+/// `v0.8.24+commit.e11b9ed9`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub api_url: String,
+    pub api_key: String,
+    pub standard_json: String,
+    pub compiler_version: String,
+    /// `<source path>:<contract>`, `src/MyToken.sol:MyToken` unless set.
+    pub contract_name: String,
+    pub poll_interval: Duration,
+    /// Submissions made before giving up on an explorer that hasn't indexed
+    /// the contract yet, and status checks made before giving up on a
+    /// verification still queued.
+    pub max_polls: u32,
+}
+
+// What the explorer answers every call with: `status` is "1" on success, and
+// `result` the submission's guid or the verification status
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    status: String,
+    result: String,
+}
+
+impl Verification {
+    pub fn new(
+        api_key: impl Into<String>,
+        standard_json: impl Into<String>,
+        compiler_version: impl Into<String>,
+    ) -> Self {
+        Self {
+            api_url: ETHERSCAN_API_URL.to_owned(),
+            api_key: api_key.into(),
+            standard_json: standard_json.into(),
+            compiler_version: compiler_version.into(),
+            contract_name: DEFAULT_CONTRACT_NAME.to_owned(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_polls: DEFAULT_MAX_POLLS,
+        }
+    }
+
+    /// Explorer API to submit to instead of [`ETHERSCAN_API_URL`], for
+    /// Etherscan-compatible explorers run by others.
+    pub fn with_api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = url.into();
+        self
+    }
+
+    pub fn with_contract_name(mut self, name: impl Into<String>) -> Self {
+        self.contract_name = name.into();
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Submit the contract at `address` on `chain_id`, deployed with the
+    /// ABI-encoded `constructor_args`, and wait until the explorer has checked
+    /// it. A contract the explorer already has verified counts as verified.
+    ///
+    /// Explorers index new contracts a little after they're mined, so a
+    /// submission turned down for want of the contract's code is made again,
+    /// backing off from the poll interval.
+    pub async fn verify(
+        &self,
+        chain_id: u64,
+        address: Address,
+        constructor_args: &[u8],
+    ) -> Result<()> {
+        let http = reqwest::Client::new();
+        let chain_id = chain_id.to_string();
+        let address = format!("{address:?}");
+        let constructor_args = hex::encode(constructor_args);
+
+        let mut backoff = Backoff::new(self.poll_interval, MAX_RESUBMIT_INTERVAL);
+        let mut submissions = 0;
+        let submitted = loop {
+            let submitted = call(
+                http.post(&self.api_url)
+                    .query(&[("chainid", chain_id.as_str())])
+                    .form(&[
+                        ("apikey", self.api_key.as_str()),
+                        ("module", "contract"),
+                        ("action", "verifysourcecode"),
+                        ("contractaddress", address.as_str()),
+                        ("sourceCode", self.standard_json.as_str()),
+                        ("codeformat", "solidity-standard-json-input"),
+                        ("contractname", self.contract_name.as_str()),
+                        ("compilerversion", self.compiler_version.as_str()),
+                        // Etherscan's spelling
+                        ("constructorArguements", constructor_args.as_str()),
+                    ]),
+            )
+            .await?;
+            submissions += 1;
+            if already_verified(&submitted.result) {
+                return Ok(());
+            }
+            if submitted.status == "1" {
+                break submitted;
+            }
+            if !not_indexed_yet(&submitted.result) || submissions >= self.max_polls {
+                return Err(MyTokenError::Verification(submitted.result));
+            }
+            tokio::time::sleep(backoff.next_delay()).await;
+        };
+
+        let guid = submitted.result;
+        for _ in 0..self.max_polls {
+            tokio::time::sleep(self.poll_interval).await;
+            let status = call(http.get(&self.api_url).query(&[
+                ("chainid", chain_id.as_str()),
+                ("apikey", self.api_key.as_str()),
+                ("module", "contract"),
+                ("action", "checkverifystatus"),
+                ("guid", guid.as_str()),
+            ]))
+            .await?;
+            if status.result.starts_with("Pass") || already_verified(&status.result) {
+                return Ok(());
+            }
+            if !status.result.starts_with("Pending") {
+                return Err(MyTokenError::Verification(status.result));
+            }
+        }
+        Err(MyTokenError::Verification(format!(
+            "still pending after {} checks, see guid {guid}",
+            self.max_polls
+        )))
+    }
+}
+
+async fn call(request: reqwest::RequestBuilder) -> Result<ApiResponse> {
+    let verification = |err: reqwest::Error| MyTokenError::Verification(err.to_string());
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(verification)?
+        .json()
+        .await
+        .map_err(verification)
+}
+
+fn already_verified(result: &str) -> bool {
+    result.to_ascii_lowercase().contains("already verified")
+}
+
+// What Etherscan answers for a contract it hasn't picked up yet, such as
+// "Unable to locate ContractCode at 0x..."
+fn not_indexed_yet(result: &str) -> bool {
+    result
+        .to_ascii_lowercase()
+        .contains("unable to locate contractcode")
+}
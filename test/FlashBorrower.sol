@@ -0,0 +1,23 @@
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/interfaces/IERC3156FlashBorrower.sol";
+import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+
+// EIP-3156 borrower for the flash loan tests. `data` is an ABI-encoded bool
+// saying whether to approve the repayment; without it the lender can't take
+// the loan back and the whole loan reverts.
+contract FlashBorrower is IERC3156FlashBorrower {
+    // Balance held while inside the loan, to show the tokens arrived
+    uint256 public borrowedBalance;
+
+    function onFlashLoan(address, address token, uint256 amount, uint256 fee, bytes calldata data)
+        external
+        returns (bytes32)
+    {
+        borrowedBalance = IERC20(token).balanceOf(address(this));
+        if (abi.decode(data, (bool))) {
+            IERC20(token).approve(msg.sender, amount + fee);
+        }
+        return keccak256("ERC3156FlashBorrower.onFlashLoan");
+    }
+}
@@ -0,0 +1,36 @@
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/utils/introspection/ERC165.sol";
+import "@openzeppelin/contracts/interfaces/IERC1363Receiver.sol";
+import "../src/MyToken.sol";
+
+// Records the `safeMint` and `transferAndCall` hook calls it receives, for the
+// Rust tests to read. A `transferAndCall` with data "reject" is refused.
+contract MintReceiver is ERC165, IMintReceiver, IERC1363Receiver {
+    address public lastOperator;
+    uint256 public lastAmount;
+    uint256 public calls;
+    bytes public lastData;
+
+    function tokensReceived(address operator, uint256 amount) external {
+        lastOperator = operator;
+        lastAmount = amount;
+        calls += 1;
+    }
+
+    function onTransferReceived(address operator, address, uint256 value, bytes calldata data)
+        external
+        returns (bytes4)
+    {
+        require(keccak256(data) != keccak256("reject"), "MintReceiver: rejected");
+        lastOperator = operator;
+        lastAmount = value;
+        lastData = data;
+        calls += 1;
+        return IERC1363Receiver.onTransferReceived.selector;
+    }
+
+    function supportsInterface(bytes4 interfaceId) public view override returns (bool) {
+        return interfaceId == type(IMintReceiver).interfaceId || super.supportsInterface(interfaceId);
+    }
+}
@@ -0,0 +1,49 @@
+pragma solidity ^0.8.20;
+
+// Minimal stand-in for the canonical Multicall3, which a fresh Anvil doesn't
+// have. Only the entry points the Rust client calls are implemented, with the
+// same signatures so the `ethers` bindings work against it unchanged.
+contract Multicall3 {
+    struct Call {
+        address target;
+        bytes callData;
+    }
+
+    struct Call3 {
+        address target;
+        bool allowFailure;
+        bytes callData;
+    }
+
+    struct Result {
+        bool success;
+        bytes returnData;
+    }
+
+    function aggregate3(Call3[] calldata calls) public payable returns (Result[] memory returnData) {
+        returnData = new Result[](calls.length);
+        for (uint256 i = 0; i < calls.length; i++) {
+            (bool success, bytes memory ret) = calls[i].target.call(calls[i].callData);
+            if (!success && !calls[i].allowFailure) {
+                // Bubble up the inner revert so the whole batch fails with it
+                assembly {
+                    revert(add(ret, 32), mload(ret))
+                }
+            }
+            returnData[i] = Result(success, ret);
+        }
+    }
+
+    function tryAggregate(bool requireSuccess, Call[] calldata calls)
+        public
+        payable
+        returns (Result[] memory returnData)
+    {
+        returnData = new Result[](calls.length);
+        for (uint256 i = 0; i < calls.length; i++) {
+            (bool success, bytes memory ret) = calls[i].target.call(calls[i].callData);
+            require(success || !requireSuccess, "Multicall3: call failed");
+            returnData[i] = Result(success, ret);
+        }
+    }
+}
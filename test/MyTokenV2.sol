@@ -0,0 +1,11 @@
+pragma solidity ^0.8.20;
+
+import "../src/MyTokenUpgradeable.sol";
+
+// A second implementation for the upgrade tests, adding one view method and
+// keeping the storage layout of the first
+contract MyTokenV2 is MyTokenUpgradeable {
+    function version() external pure returns (string memory) {
+        return "2";
+    }
+}
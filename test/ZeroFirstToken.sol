@@ -0,0 +1,17 @@
+pragma solidity ^0.8.20;
+
+import "@openzeppelin/contracts/token/ERC20/ERC20.sol";
+
+// Refuses to change one nonzero allowance straight to another, as USDT does,
+// for the Rust tests of the zero-first approve fallback. The deployer gets the
+// whole supply.
+contract ZeroFirstToken is ERC20 {
+    constructor() ERC20("Zero First", "ZFT") {
+        _mint(msg.sender, 1_000_000 ether);
+    }
+
+    function approve(address spender, uint256 value) public override returns (bool) {
+        require(value == 0 || allowance(msg.sender, spender) == 0);
+        return super.approve(spender, value);
+    }
+}
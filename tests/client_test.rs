@@ -0,0 +1,1883 @@
+mod common;
+
+use common::{CHAIN_ID, RPC_URL, TestEnv, WS_URL, funded_signer};
+use ethers::{
+    abi::{self, Token},
+    contract::abigen,
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{Address, BlockNumber, Bytes, TransactionRequest, U256},
+    utils::parse_ether,
+};
+use eyre::Result;
+use futures::StreamExt;
+use my_token::{
+    BatchTransferSkippedFilter, DEFAULT_ARTIFACT, ENTRY_POINT_V06, EntryPoint, Faucet, LegacyGas,
+    MetadataUpdatedFilter, MyTokenClient, MyTokenError, NetworkConfig, PERMIT2_ADDRESS, ScanCursor,
+    SimCall, Sponsor, SupplyChangeKind, TokenDeployer, TransferHook, TransportKind, TxStatus,
+    TxSummary, VaultClient, connect, connect_fallback, default_deadline, http_provider,
+    impersonate, to_token_units, wait_all,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+abigen!(
+    MintReceiver,
+    r#"[
+        function lastOperator() external view returns (address)
+        function lastAmount() external view returns (uint256)
+        function calls() external view returns (uint256)
+        function lastData() external view returns (bytes)
+    ]"#;
+
+    MyTokenV2,
+    r#"[
+        function version() external view returns (string)
+    ]"#;
+
+    FlashBorrower,
+    r#"[
+        function borrowedBalance() external view returns (uint256)
+    ]"#,
+);
+
+#[tokio::test]
+async fn test_safe_approve_resets_nonzero_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let spender = Address::random();
+    token.approve(spender, U256::from(100)).await?;
+
+    let summaries = token.safe_approve(spender, U256::from(250)).await?;
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(
+        token.allowance(env.owner.address(), spender).await?,
+        U256::from(250)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_approve_falls_back_to_zero_first() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let owner = env.owner.address();
+
+    // A token refusing nonzero-to-nonzero changes takes two approves
+    let address = env.deploy_implementation("ZeroFirstToken").await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let spender = Address::random();
+    token.approve(spender, U256::from(100)).await?;
+    let before = env.provider.get_transaction_count(owner, None).await?;
+    token.approve(spender, U256::from(250)).await?;
+    assert_eq!(token.allowance(owner, spender).await?, U256::from(250));
+    let after = env.provider.get_transaction_count(owner, None).await?;
+    assert_eq!(after - before, U256::from(2));
+
+    // Any other token still takes one
+    let address = env.deploy_once(owner).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    token.approve(spender, U256::from(100)).await?;
+    let before = env.provider.get_transaction_count(owner, None).await?;
+    token.approve(spender, U256::from(250)).await?;
+    assert_eq!(token.allowance(owner, spender).await?, U256::from(250));
+    let after = env.provider.get_transaction_count(owner, None).await?;
+    assert_eq!(after - before, U256::one());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_remaining_mintable() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let snapshot = env.snapshot().await?;
+
+    let recipient = Address::random();
+    token.mint(recipient, U256::from(1000)).await?;
+
+    let cap = token.cap().await?;
+    let remaining = token.remaining_mintable().await?;
+    assert_eq!(remaining, cap - token.total_supply().await?);
+
+    // Minting right up to the cap leaves nothing, rather than underflowing
+    token.mint(recipient, remaining).await?;
+    assert_eq!(token.remaining_mintable().await?, U256::zero());
+
+    env.revert(snapshot).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cap_change_waits_for_timelock() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let snapshot = env.snapshot().await?;
+
+    let old_delay = token.cap_change_delay().await?;
+    token.set_cap_change_delay(U256::from(60)).await?;
+    env.increase_time(old_delay.as_u64()).await?;
+    assert_eq!(token.cap_change_delay().await?, U256::from(60));
+
+    let new_cap = token.cap().await? * 2;
+    token.propose_cap_change(new_cap).await?;
+    assert_eq!(token.pending_cap().await?, Some(new_cap));
+    assert!(token.cap_change_time().await?.is_some());
+
+    let early = token.execute_cap_change().await;
+    assert!(
+        matches!(early, Err(MyTokenError::CapChangeNotReady(_))),
+        "executed before the delay: {early:?}"
+    );
+
+    env.increase_time(61).await?;
+    token.execute_cap_change().await?;
+    assert_eq!(token.cap().await?, new_cap);
+    assert_eq!(token.pending_cap().await?, None);
+
+    // The cap can be lowered, but never below what's already minted
+    let supply = token.total_supply().await?;
+    let result = token.propose_cap_change(supply - 1).await;
+    assert!(
+        matches!(result, Err(MyTokenError::CapBelowSupply { .. })),
+        "proposed a cap below the supply: {result:?}"
+    );
+
+    env.revert(snapshot).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lowered_cap_change_delay_waits_for_old_delay() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let snapshot = env.snapshot().await?;
+
+    // Dropping the delay to nothing doesn't let a cap change through at once
+    let old_delay = token.cap_change_delay().await?;
+    token.set_cap_change_delay(U256::zero()).await?;
+    assert_eq!(token.cap_change_delay().await?, old_delay);
+    let (pending, _) = token
+        .pending_cap_change_delay()
+        .await?
+        .expect("new delay is pending");
+    assert_eq!(pending, U256::zero());
+
+    let new_cap = token.cap().await? * 2;
+    token.propose_cap_change(new_cap).await?;
+    let early = token.execute_cap_change().await;
+    assert!(
+        matches!(early, Err(MyTokenError::CapChangeNotReady(_))),
+        "executed under the lowered delay: {early:?}"
+    );
+
+    // Once the old delay has passed, the new one applies
+    env.increase_time(old_delay.as_u64()).await?;
+    assert_eq!(token.cap_change_delay().await?, U256::zero());
+    assert_eq!(token.pending_cap_change_delay().await?, None);
+
+    env.revert(snapshot).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_enriched_transfers() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let recipient = Address::random();
+    let summary = token.transfer(recipient, U256::from(42)).await?;
+
+    let transfers = token.enriched_transfers(summary.block_number).await?;
+    let transfer = transfers
+        .iter()
+        .find(|transfer| transfer.to == recipient)
+        .expect("transfer to recipient was not found");
+
+    assert_eq!(transfer.tx_hash, summary.tx_hash);
+    assert_eq!(transfer.from, env.owner.address());
+    assert_eq!(transfer.value, U256::from(42));
+    assert_eq!(transfer.block_number, summary.block_number);
+
+    let latest = env
+        .provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .expect("latest block");
+    assert!(transfer.timestamp > 1_600_000_000);
+    assert!(transfer.timestamp <= latest.timestamp.as_u64());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_freeze_blocks_sending_only() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(100)).await?;
+
+    token.freeze(holder.address()).await?;
+    assert!(token.is_frozen(holder.address()).await?);
+
+    // A frozen account still receives
+    token.transfer(holder.address(), U256::from(50)).await?;
+    assert_eq!(token.balance_of(holder.address()).await?, U256::from(150));
+
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    let result = holder_token
+        .transfer(env.owner.address(), U256::from(10))
+        .await;
+    assert!(result.is_err(), "frozen account was able to send");
+
+    token.unfreeze(holder.address()).await?;
+    holder_token
+        .transfer(env.owner.address(), U256::from(10))
+        .await?;
+    assert_eq!(token.balance_of(holder.address()).await?, U256::from(140));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_clients_share_one_provider() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let provider = Arc::new(env.provider.clone());
+
+    // Distinct owners give three separate deployments
+    let mut tokens = Vec::new();
+    for _ in 0..3 {
+        let address = env.deploy_once(Address::random()).await?;
+        tokens.push(MyTokenClient::new(address, provider.clone()));
+    }
+
+    for token in &tokens {
+        assert_eq!(token.symbol().await?, "MTK");
+    }
+
+    // One provider (and so one connection pool) behind all three clients
+    assert_eq!(Arc::strong_count(&provider), 4);
+    let fourth = tokens[0].at(tokens[1].address());
+    assert!(Arc::ptr_eq(&fourth.client(), &provider));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unbatched_balances_keep_input_order() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone()).with_read_concurrency(4);
+
+    let accounts: Vec<Address> = (0..25).map(|_| Address::random()).collect();
+    let mut expected = Vec::new();
+    for (i, &account) in accounts.iter().enumerate() {
+        let amount = U256::from(i + 1);
+        token.transfer(account, amount).await?;
+        expected.push(amount);
+    }
+
+    assert_eq!(token.balances_unbatched(&accounts).await?, expected);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_permit_with_fetched_nonce() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let spender = Address::random();
+    let nonce = token.current_nonce(env.owner.address()).await?;
+    let permit = token
+        .sign_permit(
+            &env.owner,
+            spender,
+            U256::from(300),
+            default_deadline(10),
+            None,
+        )
+        .await?;
+    assert_eq!(permit.nonce, nonce);
+
+    token.permit(&permit).await?;
+    assert_eq!(
+        token.allowance(env.owner.address(), spender).await?,
+        U256::from(300)
+    );
+    assert_eq!(token.current_nonce(env.owner.address()).await?, nonce + 1);
+
+    // The nonce is spent, so the same signature can't be replayed
+    assert!(token.permit(&permit).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_watch_allowance_yields_new_amount() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let spender = Address::random();
+    let allowances = token.watch_allowance(
+        env.owner.address(),
+        spender,
+        WS_URL,
+        CancellationToken::new(),
+    );
+    let watcher = tokio::spawn(async move {
+        tokio::pin!(allowances);
+        tokio::time::timeout(Duration::from_secs(10), allowances.next()).await
+    });
+
+    // Give the subscription a moment to be established
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    token.approve(spender, U256::from(777)).await?;
+
+    let allowance = watcher.await?.map(Option::transpose)??;
+    assert_eq!(allowance, Some(U256::from(777)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_polling_balance_watch_over_http() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let recipient = Address::random();
+    let cancel = CancellationToken::new();
+    let balances = token.watch_balance_polling(
+        RPC_URL,
+        recipient,
+        Duration::from_millis(100),
+        cancel.clone(),
+    );
+    let (sender, mut received) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        tokio::pin!(balances);
+        while let Some(balance) = balances.next().await {
+            if sender.send(balance).is_err() {
+                break;
+            }
+        }
+    });
+
+    // The first poll only reads the balance changes are looked for against,
+    // which may already include the first transfer. The next one is a change
+    // it sees either way.
+    let mut balance = None;
+    for _ in 0..2 {
+        token.transfer(recipient, U256::from(321)).await?;
+        if let Ok(next) = tokio::time::timeout(Duration::from_secs(5), received.recv()).await {
+            balance = next.transpose()?;
+            break;
+        }
+    }
+    let expected = token.balance_of(recipient).await?;
+    assert_eq!(balance, Some(expected));
+    assert!(expected == U256::from(321) || expected == U256::from(642));
+    cancel.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_preflight_flags_missing_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+
+    // The owner has plenty of tokens but hasn't approved this spender
+    let spender = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(CHAIN_ID);
+    let spender_token = MyTokenClient::new(address, env.signer_client(spender));
+
+    let preflight = spender_token
+        .preflight_transfer_from(env.owner.address(), Address::random(), U256::from(100))
+        .await?;
+
+    assert!(preflight.balance_sufficient);
+    assert!(!preflight.allowance_sufficient);
+    assert_eq!(preflight.allowance, U256::zero());
+    assert!(preflight.estimated_gas.is_none());
+    assert!(!preflight.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_preflight_flags_expired_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let snapshot = env.snapshot().await?;
+
+    let spender = funded_signer(&env).await?;
+    let now = env
+        .provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .expect("latest block")
+        .timestamp;
+    token
+        .approve_with_expiry(spender.address(), U256::from(100), now + 60)
+        .await?;
+    let spender_token = MyTokenClient::new(address, env.signer_client(spender));
+    let preflight = || {
+        spender_token.preflight_transfer_from(
+            env.owner.address(),
+            Address::random(),
+            U256::from(10),
+        )
+    };
+    assert!(preflight().await?.allowance_sufficient);
+
+    // Large enough, but past its expiry
+    env.increase_time(120).await?;
+    let expired = preflight().await?;
+    assert_eq!(expired.allowance, U256::from(100));
+    assert!(expired.allowance_expired);
+    assert!(!expired.allowance_sufficient);
+    assert!(!expired.is_ok());
+
+    env.revert(snapshot).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_revoke_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let spender = Address::random();
+    token.approve(spender, U256::from(500)).await?;
+    assert_eq!(
+        token.allowance(env.owner.address(), spender).await?,
+        U256::from(500)
+    );
+
+    token.revoke_allowance(spender).await?;
+    assert_eq!(
+        token.allowance(env.owner.address(), spender).await?,
+        U256::zero()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_supply_events_classify_mints_and_burns() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let minted = token.mint(env.owner.address(), U256::from(1000)).await?;
+    token.burn(U256::from(400)).await?;
+
+    let changes = token.supply_events(minted.block_number).await?;
+    assert_eq!(changes.len(), 2);
+
+    assert_eq!(changes[0].kind, SupplyChangeKind::Mint);
+    assert_eq!(changes[0].amount, U256::from(1000));
+    assert_eq!(changes[1].kind, SupplyChangeKind::Burn);
+    assert_eq!(changes[1].amount, U256::from(400));
+    assert_eq!(changes[1].account, env.owner.address());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_warm_up_pays_connection_cost() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+
+    // A proxy in front of Anvil counting the connections made through it
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let proxy_url = format!("http://{}", listener.local_addr()?);
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((mut inbound, _)) = listener.accept().await {
+            accepted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut outbound = tokio::net::TcpStream::connect("127.0.0.1:8545").await?;
+                tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await?;
+                std::io::Result::Ok(())
+            });
+        }
+    });
+
+    // A fresh provider, so no pooled connection exists yet
+    let token = MyTokenClient::new(address, Arc::new(http_provider(&proxy_url)?));
+    assert_eq!(token.warm_up().await?, U256::from(CHAIN_ID));
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    // The connection warm_up opened is reused rather than a new one paid for
+    assert_eq!(token.name().await?, "MyToken");
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_watch_large_transfers_skips_small_ones() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let recipient = Address::random();
+    let transfers = token.watch_large_transfers(U256::from(1000), WS_URL, CancellationToken::new());
+    let watcher = tokio::spawn(async move {
+        let mut transfers = Box::pin(transfers.filter(|event| {
+            std::future::ready(event.as_ref().map_or(true, |event| event.to == recipient))
+        }));
+        tokio::time::timeout(Duration::from_secs(10), transfers.next()).await
+    });
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    token.transfer(recipient, U256::from(10)).await?;
+    token.transfer(recipient, U256::from(5000)).await?;
+
+    let first = watcher.await??.expect("stream ended")?;
+    assert_eq!(first.value, U256::from(5000));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_with_key_sends_once() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let recipient = Address::random();
+    let nonce_before = env
+        .provider
+        .get_transaction_count(env.owner.address(), None)
+        .await?;
+
+    let first = token
+        .transfer_with_key("order-42", recipient, U256::from(100))
+        .await?;
+    let retried = token
+        .transfer_with_key("order-42", recipient, U256::from(100))
+        .await?;
+
+    assert_eq!(retried, first);
+    assert_eq!(token.balance_of(recipient).await?, U256::from(100));
+    let nonce_after = env
+        .provider
+        .get_transaction_count(env.owner.address(), None)
+        .await?;
+    assert_eq!(nonce_after, nonce_before + 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_approve_and_pull_is_one_transaction() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    // The stand-in from `test/Multicall3.sol`, since a fresh Anvil has nothing
+    // at the canonical address
+    let multicall = env.deploy_implementation("Multicall3").await?;
+    let token = MyTokenClient::new(address, env.client.clone()).with_multicall(multicall);
+
+    let recipient = Address::random();
+    let summary = token
+        .approve_and_pull(&env.owner, recipient, U256::from(300))
+        .await?;
+
+    assert_eq!(token.balance_of(recipient).await?, U256::from(300));
+    assert_eq!(
+        token.allowance(env.owner.address(), multicall).await?,
+        U256::zero()
+    );
+
+    // The permit's Approval and the pull's Transfer share the one receipt
+    let block = summary.block_number;
+    let approvals = token
+        .contract()
+        .approval_filter()
+        .from_block(block)
+        .to_block(block)
+        .query_with_meta()
+        .await?;
+    let transfers = token
+        .contract()
+        .transfer_filter()
+        .from_block(block)
+        .to_block(block)
+        .query_with_meta()
+        .await?;
+    assert!(approvals.iter().any(
+        |(event, meta)| event.spender == multicall && meta.transaction_hash == summary.tx_hash
+    ));
+    assert!(
+        transfers
+            .iter()
+            .any(|(event, meta)| event.to == recipient && meta.transaction_hash == summary.tx_hash)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dropped_transaction_is_detected() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let nonce = env
+        .provider
+        .get_transaction_count(env.owner.address(), None)
+        .await?;
+
+    env.set_automine(false).await?;
+    let client = env.client.clone();
+    let transfer = tokio::spawn(async move {
+        let network = NetworkConfig {
+            receipt_timeout_ms: 1000,
+            ..NetworkConfig::default()
+        };
+        MyTokenClient::new(address, client)
+            .with_network(network)
+            .transfer(Address::random(), U256::from(100))
+            .await
+    });
+
+    // Evict the transfer, then mine something else at its nonce
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let _: () = env
+        .provider
+        .request("anvil_dropAllTransactions", ())
+        .await?;
+    let replacement = TransactionRequest::new()
+        .to(env.owner.address())
+        .value(1)
+        .nonce(nonce);
+    env.client.send_transaction(replacement, None).await?;
+    let _: U256 = env.provider.request("evm_mine", ()).await?;
+    env.set_automine(true).await?;
+
+    let result = tokio::time::timeout(Duration::from_secs(10), transfer).await??;
+    assert!(matches!(result, Err(MyTokenError::Dropped(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_from_emits_remaining_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let spender = funded_signer(&env).await?;
+    token.approve(spender.address(), U256::from(500)).await?;
+
+    let recipient = Address::random();
+    let spender_token = MyTokenClient::new(address, env.signer_client(spender.clone()));
+    let summary = spender_token
+        .transfer_from(env.owner.address(), recipient, U256::from(200))
+        .await?;
+
+    let events = token.transfer_from_events(summary.tx_hash).await?;
+    assert_eq!(events.transfer.to, recipient);
+    assert_eq!(events.transfer.value, U256::from(200));
+
+    let approval = events.approval.expect("transferFrom emitted no Approval");
+    assert_eq!(approval.owner, env.owner.address());
+    assert_eq!(approval.spender, spender.address());
+    assert_eq!(approval.value, U256::from(300));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_from_self_needs_no_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let owner = env.owner.address();
+    let recipient = Address::random();
+    assert_eq!(token.allowance(owner, owner).await?, U256::zero());
+
+    let summary = token
+        .transfer_from(owner, recipient, U256::from(250))
+        .await?;
+    assert_eq!(token.balance_of(recipient).await?, U256::from(250));
+
+    // Nothing was spent from, or written to, the owner's self-allowance
+    let events = token.transfer_from_events(summary.tx_hash).await?;
+    assert!(events.approval.is_none());
+    assert_eq!(token.allowance(owner, owner).await?, U256::zero());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_spendable_is_lesser_of_balance_and_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(500)).await?;
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    let spender = Address::random();
+
+    holder_token.approve(spender, U256::from(200)).await?;
+    assert_eq!(
+        token.spendable(holder.address(), spender).await?,
+        U256::from(200)
+    );
+
+    // An allowance beyond the balance still only reaches the balance
+    holder_token.approve(spender, U256::from(900)).await?;
+    assert_eq!(
+        token.spendable(holder.address(), spender).await?,
+        U256::from(500)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_allowance_saves_gas_on_transfer_from() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    // Fund the recipient first, so neither transfer pays for a new balance
+    let recipient = Address::random();
+    token.transfer(recipient, U256::from(1)).await?;
+
+    let mut gas = Vec::new();
+    for allowance in [U256::from(1_000), U256::MAX] {
+        let spender = funded_signer(&env).await?;
+        token.approve(spender.address(), allowance).await?;
+
+        let spender_token = MyTokenClient::new(address, env.signer_client(spender));
+        let summary = spender_token
+            .transfer_from(env.owner.address(), recipient, U256::from(10))
+            .await?;
+        gas.push(summary.gas_used);
+    }
+
+    // An unlimited allowance is left as it is, skipping the SSTORE a finite
+    // one needs to be decremented
+    let (finite, unlimited) = (gas[0], gas[1]);
+    assert!(
+        unlimited < finite,
+        "transferFrom gas: {unlimited} with U256::MAX, {finite} with a finite allowance"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_reports_gas_cost() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let summary = token.transfer(Address::random(), U256::from(5)).await?;
+    let cost = summary.gas_cost_wei();
+    assert_eq!(cost, summary.gas_used * summary.effective_gas_price);
+    assert!(!cost.is_zero());
+    assert_eq!(to_token_units(&summary.gas_cost_eth_string(), 18)?, cost);
+
+    let at_one_gwei = TxSummary {
+        gas_used: U256::from(21_000),
+        effective_gas_price: U256::exp10(9),
+        ..summary
+    };
+    assert_eq!(at_one_gwei.gas_cost_eth_string(), "0.000021");
+    assert_eq!(TxSummary::skipped().gas_cost_eth_string(), "0");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_vault_deposit_and_withdraw() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let vault = VaultClient::new(env.deploy_vault(address).await?, &token);
+    let owner = env.owner.address();
+
+    let before = token.balance_of(owner).await?;
+    token.approve(vault.address(), U256::from(100)).await?;
+    vault.deposit(U256::from(100), owner).await?;
+
+    // An empty vault issues shares 1:1
+    assert_eq!(vault.shares_of(owner).await?, U256::from(100));
+    assert_eq!(vault.total_assets().await?, U256::from(100));
+    assert_eq!(token.balance_of(owner).await?, before - 100);
+
+    vault.withdraw(U256::from(100), owner, owner).await?;
+    assert_eq!(vault.shares_of(owner).await?, U256::zero());
+    assert_eq!(token.balance_of(owner).await?, before);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_faucet_drips_eth_and_tokens() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let faucet = Faucet::new(MyTokenClient::new(address, env.client.clone()));
+
+    let target = Address::random();
+    let summaries = faucet
+        .drip(target, parse_ether(1)?, U256::from(500))
+        .await?;
+    assert_eq!(summaries.len(), 2);
+
+    let token = MyTokenClient::new(address, env.client.clone());
+    assert_eq!(
+        env.provider.get_balance(target, None).await?,
+        parse_ether(1)?
+    );
+    assert_eq!(token.balance_of(target).await?, U256::from(500));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_offline_signed_transfer() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    // Everything a signer without connectivity would be handed
+    let nonce = env
+        .provider
+        .get_transaction_count(env.owner.address(), None)
+        .await?;
+    let gas = LegacyGas {
+        limit: U256::from(100_000),
+        price: env.provider.get_gas_price().await?,
+    };
+
+    let recipient = Address::random();
+    let signed =
+        token.sign_transfer_offline(&env.owner, nonce, CHAIN_ID, recipient, U256::from(64), gas)?;
+
+    token.send_raw(signed).await?;
+    assert_eq!(token.balance_of(recipient).await?, U256::from(64));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_percent_sends_a_quarter() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(1001)).await?;
+
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    let recipient = Address::random();
+    holder_token.transfer_percent(recipient, 2500).await?;
+
+    // 25% of 1001, truncated
+    assert_eq!(token.balance_of(recipient).await?, U256::from(250));
+    assert_eq!(token.balance_of(holder.address()).await?, U256::from(751));
+
+    let result = holder_token.transfer_percent(recipient, 10_001).await;
+    assert!(matches!(result, Err(MyTokenError::InvalidBps(10_001))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_contract_metadata_of_fresh_token() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let before = env.provider.get_block_number().await?.as_u64();
+    // A new owner forces a fresh deployment
+    let address = env.deploy_once(Address::random()).await?;
+    let after = env.provider.get_block_number().await?.as_u64();
+
+    let meta = MyTokenClient::new(address, env.client.clone())
+        .contract_metadata()
+        .await?;
+    assert!(meta.code_size > 0);
+    assert!(!meta.is_proxy);
+    assert!(meta.deployment_block > before && meta.deployment_block <= after);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_allowance_cannot_be_spent() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let snapshot = env.snapshot().await?;
+
+    let spender = funded_signer(&env).await?;
+
+    let now = env
+        .provider
+        .get_block(BlockNumber::Latest)
+        .await?
+        .expect("latest block")
+        .timestamp;
+    let deadline = now + 60;
+    token
+        .approve_with_expiry(spender.address(), U256::from(100), deadline)
+        .await?;
+    assert_eq!(
+        token
+            .allowance_expiry(env.owner.address(), spender.address())
+            .await?,
+        Some(deadline)
+    );
+
+    env.increase_time(120).await?;
+    let spender_token = MyTokenClient::new(address, env.signer_client(spender));
+    let result = spender_token
+        .transfer_from(env.owner.address(), Address::random(), U256::from(10))
+        .await;
+    assert!(result.is_err(), "expired allowance was spent");
+
+    env.revert(snapshot).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_six_decimal_token_units() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env
+        .deploy_once_with_decimals(env.owner.address(), 6)
+        .await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let decimals = token.decimals().await?;
+    assert_eq!(decimals, 6);
+    assert_eq!(
+        token.total_supply().await?,
+        U256::from(1_000_000u64) * U256::exp10(6)
+    );
+
+    let recipient = Address::random();
+    let amount = to_token_units("1.5", decimals)?;
+    assert_eq!(amount, U256::from(1_500_000));
+    token.transfer(recipient, amount).await?;
+    assert_eq!(token.balance_of(recipient).await?, U256::from(1_500_000));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_safe_mint_calls_receiver_hook() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let receiver = env.deploy_implementation("MintReceiver").await?;
+    let receiver = MintReceiver::new(receiver, env.client.clone());
+    token.safe_mint(receiver.address(), U256::from(250)).await?;
+    assert_eq!(token.balance_of(receiver.address()).await?, U256::from(250));
+    assert_eq!(receiver.calls().call().await?, U256::one());
+    assert_eq!(receiver.last_amount().call().await?, U256::from(250));
+    assert_eq!(receiver.last_operator().call().await?, env.owner.address());
+
+    // No code to call at an EOA, so the mint is all that happens
+    let account = Address::random();
+    token.safe_mint(account, U256::from(40)).await?;
+    assert_eq!(token.balance_of(account).await?, U256::from(40));
+    assert_eq!(receiver.calls().call().await?, U256::one());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wait_all_keeps_submission_order() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    // Broadcast all three before waiting on any
+    let mut calls = Vec::new();
+    for amount in 1..=3u64 {
+        calls.push(
+            token
+                .contract()
+                .transfer(Address::random(), U256::from(amount))
+                .legacy(),
+        );
+    }
+    let mut pendings = Vec::new();
+    let mut hashes = Vec::new();
+    for call in &calls {
+        let pending = call.send().await?;
+        hashes.push(*pending);
+        pendings.push(pending);
+    }
+
+    let summaries = wait_all(pendings).await?;
+    assert_eq!(summaries.len(), 3);
+    assert!(summaries.iter().all(|s| s.status == TxStatus::Success));
+    assert_eq!(
+        summaries.iter().map(|s| s.tx_hash).collect::<Vec<_>>(),
+        hashes
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upgrade_keeps_balances() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let proxy = env.deploy_proxied(env.owner.address()).await?;
+    let token = MyTokenClient::new(proxy, env.client.clone());
+    let v1 = token
+        .implementation()
+        .await?
+        .expect("proxy has an implementation");
+
+    let holder = Address::random();
+    token.transfer(holder, U256::from(700)).await?;
+    let owner_balance = token.balance_of(env.owner.address()).await?;
+
+    let v2 = env.deploy_implementation("MyTokenV2").await?;
+    token.upgrade_to(v2).await?;
+    assert_ne!(v1, v2);
+    assert_eq!(token.implementation().await?, Some(v2));
+
+    // Same storage, new logic
+    assert_eq!(token.balance_of(holder).await?, U256::from(700));
+    assert_eq!(token.balance_of(env.owner.address()).await?, owner_balance);
+    let upgraded = MyTokenV2::new(proxy, env.client.clone());
+    assert_eq!(upgraded.version().call().await?, "2");
+
+    // A plain deployment has no implementation slot
+    let plain = MyTokenClient::new(
+        env.deploy_once(env.owner.address()).await?,
+        env.client.clone(),
+    );
+    assert_eq!(plain.implementation().await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_total_supply_at_past_block() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let before = env.provider.get_block_number().await?;
+    let supply_before = token.total_supply().await?;
+    token.mint(Address::random(), U256::from(1_000)).await?;
+
+    assert_eq!(token.total_supply_at(before.into()).await?, supply_before);
+    assert_eq!(
+        token.total_supply().await?,
+        supply_before + U256::from(1_000)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_balance_history_across_transfer() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let recipient = Address::random();
+    let before = env.provider.get_block_number().await?.as_u64();
+    let summary = token.transfer(recipient, U256::from(250)).await?;
+
+    // Blocks are answered in the order given, not sorted
+    let history = token
+        .balance_history(recipient, &[summary.block_number, before])
+        .await?;
+    assert_eq!(history, vec![U256::from(250), U256::zero()]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_simulate_approve_then_transfer_from() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let owner = env.owner.address();
+    let spender = Address::random();
+    let recipient = Address::random();
+    let amount = U256::from(300);
+    let block = env.provider.get_block_number().await?;
+
+    // transferFrom only works given the approval before it
+    let results = token
+        .simulate_bundle(vec![
+            SimCall::Approve {
+                owner,
+                spender,
+                amount,
+            },
+            SimCall::TransferFrom {
+                spender,
+                from: owner,
+                to: recipient,
+                amount,
+            },
+            SimCall::TransferFrom {
+                spender,
+                from: owner,
+                to: recipient,
+                amount,
+            },
+        ])
+        .await?;
+    assert_eq!(results, [true, true, false]);
+
+    // Nothing was mined and no state changed
+    assert_eq!(env.provider.get_block_number().await?, block);
+    assert_eq!(token.allowance(owner, spender).await?, U256::zero());
+    assert_eq!(token.balance_of(recipient).await?, U256::zero());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_simulate_bundle_takes_transfer_fee() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    // 10%, so a holder sent 1000 ends up with 900
+    token.set_transfer_fee(1000).await?;
+
+    let owner = env.owner.address();
+    let [holder, recipient] = [Address::random(), Address::random()];
+    let transfer = |from, to, amount: U256| SimCall::Transfer { from, to, amount };
+    let results = token
+        .simulate_bundle(vec![
+            transfer(owner, holder, 1000.into()),
+            transfer(holder, recipient, 1000.into()),
+            // The recipient only got 900 of the 1000
+            transfer(recipient, holder, 901.into()),
+            transfer(recipient, holder, 800.into()),
+        ])
+        .await?;
+    assert_eq!(results, [true, true, false, true]);
+
+    // The owner is credited the fee, so having sent everything else it holds
+    // it can send the 100 taken and no more
+    let owner_balance = token.balance_of(owner).await?;
+    let results = token
+        .simulate_bundle(vec![
+            transfer(owner, holder, owner_balance),
+            transfer(holder, recipient, 1000.into()),
+            transfer(owner, holder, 100.into()),
+            transfer(owner, holder, 1.into()),
+        ])
+        .await?;
+    assert_eq!(results, [true, true, true, false]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_simulate_with_hypothetical_balance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let account = Address::random();
+    let transfer = SimCall::Transfer {
+        from: account,
+        to: Address::random(),
+        amount: U256::from(500),
+    };
+    assert_eq!(token.balance_of(account).await?, U256::zero());
+    assert_eq!(token.simulate_bundle(vec![transfer]).await?, [false]);
+
+    assert!(
+        token
+            .simulate_with_balance(account, U256::from(1000), transfer)
+            .await?
+    );
+    assert_eq!(token.balance_of(account).await?, U256::zero());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connect_over_http_and_ws() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let supply = MyTokenClient::new(address, env.client.clone())
+        .total_supply()
+        .await?;
+
+    for (url, kind) in [(RPC_URL, TransportKind::Http), (WS_URL, TransportKind::Ws)] {
+        let provider = connect(url).await?;
+        assert_eq!(provider.as_ref().kind(), kind);
+
+        let token = MyTokenClient::new(address, Arc::new(provider));
+        assert_eq!(token.chain_id().await?, CHAIN_ID);
+        assert_eq!(token.total_supply().await?, supply);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_burn_from_spends_allowance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let other_account = funded_signer(&env).await?;
+    token
+        .approve(other_account.address(), U256::from(100))
+        .await?;
+
+    let supply = token.total_supply().await?;
+    let balance = token.balance_of(env.owner.address()).await?;
+    let burner = MyTokenClient::new(address, env.signer_client(other_account.clone()));
+    burner
+        .burn_from(env.owner.address(), U256::from(100))
+        .await?;
+
+    assert_eq!(token.total_supply().await?, supply - 100);
+    assert_eq!(token.balance_of(env.owner.address()).await?, balance - 100);
+    assert_eq!(
+        token
+            .allowance(env.owner.address(), other_account.address())
+            .await?,
+        U256::zero()
+    );
+
+    // The allowance is used up
+    let result = burner.burn_from(env.owner.address(), U256::one()).await;
+    assert!(result.is_err(), "burned past the allowance");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deploy_writes_deployment_record() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let dir = std::env::temp_dir().join(format!("deployments-{:?}", Address::random()));
+
+    let artifact = format!("{}/{DEFAULT_ARTIFACT}", env!("CARGO_MANIFEST_DIR"));
+    let deployment = TokenDeployer::new(env.client.clone(), artifact)
+        .with_output_dir(&dir)
+        .deploy()
+        .await?;
+    assert_eq!(deployment.deployer, env.owner.address());
+
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(
+        dir.join(format!("{CHAIN_ID}.json")),
+    )?)?;
+    let address: Address = written["address"]
+        .as_str()
+        .expect("address is a string")
+        .parse()?;
+    assert_eq!(address, deployment.address);
+    assert!(!env.provider.get_code(address, None).await?.is_empty());
+    assert!(written["abi"].as_array().is_some_and(|abi| !abi.is_empty()));
+    assert_eq!(written["block"], deployment.block);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_balance_cross_check_agrees_on_one_node() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    // Both ends are the same Anvil here, so they must agree. In production the
+    // secondary would be an independent node, e.g. a different RPC provider.
+    let holder = Address::random();
+    token.transfer(holder, U256::from(42)).await?;
+    assert_eq!(
+        token.balance_cross_check(holder, RPC_URL).await?,
+        U256::from(42)
+    );
+    assert_eq!(
+        token.balance_cross_check(holder, WS_URL).await?,
+        U256::from(42)
+    );
+    Ok(())
+}
+
+// Records every callback it receives
+#[derive(Default)]
+struct RecordingHook {
+    calls: std::sync::Mutex<Vec<String>>,
+}
+
+impl TransferHook for RecordingHook {
+    fn before(&self, to: Address, amount: U256) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("before {to:?} {amount}"));
+    }
+
+    fn after(&self, summary: &TxSummary) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("after {:?}", summary.tx_hash));
+    }
+}
+
+#[tokio::test]
+async fn test_transfer_hooks_run_around_transfer() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let hook = Arc::new(RecordingHook::default());
+    let token = MyTokenClient::new(address, env.client.clone()).with_transfer_hook(hook.clone());
+
+    let recipient = Address::random();
+    let summary = token.transfer(recipient, U256::from(5)).await?;
+
+    assert_eq!(
+        *hook.calls.lock().unwrap(),
+        [
+            format!("before {recipient:?} 5"),
+            format!("after {:?}", summary.tx_hash),
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_flash_loan_must_be_repaid() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let borrower = env.deploy_implementation("FlashBorrower").await?;
+    let borrower = FlashBorrower::new(borrower, env.client.clone());
+
+    let amount = U256::from(1_000_000);
+    assert!(token.max_flash_loan().await? >= amount);
+    assert_eq!(token.flash_fee(amount).await?, U256::zero());
+    let supply = token.total_supply().await?;
+
+    let repay = |repay: bool| Bytes::from(abi::encode(&[Token::Bool(repay)]));
+    token
+        .flash_loan(borrower.address(), amount, repay(true))
+        .await?;
+    assert_eq!(borrower.borrowed_balance().call().await?, amount);
+    assert_eq!(token.total_supply().await?, supply);
+    assert_eq!(token.balance_of(borrower.address()).await?, U256::zero());
+
+    // Not approving the repayment reverts the loan as a whole
+    let result = token
+        .flash_loan(borrower.address(), amount, repay(false))
+        .await;
+    assert!(result.is_err(), "unpaid flash loan went through");
+    assert_eq!(token.total_supply().await?, supply);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_and_call_notifies_receiver() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let receiver = env.deploy_implementation("MintReceiver").await?;
+    let receiver = MintReceiver::new(receiver, env.client.clone());
+
+    let data = Bytes::from_static(b"order 17");
+    token
+        .transfer_and_call(receiver.address(), U256::from(80), data.clone())
+        .await?;
+    assert_eq!(token.balance_of(receiver.address()).await?, U256::from(80));
+    assert_eq!(receiver.last_data().call().await?, data);
+    assert_eq!(receiver.last_amount().call().await?, U256::from(80));
+
+    // The receiver's own reason comes back when it refuses
+    let result = token
+        .transfer_and_call(
+            receiver.address(),
+            U256::from(1),
+            Bytes::from_static(b"reject"),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(MyTokenError::ExecutionReverted(Some(reason))) if reason == "MintReceiver: rejected"
+    ));
+
+    let eoa = Address::random();
+    let result = token
+        .transfer_and_call(eoa, U256::from(1), Bytes::new())
+        .await;
+    assert!(matches!(result, Err(MyTokenError::NoContract(account)) if account == eoa));
+    assert_eq!(token.balance_of(receiver.address()).await?, U256::from(80));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cursor_scan_returns_only_new_transfers() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let path = std::env::temp_dir().join(format!("cursor-{:?}", Address::random()));
+
+    let first = token.transfer(Address::random(), U256::from(1)).await?;
+    let mut cursor = ScanCursor::open(&path)?;
+    let scanned = token.enriched_transfers_since(&mut cursor).await?;
+    assert!(scanned.iter().any(|t| t.tx_hash == first.tx_hash));
+
+    let second = token.transfer(Address::random(), U256::from(2)).await?;
+    // A new run picks the block up from the file
+    let mut cursor = ScanCursor::open(&path)?;
+    let scanned = token.enriched_transfers_since(&mut cursor).await?;
+    assert_eq!(scanned.len(), 1);
+    assert_eq!(scanned[0].tx_hash, second.tx_hash);
+    assert_eq!(cursor.last_block(), Some(second.block_number));
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_owner_renames_token() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    // Read first, so the old values are cached
+    assert_eq!(token.name().await?, "MyToken");
+    assert_eq!(token.symbol().await?, "MTK");
+
+    token.set_name("Renamed Token").await?;
+    token.set_symbol("RNM").await?;
+    assert_eq!(token.name().await?, "Renamed Token");
+    assert_eq!(token.symbol().await?, "RNM");
+
+    let events = token
+        .contract()
+        .event::<MetadataUpdatedFilter>()
+        .from_block(0)
+        .query()
+        .await?;
+    assert_eq!(events.last().map(|e| e.symbol.as_str()), Some("RNM"));
+
+    let stranger = funded_signer(&env).await?;
+    let stranger_token = MyTokenClient::new(address, env.signer_client(stranger));
+    assert!(stranger_token.set_name("Hijacked").await.is_err());
+    assert!(stranger_token.set_symbol("HJK").await.is_err());
+    assert_eq!(stranger_token.name().await?, "Renamed Token");
+
+    Ok(())
+}
+
+// Needs Permit2 at its canonical address, e.g. on a mainnet fork:
+// `anvil --fork-url <mainnet rpc> --chain-id 31337`, then `cargo test -- --ignored`
+#[tokio::test]
+#[ignore = "needs Permit2 deployed on the chain"]
+async fn test_permit2_signature_transfer() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(100)).await?;
+    // The one approval Permit2 needs, in place of one per spender
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    holder_token.approve(PERMIT2_ADDRESS, U256::MAX).await?;
+
+    let spender = token.sender()?;
+    let permit = token
+        .sign_permit2_transfer(
+            PERMIT2_ADDRESS,
+            &holder,
+            spender,
+            U256::from(60),
+            default_deadline(10),
+        )
+        .await?;
+    let recipient = Address::random();
+    token
+        .permit2_transfer_from(&permit, recipient, U256::from(40))
+        .await?;
+
+    assert_eq!(token.balance_of(recipient).await?, U256::from(40));
+    assert_eq!(token.balance_of(holder.address()).await?, U256::from(60));
+    assert!(token.allowance(holder.address(), spender).await?.is_zero());
+
+    // Each permit is good for one transfer only
+    let replayed = token
+        .permit2_transfer_from(&permit, recipient, U256::from(10))
+        .await;
+    assert!(replayed.is_err(), "permit was used twice");
+    Ok(())
+}
+
+// Acts as a real holder on a mainnet fork: `anvil --fork-url <mainnet rpc>
+// --chain-id 31337`, then `FORK_TOKEN=<erc20> FORK_HOLDER=<holder> cargo test
+// -- --ignored`
+#[tokio::test]
+#[ignore = "needs a mainnet fork, FORK_TOKEN and FORK_HOLDER"]
+async fn test_impersonated_holder_transfers() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address: Address = std::env::var("FORK_TOKEN")?.parse()?;
+    let holder: Address = std::env::var("FORK_HOLDER")?.parse()?;
+
+    // Gas money, whether or not the holder can receive ETH
+    let _: () = env
+        .provider
+        .request("anvil_setBalance", (holder, parse_ether(10)?))
+        .await?;
+    let whale = Arc::new(impersonate(&env.provider, holder).await?);
+    let token = MyTokenClient::new(address, whale.clone());
+    assert_eq!(token.sender()?, holder);
+
+    let before = token.balance_of(holder).await?;
+    assert!(!before.is_zero(), "FORK_HOLDER holds none of FORK_TOKEN");
+    let recipient = Address::random();
+    let amount = before / 2;
+    token.transfer(recipient, amount).await?;
+
+    assert_eq!(token.balance_of(recipient).await?, amount);
+    assert_eq!(token.balance_of(holder).await?, before - amount);
+    Ok(())
+}
+
+// Needs the v0.6 EntryPoint, a SimpleAccount owned by AA_OWNER_KEY at
+// AA_ACCOUNT, and a paymaster at AA_PAYMASTER with an EntryPoint deposit that
+// sponsors any operation, e.g. on a mainnet fork: `anvil --fork-url <mainnet
+// rpc> --chain-id 31337`, then `AA_ACCOUNT=<account> AA_OWNER_KEY=<key>
+// AA_PAYMASTER=<paymaster> cargo test -- --ignored`
+#[tokio::test]
+#[ignore = "needs an EntryPoint, smart account and paymaster on the chain"]
+async fn test_paymaster_sponsored_transfer() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let account: Address = std::env::var("AA_ACCOUNT")?.parse()?;
+    let owner = std::env::var("AA_OWNER_KEY")?
+        .parse::<LocalWallet>()?
+        .with_chain_id(CHAIN_ID);
+    let sponsor = Sponsor::new(ENTRY_POINT_V06, std::env::var("AA_PAYMASTER")?.parse()?);
+
+    token.transfer(account, U256::from(100)).await?;
+    let entry_point = EntryPoint::new(ENTRY_POINT_V06, env.client.clone());
+    let deposit = entry_point.balance_of(sponsor.paymaster).call().await?;
+    let account_eth = env.provider.get_balance(account, None).await?;
+
+    let recipient = Address::random();
+    let op = token
+        .sign_sponsored_transfer(&sponsor, account, &owner, recipient, U256::from(40))
+        .await?;
+    token
+        .submit_user_op(&sponsor, op, env.owner.address())
+        .await?;
+
+    assert_eq!(token.balance_of(recipient).await?, U256::from(40));
+    assert_eq!(token.balance_of(account).await?, U256::from(60));
+    // The paymaster's deposit paid for gas, not the account
+    assert_eq!(env.provider.get_balance(account, None).await?, account_eth);
+    assert!(entry_point.balance_of(sponsor.paymaster).call().await? < deposit);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_transfer_best_effort_skips_failures() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let (first, second) = (Address::random(), Address::random());
+    let payouts = [
+        (first, U256::from(10)),
+        (Address::zero(), U256::from(5)),
+        (Address::random(), U256::MAX),
+        (second, U256::from(20)),
+    ];
+    let summary = token.batch_transfer(&payouts, false).await?;
+
+    assert_eq!(token.balance_of(first).await?, U256::from(10));
+    assert_eq!(token.balance_of(second).await?, U256::from(20));
+    let skipped = token
+        .contract()
+        .event::<BatchTransferSkippedFilter>()
+        .from_block(summary.block_number)
+        .to_block(summary.block_number)
+        .query()
+        .await?;
+    let skipped: Vec<_> = skipped.iter().map(|event| event.to).collect();
+    assert_eq!(skipped, [payouts[1].0, payouts[2].0]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_transfer_atomic_reverts_whole_batch() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let payee = Address::random();
+    let payouts = [(payee, U256::from(10)), (Address::zero(), U256::from(5))];
+    let result = token.batch_transfer(&payouts, true).await;
+
+    assert!(
+        result.is_err(),
+        "atomic batch with a bad recipient went through"
+    );
+    assert!(token.balance_of(payee).await?.is_zero());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_whoami_reports_signer_balances() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(250)).await?;
+
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    let account = holder_token.whoami().await?;
+    assert_eq!(account.address, holder.address());
+    assert_eq!(account.eth_balance, parse_ether(1)?);
+    assert_eq!(
+        account.token_balance,
+        token.balance_of(holder.address()).await?
+    );
+
+    let read_only = MyTokenClient::new(address, Arc::new(env.provider.clone()));
+    assert!(matches!(
+        read_only.whoami().await,
+        Err(MyTokenError::NoSigner)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_over_balance_transfer_reports_amounts() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(40)).await?;
+
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    let result = holder_token
+        .transfer(env.owner.address(), U256::from(100))
+        .await;
+    match result {
+        Err(MyTokenError::InsufficientBalance { available, needed }) => {
+            assert_eq!(available, U256::from(40));
+            assert_eq!(needed, U256::from(100));
+        }
+        other => panic!("expected InsufficientBalance, got {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_of_entire_balance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(40)).await?;
+
+    // Exactly the balance is allowed, leaving nothing behind
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    let recipient = Address::random();
+    let balance = holder_token.balance_of(holder.address()).await?;
+    let summary = holder_token.transfer(recipient, balance).await?;
+    assert_eq!(summary.status, TxStatus::Success);
+    assert_eq!(token.balance_of(holder.address()).await?, U256::zero());
+    assert_eq!(token.balance_of(recipient).await?, U256::from(40));
+
+    // One unit more than that is not
+    let result = holder_token.transfer(recipient, U256::one()).await;
+    match result {
+        Err(MyTokenError::InsufficientBalance { available, needed }) => {
+            assert_eq!(available, U256::zero());
+            assert_eq!(needed, U256::one());
+        }
+        other => panic!("expected InsufficientBalance, got {other:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_to_self_keeps_balance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    // With a fee, so a self-transfer that paid it would show
+    token.set_transfer_fee(500).await?;
+
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(1000)).await?;
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+
+    let summary = holder_token
+        .transfer(holder.address(), U256::from(400))
+        .await?;
+    assert_eq!(summary.status, TxStatus::Success);
+    assert_eq!(token.balance_of(holder.address()).await?, U256::from(1000));
+
+    let event = token.transfer_from_events(summary.tx_hash).await?.transfer;
+    assert_eq!(
+        (event.from, event.to, event.value),
+        (holder.address(), holder.address(), U256::from(400))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_min_received_with_fee() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    token.set_transfer_fee(500).await?;
+    assert_eq!(token.transfer_fee_bps().await?, 500);
+
+    // Transfers from the owner are free, so the holder gets it all
+    let holder = funded_signer(&env).await?;
+    token.transfer(holder.address(), U256::from(1000)).await?;
+    assert_eq!(token.balance_of(holder.address()).await?, U256::from(1000));
+
+    let holder_token = MyTokenClient::new(address, env.signer_client(holder.clone()));
+    let payee = Address::random();
+    let result = holder_token
+        .transfer_min_received(payee, U256::from(1000), U256::from(951))
+        .await;
+    assert!(matches!(
+        result,
+        Err(MyTokenError::BelowMinimumReceived { received, .. }) if received == U256::from(950)
+    ));
+    assert_eq!(token.balance_of(holder.address()).await?, U256::from(1000));
+
+    holder_token
+        .transfer_min_received(payee, U256::from(1000), U256::from(950))
+        .await?;
+    assert_eq!(token.balance_of(payee).await?, U256::from(950));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reads_fail_over_to_next_url() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+
+    // Nothing listens on port 1
+    let provider = connect_fallback(&["http://127.0.0.1:1", RPC_URL]).await?;
+    let provider = Arc::new(provider);
+    let token = MyTokenClient::new(address, provider.clone());
+
+    assert_eq!(token.name().await?, "MyToken");
+    assert_eq!(provider.as_ref().as_ref().current(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_owner_sweeps_stray_eth() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    // Gas left to the estimate, as `receive` logging the deposit costs more
+    // than the 21000 of a plain transfer
+    let deposit = TransactionRequest::new().to(address).value(parse_ether(1)?);
+    env.client.send_transaction(deposit, None).await?.await?;
+    assert_eq!(
+        env.provider.get_balance(address, None).await?,
+        parse_ether(1)?
+    );
+
+    let stranger = funded_signer(&env).await?;
+    let stranger_token = MyTokenClient::new(address, env.signer_client(stranger.clone()));
+    assert!(stranger_token.sweep_eth(stranger.address()).await.is_err());
+
+    let other_account = Address::random();
+    token.sweep_eth(other_account).await?;
+    assert!(env.provider.get_balance(address, None).await?.is_zero());
+    assert_eq!(
+        env.provider.get_balance(other_account, None).await?,
+        parse_ether(1)?
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_smoke_test_passes_on_fresh_deployment() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let before = token.balance_of(env.owner.address()).await?;
+    let report = token.smoke_test().await?;
+    assert!(report.passed(), "smoke test failed:\n{report}");
+    // One unit transferred and one pulled by the spender, both from the signer
+    let after = token.balance_of(env.owner.address()).await?;
+    assert_eq!(before - after, U256::from(2));
+    let steps: Vec<_> = report.steps.iter().map(|step| step.name).collect();
+    assert_eq!(
+        steps,
+        [
+            "metadata",
+            "transfer",
+            "approve",
+            "transferFrom",
+            "insufficient balance reverts",
+            "insufficient allowance reverts",
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_balance_of_detailed_flags_contracts() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder_contract = env.deploy_implementation("MintReceiver").await?;
+    token.transfer(holder_contract, U256::from(70)).await?;
+    let detailed = token.balance_of_detailed(holder_contract).await?;
+    assert_eq!(detailed.balance, U256::from(70));
+    assert!(detailed.is_contract);
+
+    let eoa = Address::random();
+    token.transfer(eoa, U256::from(30)).await?;
+    let detailed = token.balance_of_detailed(eoa).await?;
+    assert_eq!(detailed.balance, U256::from(30));
+    assert!(!detailed.is_contract);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dry_run_transfer_changes_nothing() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone()).with_dry_run(true);
+
+    let recipient = Address::random();
+    let owner_balance = token.balance_of(env.owner.address()).await?;
+    let block = env.provider.get_block_number().await?;
+
+    let summary = token.transfer(recipient, U256::from(100)).await?;
+    assert_eq!(summary.status, TxStatus::Simulated);
+    assert!(summary.tx_hash.is_zero());
+
+    assert_eq!(env.provider.get_block_number().await?, block);
+    assert!(token.balance_of(recipient).await?.is_zero());
+    assert_eq!(token.balance_of(env.owner.address()).await?, owner_balance);
+
+    // A write that would revert still fails
+    let result = token.transfer(recipient, owner_balance + 1).await;
+    assert!(matches!(
+        result,
+        Err(MyTokenError::InsufficientBalance { .. })
+    ));
+    Ok(())
+}
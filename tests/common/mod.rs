@@ -0,0 +1,332 @@
+// Shared setup for the integration tests. Everything here talks to a local
+// Anvil instance and expects `forge build` to have produced the artifacts in
+// `out/`.
+#![allow(dead_code)]
+
+use ethers::{
+    abi::{Abi, Token},
+    prelude::*,
+    signers::coins_bip39::English,
+    utils::keccak256,
+};
+use eyre::{Result, WrapErr};
+use my_token::{checksum, from_token_units, to_token_units};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+pub const RPC_URL: &str = "http://localhost:8545";
+pub const WS_URL: &str = "ws://localhost:8545";
+pub const CHAIN_ID: u64 = 31337;
+
+// Anvil's first default account
+pub const DEPLOYER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+// Fixed mnemonic for test accounts, so the same addresses show up in every run.
+// Deliberately not Anvil's, whose accounts come prefunded.
+const TEST_MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+pub type TestClient = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+// Token addresses deployed by this process, keyed by the hash of the creation
+// bytecode plus the encoded constructor args
+static DEPLOYMENTS: OnceLock<Mutex<HashMap<H256, Address>>> = OnceLock::new();
+static DEPLOY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// All tests share the deployer account, so they take turns to avoid nonce clashes
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// The first `n` accounts derived from `TEST_MNEMONIC`
+pub fn test_wallets(n: usize) -> Vec<LocalWallet> {
+    (0..n as u32)
+        .map(|index| {
+            MnemonicBuilder::<English>::default()
+                .phrase(TEST_MNEMONIC)
+                .index(index)
+                .and_then(|builder| builder.build())
+                .expect("test mnemonic derives valid wallets")
+                .with_chain_id(CHAIN_ID)
+        })
+        .collect()
+}
+
+// Assert that `actual` raw units are `expected_human` tokens of `decimals`
+// decimals, showing both as token amounts when they aren't
+#[track_caller]
+pub fn assert_tokens_eq(actual: U256, expected_human: &str, decimals: u8) {
+    let expected = to_token_units(expected_human, decimals)
+        .unwrap_or_else(|err| panic!("invalid expected amount {expected_human:?}: {err}"));
+    assert!(
+        actual == expected,
+        "token amounts differ: got {} ({actual} raw), expected {} ({expected} raw)",
+        from_token_units(actual, decimals).unwrap_or_else(|_| actual.to_string()),
+        from_token_units(expected, decimals).unwrap_or_else(|_| expected.to_string()),
+    );
+}
+
+// A fresh wallet on the test chain, given 1 ETH by the deployer to pay for
+// its own transactions
+pub async fn funded_signer(env: &TestEnv) -> Result<LocalWallet> {
+    let wallet = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(CHAIN_ID);
+    env.fund(wallet.address(), ethers::utils::parse_ether(1)?)
+        .await?;
+    Ok(wallet)
+}
+
+pub struct Artifact {
+    pub abi: Abi,
+    pub bytecode: Bytes,
+    pub deployed_bytecode: Bytes,
+    // (start, length) of each immutable slot, filled in at deploy time
+    pub immutable_ranges: Vec<(usize, usize)>,
+}
+
+// Load a Foundry build artifact from `out/<name>.sol/<name>.json`
+pub fn load_artifact(name: &str) -> Result<Artifact> {
+    let path = format!("{}/out/{name}.sol/{name}.json", env!("CARGO_MANIFEST_DIR"));
+    let raw = std::fs::read_to_string(&path)
+        .wrap_err_with(|| format!("missing artifact {path}, run `forge build` first"))?;
+    let json: serde_json::Value = serde_json::from_str(&raw)?;
+
+    let abi: Abi = serde_json::from_value(json["abi"].clone())?;
+    let bytecode = json["bytecode"]["object"]
+        .as_str()
+        .ok_or_else(|| eyre::eyre!("artifact {path} has no bytecode"))?
+        .parse::<Bytes>()?;
+    let deployed_bytecode = json["deployedBytecode"]["object"]
+        .as_str()
+        .ok_or_else(|| eyre::eyre!("artifact {path} has no deployed bytecode"))?
+        .parse::<Bytes>()?;
+
+    let mut immutable_ranges = Vec::new();
+    if let Some(references) = json["deployedBytecode"]["immutableReferences"].as_object() {
+        for range in references
+            .values()
+            .filter_map(|ranges| ranges.as_array())
+            .flatten()
+        {
+            let start = range["start"].as_u64().unwrap_or_default() as usize;
+            let length = range["length"].as_u64().unwrap_or_default() as usize;
+            immutable_ranges.push((start, length));
+        }
+    }
+
+    Ok(Artifact {
+        abi,
+        bytecode,
+        deployed_bytecode,
+        immutable_ranges,
+    })
+}
+
+pub struct TestEnv {
+    pub provider: Provider<Http>,
+    pub owner: LocalWallet,
+    pub client: Arc<TestClient>,
+    _guard: tokio::sync::MutexGuard<'static, ()>,
+}
+
+impl TestEnv {
+    pub async fn new() -> Result<Self> {
+        let guard = ENV_LOCK.lock().await;
+
+        let provider = Provider::<Http>::try_from(RPC_URL)?.interval(Duration::from_millis(100));
+        let owner = LocalWallet::from_str(DEPLOYER_KEY)?.with_chain_id(CHAIN_ID);
+        let client = Arc::new(SignerMiddleware::new(provider.clone(), owner.clone()));
+
+        Ok(Self {
+            provider,
+            owner,
+            client,
+            _guard: guard,
+        })
+    }
+
+    // Client signing as `wallet`, sharing this env's provider
+    pub fn signer_client(&self, wallet: LocalWallet) -> Arc<TestClient> {
+        Arc::new(SignerMiddleware::new(self.provider.clone(), wallet))
+    }
+
+    // Send `amount` wei from the deployer to `to`
+    pub async fn fund(&self, to: Address, amount: U256) -> Result<()> {
+        let tx = TransactionRequest::new().to(to).value(amount).gas(21000);
+        self.client
+            .send_transaction(tx, None)
+            .await?
+            .await?
+            .ok_or_else(|| eyre::eyre!("no receipt for funding {}", checksum(to)))?;
+        Ok(())
+    }
+
+    // Deploy the token on first use and hand back the cached address afterwards.
+    // A cached address whose code has gone (e.g. reverted past with `revert`) or
+    // no longer matches the compiled contract is deployed again.
+    pub async fn deploy_once(&self, initial_owner: Address) -> Result<Address> {
+        self.deploy_once_with_decimals(initial_owner, 18).await
+    }
+
+    // `deploy_once` for a token with `decimals` decimals instead of 18
+    pub async fn deploy_once_with_decimals(
+        &self,
+        initial_owner: Address,
+        decimals: u8,
+    ) -> Result<Address> {
+        let artifact = load_artifact("MyToken")?;
+        let args = [
+            Token::Address(initial_owner),
+            Token::Uint(U256::from(decimals)),
+        ];
+        let key = H256(keccak256(
+            [artifact.bytecode.as_ref(), &ethers::abi::encode(&args)].concat(),
+        ));
+
+        let cached = deployments().lock().unwrap().get(&key).copied();
+        if let Some(address) = cached {
+            if self.is_current(address, &artifact).await? {
+                return Ok(address);
+            }
+            println!(
+                "Warning: cached MyToken at {} is stale, redeploying",
+                checksum(address)
+            );
+        }
+
+        let address = self.deploy(artifact, initial_owner, decimals).await?;
+        deployments().lock().unwrap().insert(key, address);
+        Ok(address)
+    }
+
+    // A token deployed just for the caller, never handed out by `deploy_once`,
+    // for tests that change what other tests take for granted, like the name
+    pub async fn deploy_fresh(&self, initial_owner: Address) -> Result<Address> {
+        self.deploy(load_artifact("MyToken")?, initial_owner, 18)
+            .await
+    }
+
+    // Use the token at `address` if its code matches the local build, deploying
+    // a fresh one otherwise. Guards against testing a stale deployment after
+    // changing the contract.
+    pub async fn ensure_deployed(
+        &self,
+        address: Address,
+        initial_owner: Address,
+    ) -> Result<Address> {
+        let artifact = load_artifact("MyToken")?;
+        if self.is_current(address, &artifact).await? {
+            return Ok(address);
+        }
+
+        println!(
+            "Warning: code at {} doesn't match the compiled MyToken, redeploying",
+            checksum(address)
+        );
+        self.deploy(artifact, initial_owner, 18).await
+    }
+
+    // Whether the code at `address` is the local build of `artifact`, ignoring
+    // the immutable slots that are only known after deployment
+    async fn is_current(&self, address: Address, artifact: &Artifact) -> Result<bool> {
+        let mut code = self.provider.get_code(address, None).await?.to_vec();
+        if code.len() != artifact.deployed_bytecode.len() {
+            return Ok(false);
+        }
+
+        for &(start, length) in &artifact.immutable_ranges {
+            if let Some(slot) = code.get_mut(start..start + length) {
+                slot.fill(0);
+            }
+        }
+        Ok(keccak256(&code) == keccak256(&artifact.deployed_bytecode))
+    }
+
+    // Number of `MyToken` deployments in this process, whether by `deploy_once`,
+    // `deploy_fresh` or `ensure_deployed`
+    pub fn deploy_count() -> usize {
+        DEPLOY_COUNT.load(Ordering::SeqCst)
+    }
+
+    // Deploy a `MyVault` holding `asset`
+    pub async fn deploy_vault(&self, asset: Address) -> Result<Address> {
+        let artifact = load_artifact("MyVault")?;
+        let factory = ContractFactory::new(artifact.abi, artifact.bytecode, self.client.clone());
+        let contract = factory.deploy(asset)?.legacy().send().await?;
+        Ok(contract.address())
+    }
+
+    // Deploy `MyTokenUpgradeable` behind a `MyTokenProxy`, initialized for
+    // `initial_owner`, returning the proxy address
+    pub async fn deploy_proxied(&self, initial_owner: Address) -> Result<Address> {
+        let implementation = self.deploy_implementation("MyTokenUpgradeable").await?;
+        let initialize = load_artifact("MyTokenUpgradeable")?
+            .abi
+            .function("initialize")?
+            .encode_input(&[Token::Address(initial_owner)])?;
+
+        let artifact = load_artifact("MyTokenProxy")?;
+        let factory = ContractFactory::new(artifact.abi, artifact.bytecode, self.client.clone());
+        let contract = factory
+            .deploy((implementation, Bytes::from(initialize)))?
+            .legacy()
+            .send()
+            .await?;
+        Ok(contract.address())
+    }
+
+    // Deploy a contract whose constructor takes no arguments, such as the
+    // `MyTokenV2` implementation or a helper from `test/`, by artifact name
+    pub async fn deploy_implementation(&self, name: &str) -> Result<Address> {
+        let artifact = load_artifact(name)?;
+        let factory = ContractFactory::new(artifact.abi, artifact.bytecode, self.client.clone());
+        let contract = factory.deploy(())?.legacy().send().await?;
+        Ok(contract.address())
+    }
+
+    // Take an Anvil snapshot, returning its id for `revert`
+    pub async fn snapshot(&self) -> Result<U256> {
+        Ok(self.provider.request("evm_snapshot", ()).await?)
+    }
+
+    // Roll the chain back to a snapshot taken with `snapshot`
+    pub async fn revert(&self, id: U256) -> Result<()> {
+        let reverted: bool = self.provider.request("evm_revert", [id]).await?;
+        eyre::ensure!(reverted, "failed to revert to snapshot {id}");
+        Ok(())
+    }
+
+    // Move the chain clock forward by `seconds` and mine a block at the new time
+    pub async fn increase_time(&self, seconds: u64) -> Result<()> {
+        let _: serde_json::Value = self.provider.request("evm_increaseTime", [seconds]).await?;
+        let _: U256 = self.provider.request("evm_mine", ()).await?;
+        Ok(())
+    }
+
+    // Switch Anvil between mining on every transaction and leaving them pending
+    pub async fn set_automine(&self, enabled: bool) -> Result<()> {
+        let _: () = self.provider.request("evm_setAutomine", [enabled]).await?;
+        Ok(())
+    }
+
+    async fn deploy(
+        &self,
+        artifact: Artifact,
+        initial_owner: Address,
+        decimals: u8,
+    ) -> Result<Address> {
+        let factory = ContractFactory::new(artifact.abi, artifact.bytecode, self.client.clone());
+        let contract = factory
+            .deploy((initial_owner, decimals))?
+            .legacy()
+            .send()
+            .await?;
+        DEPLOY_COUNT.fetch_add(1, Ordering::SeqCst);
+        println!("Deployed MyToken at {}", checksum(contract.address()));
+        Ok(contract.address())
+    }
+}
+
+fn deployments() -> &'static Mutex<HashMap<H256, Address>> {
+    DEPLOYMENTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{TestEnv, assert_tokens_eq, test_wallets};
+use ethers::{
+    signers::Signer,
+    types::{Address, Bytes, U256},
+};
+use eyre::Result;
+
+#[tokio::test]
+async fn test_deploy_once_reuses_deployment() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let owner = env.owner.address();
+
+    let first = env.deploy_once(owner).await?;
+    let deployments = TestEnv::deploy_count();
+
+    let second = env.deploy_once(owner).await?;
+    assert_eq!(first, second);
+    assert_eq!(TestEnv::deploy_count(), deployments);
+
+    // A snapshot taken after deployment keeps the cached address usable
+    let snapshot = env.snapshot().await?;
+    env.revert(snapshot).await?;
+    assert_eq!(env.deploy_once(owner).await?, first);
+    assert_eq!(TestEnv::deploy_count(), deployments);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ensure_deployed_replaces_stale_code() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let owner = env.owner.address();
+
+    // An address carrying code that isn't the current MyToken build
+    let stale = Address::random();
+    let junk = Bytes::from(vec![0x60, 0x00, 0x60, 0x00, 0xfd]);
+    let _: () = env.provider.request("anvil_setCode", (stale, junk)).await?;
+
+    let deployments = TestEnv::deploy_count();
+    let address = env.ensure_deployed(stale, owner).await?;
+    assert_ne!(address, stale);
+    assert_eq!(TestEnv::deploy_count(), deployments + 1);
+
+    // The fresh deployment is recognised as current
+    assert_eq!(env.ensure_deployed(address, owner).await?, address);
+    assert_eq!(TestEnv::deploy_count(), deployments + 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_wallets_are_deterministic() {
+    let first: Vec<Address> = test_wallets(3).iter().map(Signer::address).collect();
+    let second: Vec<Address> = test_wallets(3).iter().map(Signer::address).collect();
+
+    assert_eq!(first, second);
+    assert_eq!(first.len(), 3);
+    assert_ne!(first[0], first[1]);
+}
+
+#[test]
+fn test_assert_tokens_eq_compares_human_amounts() {
+    assert_tokens_eq(U256::from(1_500_000), "1.5", 6);
+    assert_tokens_eq(U256::exp10(18), "1", 18);
+
+    let mismatch = std::panic::catch_unwind(|| assert_tokens_eq(U256::from(1_000_000), "1.5", 6));
+    let message = mismatch.expect_err("unequal amounts passed");
+    let message = message
+        .downcast_ref::<String>()
+        .expect("panic message is a string");
+    assert_eq!(
+        message,
+        "token amounts differ: got 1 (1000000 raw), expected 1.5 (1500000 raw)"
+    );
+}
@@ -7,6 +7,10 @@ use eyre::Result;
 use std::sync::Arc;
 use std::str::FromStr;
 
+mod common;
+
+use common::assert_tokens_eq;
+
 // Test contract ABI
 abigen!(
     TestERC20,
@@ -20,6 +24,7 @@ abigen!(
         function allowance(address owner, address spender) external view returns (uint256)
         function approve(address spender, uint256 amount) external returns (bool)
         function transferFrom(address from, address to, uint256 amount) external returns (bool)
+        event Approval(address indexed owner, address indexed spender, uint256 value)
     ]"#,
 );
 
@@ -43,26 +48,28 @@ async fn test_erc20_contract() -> Result<()> {
     let contract_address = H160::from_str("0x5FbDB2315678afecb367f032d93F642f64180aa3").unwrap();
     let contract = TestERC20::new(contract_address, client.clone());
     
-    let other_account = LocalWallet::new(&mut rand::thread_rng())
-        .with_chain_id(31337u64);
-    println!("Other account address: {}", other_account.address());
+    // Named accounts from a fixed mnemonic, so addresses match across runs
+    let [other_account, recipient, new_recipient, another_account]: [LocalWallet; 4] =
+        common::test_wallets(4).try_into().unwrap();
+    println!("Other account address: {}", my_token::checksum(other_account.address()));
     
     // Test 1: Initial state
     println!("Testing initial state...");
     assert_eq!(contract.name().call().await?, "MyToken");
     assert_eq!(contract.symbol().call().await?, "MTK");
-    assert_eq!(contract.decimals().call().await?, 18);
+    let decimals = contract.decimals().call().await?;
+    assert_eq!(decimals, 18);
     
-    let expected_supply = U256::from(1000000) * U256::from(10).pow(U256::from(18));
     let total_supply = contract.total_supply().call().await?;
-    assert_eq!(total_supply, expected_supply);
+    assert_tokens_eq(total_supply, "1000000", decimals);
     
     let owner_balance = contract.balance_of(owner.address()).call().await?;
-    assert_eq!(owner_balance, total_supply);
+    assert_tokens_eq(owner_balance, "1000000", decimals);
     
     // Test 2: Transfer
     println!("Testing transfer...");
-    let amount = U256::from(100);
+    let amount_tokens = "100";
+    let amount = my_token::to_token_units(amount_tokens, decimals)?;
     let nonce = client.get_transaction_count(
         owner.address(),
         None
@@ -70,9 +77,6 @@ async fn test_erc20_contract() -> Result<()> {
     
     let tx = contract
         .transfer(other_account.address(), amount)
-        .legacy()
-        .gas(300000)
-        .gas_price(U256::from(2u64 * 1_000_000_000u64))
         .nonce(nonce);
     
     let pending_tx = tx.send().await?;
@@ -85,7 +89,7 @@ async fn test_erc20_contract() -> Result<()> {
         .balance_of(other_account.address())
         .call()
         .await?;
-    assert_eq!(recipient_balance, amount);
+    assert_tokens_eq(recipient_balance, amount_tokens, decimals);
     
     // *** IMPORTANT: Send ETH to other_account to pay for gas ***
     println!("Funding other_account with ETH for gas...");
@@ -97,8 +101,6 @@ async fn test_erc20_contract() -> Result<()> {
     let tx_request = TransactionRequest::new()
         .to(other_account.address())
         .value(U256::from(1000000000000000000u64)) // 1 ETH
-        .gas(21000)
-        .gas_price(U256::from(2u64 * 1_000_000_000u64))
         .nonce(nonce);
     
     let pending_tx = client.send_transaction(tx_request, None).await?;
@@ -109,7 +111,7 @@ async fn test_erc20_contract() -> Result<()> {
     
     // Test 3: Approve and TransferFrom
     println!("Testing approve and transferFrom...");
-    let approve_amount = U256::from(200);
+    let approve_amount = amount * 2;
     let nonce = client.get_transaction_count(
         owner.address(),
         None
@@ -117,14 +119,11 @@ async fn test_erc20_contract() -> Result<()> {
     
     let approve_tx = contract
         .approve(other_account.address(), approve_amount)
-        .legacy()
-        .gas(300000)
-        .gas_price(U256::from(3u64 * 1_000_000_000u64))
         .nonce(nonce);
     
     let pending_tx = approve_tx.send().await?;
-    let receipt = pending_tx.await?;
-    println!("Approve transaction confirmed: {:?}", receipt.unwrap().transaction_hash);
+    let receipt = pending_tx.await?.unwrap();
+    println!("Approve transaction confirmed: {:?}", receipt.transaction_hash);
     
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
     
@@ -134,6 +133,22 @@ async fn test_erc20_contract() -> Result<()> {
         .await?;
     assert_eq!(allowance, approve_amount);
     
+    // The approve should have emitted a matching Approval event
+    let approval_block = receipt.block_number.unwrap();
+    let approvals = contract
+        .approval_filter()
+        .from_block(approval_block)
+        .to_block(approval_block)
+        .query_with_meta()
+        .await?;
+    let (approval, _) = approvals
+        .iter()
+        .find(|(_, meta)| meta.transaction_hash == receipt.transaction_hash)
+        .expect("approve emitted no Approval event");
+    assert_eq!(approval.owner, owner.address());
+    assert_eq!(approval.spender, other_account.address());
+    assert_eq!(approval.value, approve_amount);
+    
     // Now create a client for the other account to execute transferFrom
     let other_client = Arc::new(SignerMiddleware::new(
         provider.clone(),
@@ -146,11 +161,10 @@ async fn test_erc20_contract() -> Result<()> {
     
     let other_contract = TestERC20::new(contract_address, other_client.clone());
     
-    let recipient = LocalWallet::new(&mut rand::thread_rng())
-        .with_chain_id(31337u64);
-    println!("Recipient address: {}", recipient.address());
+    println!("Recipient address: {}", my_token::checksum(recipient.address()));
     
-    let transfer_amount = U256::from(150);
+    let transfer_tokens = "150";
+    let transfer_amount = my_token::to_token_units(transfer_tokens, decimals)?;
     let nonce = other_client.get_transaction_count(
         other_account.address(),
         None
@@ -158,9 +172,6 @@ async fn test_erc20_contract() -> Result<()> {
     
     let transfer_tx = other_contract
         .transfer_from(owner.address(), recipient.address(), transfer_amount)
-        .legacy()
-        .gas(300000)
-        .gas_price(U256::from(4u64 * 1_000_000_000u64))
         .nonce(nonce);
     
     let pending_tx = transfer_tx.send().await?;
@@ -173,7 +184,7 @@ async fn test_erc20_contract() -> Result<()> {
         .balance_of(recipient.address())
         .call()
         .await?;
-    assert_eq!(recipient_balance, transfer_amount);
+    assert_tokens_eq(recipient_balance, transfer_tokens, decimals);
     
     // Test 4: Insufficient balance - use call() instead of send()
     println!("Testing insufficient balance...");
@@ -198,11 +209,6 @@ async fn test_erc20_contract() -> Result<()> {
     
     // Test 5: Insufficient allowance - use call() instead of send()
     println!("Testing insufficient allowance...");
-    let new_recipient = LocalWallet::new(&mut rand::thread_rng())
-        .with_chain_id(31337u64);
-    
-    let another_account = LocalWallet::new(&mut rand::thread_rng())
-        .with_chain_id(31337u64);
     
     // Fund another_account with ETH
     let nonce = client.get_transaction_count(
@@ -213,8 +219,6 @@ async fn test_erc20_contract() -> Result<()> {
     let tx_request = TransactionRequest::new()
         .to(another_account.address())
         .value(U256::from(1000000000000000000u64)) // 1 ETH
-        .gas(21000)
-        .gas_price(U256::from(6u64 * 1_000_000_000u64))
         .nonce(nonce);
     
     let pending_tx = client.send_transaction(tx_request, None).await?;
@@ -0,0 +1,144 @@
+mod common;
+
+use common::TestEnv;
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{Address, U256},
+};
+use eyre::Result;
+use my_token::{BalanceMismatch, Indexer, MyTokenClient};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn test_cancelled_indexer_persists_last_block() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let recipient = Address::random();
+    let summary = token.transfer(recipient, U256::from(9)).await?;
+    let path = std::env::temp_dir().join(format!("indexer-{recipient:?}.sqlite"));
+
+    let indexer = Arc::new(
+        Indexer::open(MyTokenClient::new(address, env.client.clone()), &path)?
+            .with_start_block(summary.block_number)
+            .with_poll_interval(Duration::from_millis(100)),
+    );
+    let cancel = CancellationToken::new();
+    let task = tokio::spawn({
+        let indexer = indexer.clone();
+        let cancel = cancel.clone();
+        async move { indexer.run(cancel).await }
+    });
+
+    // Let it catch up with the transfer, then stop it
+    while indexer
+        .last_block()?
+        .is_none_or(|last| last < summary.block_number)
+    {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    cancel.cancel();
+    let last = tokio::time::timeout(Duration::from_secs(5), task).await???;
+    assert!(last >= Some(summary.block_number));
+
+    // A fresh indexer over the same file sees what the first one committed
+    drop(indexer);
+    let reopened = Indexer::open(MyTokenClient::new(address, env.client.clone()), &path)?;
+    assert_eq!(reopened.last_block()?, last);
+    assert!(
+        reopened
+            .transfers()?
+            .iter()
+            .any(|transfer| transfer.to == recipient && transfer.tx_hash == summary.tx_hash)
+    );
+    assert!(last <= Some(env.provider.get_block_number().await?.as_u64()));
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reorg_rolls_back_orphaned_transfers() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let kept = Address::random();
+    let first = token.transfer(kept, U256::from(1)).await?;
+    let path = std::env::temp_dir().join(format!("indexer-{kept:?}.sqlite"));
+    let indexer = Indexer::open(MyTokenClient::new(address, env.client.clone()), &path)?
+        .with_start_block(first.block_number);
+    indexer.index_once().await?;
+
+    // Index a transfer, then replace its block with one holding another
+    let fork = env.snapshot().await?;
+    let orphaned = Address::random();
+    let orphaned_tx = token.transfer(orphaned, U256::from(2)).await?;
+    indexer.index_once().await?;
+    assert!(indexer.transfers()?.iter().any(|t| t.to == orphaned));
+
+    env.revert(fork).await?;
+    let canonical = Address::random();
+    let canonical_tx = token.transfer(canonical, U256::from(3)).await?;
+    assert_eq!(canonical_tx.block_number, orphaned_tx.block_number);
+    indexer.index_once().await?;
+
+    let transfers = indexer.transfers()?;
+    assert!(transfers.iter().all(|t| t.tx_hash != orphaned_tx.tx_hash));
+    assert!(transfers.iter().any(|t| t.to == kept));
+    let replacement = transfers
+        .iter()
+        .find(|t| t.to == canonical)
+        .expect("canonical transfer indexed");
+    let block = env.provider.get_block(canonical_tx.block_number).await?;
+    assert_eq!(Some(replacement.block_hash), block.and_then(|b| b.hash));
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reconcile_flags_corrupted_balance() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let start = env.provider.get_block_number().await?.as_u64();
+    let address = env.deploy_fresh(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+
+    let holder = Address::random();
+    let minted = token.mint(holder, U256::from(500)).await?;
+    token.transfer(Address::random(), U256::from(7)).await?;
+    let path = std::env::temp_dir().join(format!("indexer-{holder:?}.sqlite"));
+    let indexer = Indexer::open(MyTokenClient::new(address, env.client.clone()), &path)?
+        .with_start_block(start);
+    indexer.index_once().await?;
+    assert!(token.reconcile(&path).await?.is_consistent());
+
+    // The mint is the holder's only transfer, and comes from the zero
+    // address, so inflating it throws off no one else
+    rusqlite::Connection::open(&path)?.execute(
+        "UPDATE transfers SET value = '900' WHERE tx_hash = ?1",
+        [format!("{:?}", minted.tx_hash)],
+    )?;
+    let report = token.reconcile(&path).await?;
+    assert_eq!(
+        report.mismatches,
+        vec![BalanceMismatch {
+            account: holder,
+            indexed: U256::from(900),
+            on_chain: U256::from(500),
+        }]
+    );
+
+    // Read through Multicall3 instead, the balances are the same
+    let multicall = env.deploy_implementation("Multicall3").await?;
+    let batched = token.at(address).with_multicall(multicall);
+    assert_eq!(batched.reconcile(&path).await?, report);
+
+    drop(indexer);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,1725 @@
+// Tests that run against mocked providers, with no node needed
+
+use async_trait::async_trait;
+use axum::{Form, Json, Router, extract::Query, routing::post};
+use ethers::{
+    abi::{self, AbiDecode, AbiEncode, Token},
+    contract::EthEvent,
+    middleware::SignerMiddleware,
+    providers::{
+        JsonRpcClient, JsonRpcError, Middleware, MockError, MockProvider, MockResponse, Provider,
+    },
+    signers::{LocalWallet, Signer},
+    types::{
+        Address, Block, Bytes, Eip1559TransactionRequest, FeeHistory, H256, Log, Transaction,
+        TransactionReceipt, TxHash, U64, U256, transaction::eip2718::TypedTransaction,
+    },
+    utils::{hex, rlp::Rlp},
+};
+use eyre::Result;
+use futures::StreamExt;
+use my_token::{
+    AirdropLog, Chain, Config, DecimalsCall, ETHERSCAN_API_URL, EnrichedTransfer, GasStrategy,
+    IdempotencyStore, Indexer, JsonLinesStore, KeyedTransfer, LegacyGas, MemoryStore, MockTxSender,
+    MyTokenClient, MyTokenError, NetworkConfig, ScanCursor, TRANSFER_TOPIC, TokenApi, TransferCall,
+    TransferFilter, TransportKind, TxSender, TxStatus, TxSummary, TxType, Verification, ZeroAmount,
+    airdrop, checksum, connect, connect_fallback, decode_transfer_log, format_transfer,
+    from_token_units, to_token_units,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// Mock transport that counts the requests made for each method, keeping the
+// params of the latest one
+#[derive(Debug)]
+struct Counting {
+    inner: MockProvider,
+    calls: Mutex<HashMap<String, usize>>,
+    params: Mutex<HashMap<String, serde_json::Value>>,
+}
+
+impl Counting {
+    fn new(inner: MockProvider) -> Self {
+        Self {
+            inner,
+            calls: Mutex::default(),
+            params: Mutex::default(),
+        }
+    }
+
+    fn calls(&self, method: &str) -> usize {
+        self.calls.lock().unwrap().get(method).copied().unwrap_or(0)
+    }
+
+    fn params(&self, method: &str) -> Option<serde_json::Value> {
+        self.params.lock().unwrap().get(method).cloned()
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for Counting {
+    type Error = MockError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        *self
+            .calls
+            .lock()
+            .unwrap()
+            .entry(method.to_owned())
+            .or_default() += 1;
+        let value = serde_json::to_value(&params).map_err(MockError::SerdeJson)?;
+        self.params.lock().unwrap().insert(method.to_owned(), value);
+        self.inner.request(method, params).await
+    }
+}
+
+// Chain where the one transaction is mined `mined_after` into the test, and
+// the block number never moves, counting receipt polls. The transaction is
+// never in its mempool.
+#[derive(Debug)]
+struct SlowlyMined {
+    start: tokio::time::Instant,
+    mined_after: Duration,
+    polls: AtomicUsize,
+}
+
+impl SlowlyMined {
+    fn new(mined_after: Duration) -> Self {
+        Self {
+            start: tokio::time::Instant::now(),
+            mined_after,
+            polls: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for SlowlyMined {
+    type Error = MockError;
+
+    async fn request<T, R>(&self, method: &str, _params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let value = match method {
+            "eth_getTransactionReceipt" => {
+                self.polls.fetch_add(1, Ordering::SeqCst);
+                let receipt = TransactionReceipt {
+                    block_number: Some(U64::from(5)),
+                    status: Some(U64::one()),
+                    ..TransactionReceipt::default()
+                };
+                let mined = self.start.elapsed() >= self.mined_after;
+                serde_json::to_value(mined.then_some(receipt))
+            }
+            "eth_blockNumber" => serde_json::to_value(U64::from(5)),
+            "eth_chainId" => serde_json::to_value(U256::from(31337)),
+            "eth_getTransactionCount" => serde_json::to_value(U256::zero()),
+            "eth_getTransactionByHash" => Ok(serde_json::Value::Null),
+            _ => return Err(MockError::EmptyResponses),
+        };
+        serde_json::from_value(value?).map_err(MockError::SerdeJson)
+    }
+}
+
+#[tokio::test]
+async fn test_transfer_rejects_zero_recipient() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+
+    let result = token.transfer(Address::zero(), U256::from(100)).await;
+    assert!(matches!(result, Err(MyTokenError::InvalidRecipient(_))));
+
+    let result = token
+        .transfer_from(Address::random(), Address::zero(), U256::from(100))
+        .await;
+    assert!(matches!(result, Err(MyTokenError::InvalidRecipient(_))));
+
+    // Nothing reached the provider
+    assert!(matches!(
+        mock.assert_request("eth_sendTransaction", ()),
+        Err(MockError::EmptyRequests)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_zero_amount_policy() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+
+    let result = token.transfer(Address::random(), U256::zero()).await;
+    assert!(matches!(result, Err(MyTokenError::ZeroAmount)));
+
+    let token = token.with_zero_amount(ZeroAmount::Skip);
+    let summary = token.transfer(Address::random(), U256::zero()).await?;
+    assert_eq!(summary.status, TxStatus::Skipped);
+
+    assert!(matches!(
+        mock.assert_request("eth_sendTransaction", ()),
+        Err(MockError::EmptyRequests)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_safe_transfer_surfaces_false_return() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+
+    // A non-compliant token answering `transfer` with `false` instead of reverting
+    let returned = Bytes::from(abi::encode(&[Token::Bool(false)]));
+    mock.push::<Bytes, _>(returned)?;
+
+    // Failing here, rather than on the missing response for a send, shows the
+    // transaction was never broadcast
+    let result = token
+        .safe_transfer(Address::random(), U256::from(100))
+        .await;
+    assert!(matches!(result, Err(MyTokenError::ReturnedFalse)));
+    Ok(())
+}
+
+#[test]
+fn test_format_transfer() {
+    let event = TransferFilter {
+        from: "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"
+            .parse()
+            .unwrap(),
+        to: "0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359"
+            .parse()
+            .unwrap(),
+        value: U256::from(1_500_000_000_000_000_000u64),
+    };
+
+    assert_eq!(
+        format_transfer(&event, 18).unwrap(),
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed -> 0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359: 1.5"
+    );
+}
+
+#[test]
+fn test_decode_transfer_log() {
+    assert_eq!(TRANSFER_TOPIC, TransferFilter::signature());
+
+    let from = Address::random();
+    let to = Address::random();
+    let log = Log {
+        address: Address::random(),
+        topics: vec![TRANSFER_TOPIC, H256::from(from), H256::from(to)],
+        data: U256::from(42).encode().into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        decode_transfer_log(&log),
+        Some(TransferFilter {
+            from,
+            to,
+            value: U256::from(42),
+        })
+    );
+}
+
+#[test]
+fn test_decode_transfer_log_ignores_other_events() {
+    // Laid out like a transfer, but under another event's topic
+    let log = Log {
+        topics: vec![H256::random(), H256::random(), H256::random()],
+        data: U256::from(42).encode().into(),
+        ..Default::default()
+    };
+    assert_eq!(decode_transfer_log(&log), None);
+}
+
+#[tokio::test]
+async fn test_symbol_falls_back_to_bytes32() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+
+    // Older tokens like MKR return a zero-padded bytes32 instead of a string
+    let mut symbol = [0u8; 32];
+    symbol[..3].copy_from_slice(b"MKR");
+    let returned = Bytes::from(abi::encode(&[Token::FixedBytes(symbol.to_vec())]));
+    mock.push::<Bytes, _>(returned)?;
+    assert_eq!(token.symbol().await?, "MKR");
+
+    // A fresh client, as the symbol above is remembered
+    let returned = Bytes::from(abi::encode(&[Token::String("MTK".into())]));
+    mock.push::<Bytes, _>(returned)?;
+    assert_eq!(token.at(Address::random()).symbol().await?, "MTK");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_permit_domain_separator_follows_chain_id() -> Result<()> {
+    let mock = MockProvider::new();
+    let counting = Arc::new(Provider::new(Counting::new(mock.clone())));
+    let token = MyTokenClient::new(Address::random(), counting.clone());
+    let owner = LocalWallet::new(&mut rand::thread_rng());
+    let sign = || {
+        token.sign_permit(
+            &owner,
+            Address::zero(),
+            U256::from(100),
+            U256::MAX,
+            Some(U256::zero()),
+        )
+    };
+    // Answers are popped last pushed first: the chain id, then the separator
+    let separator = |seed: u8| Bytes::from(H256::repeat_byte(seed).encode());
+
+    mock.push::<Bytes, _>(separator(1))?;
+    mock.push(U256::from(31337))?;
+    let first = sign().await?;
+    // Same chain, so the separator is reused
+    mock.push(U256::from(31337))?;
+    assert_eq!(sign().await?, first);
+    assert_eq!(counting.as_ref().as_ref().calls("eth_call"), 1);
+
+    // On a fork with its own chain id, the contract's separator is read again
+    mock.push::<Bytes, _>(separator(2))?;
+    mock.push(U256::from(1))?;
+    let forked = sign().await?;
+    assert_eq!(counting.as_ref().as_ref().calls("eth_call"), 2);
+    assert_ne!((forked.r, forked.s), (first.r, first.s));
+    mock.push(U256::from(1))?;
+    assert_eq!(token.domain_separator().await?, H256::repeat_byte(2));
+    Ok(())
+}
+
+#[test]
+fn test_checksum_matches_eip55() {
+    let address: Address = "0xdbf03b407c01e7cd3cbea99509d93f8dddc8c6fb"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        checksum(address),
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB"
+    );
+}
+
+#[tokio::test]
+async fn test_config_selects_connected_network() -> Result<()> {
+    let config = Config::from_toml(
+        r#"
+        [networks.1]
+        tx_type = "legacy"
+        confirmations = 3
+
+        [networks.31337]
+        tx_type = "eip1559"
+        gas = { strategy = "oracle", percentile = 50 }
+        poll_interval_ms = 100
+        "#,
+    )?;
+
+    let (provider, mock) = Provider::mocked();
+    mock.push(U256::from(31337))?;
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider))
+        .with_config(&config)
+        .await?;
+
+    assert_eq!(token.network().tx_type, TxType::Eip1559);
+    assert_eq!(
+        token.network().gas,
+        GasStrategy::Oracle { percentile: 50.0 }
+    );
+    assert_eq!(token.network().confirmations, 1);
+    assert_eq!(token.network().poll_interval_ms, Some(100));
+    Ok(())
+}
+
+// A `Transfer` of `value` from `from` to `to` logged by `token`, first in
+// its own transaction in `block`
+fn transfer_log(token: Address, from: Address, to: Address, value: U256, block: u64) -> Log {
+    Log {
+        address: token,
+        topics: vec![
+            TransferFilter::signature(),
+            H256::from(from),
+            H256::from(to),
+        ],
+        data: abi::encode(&[Token::Uint(value)]).into(),
+        block_number: Some(U64::from(block)),
+        block_hash: Some(H256::random()),
+        transaction_hash: Some(TxHash::random()),
+        transaction_index: Some(U64::zero()),
+        log_index: Some(U256::zero()),
+        ..Log::default()
+    }
+}
+
+#[tokio::test]
+async fn test_block_timestamps_are_cached() -> Result<()> {
+    let mock = MockProvider::new();
+    let counting = Arc::new(Provider::new(Counting::new(mock.clone())));
+    let address = Address::random();
+    let token = MyTokenClient::new(address, counting.clone());
+
+    // Two transfers mined in the same block
+    let transfer = |log_index: u64| Log {
+        transaction_index: Some(U64::from(log_index)),
+        log_index: Some(U256::from(log_index)),
+        ..transfer_log(
+            address,
+            Address::random(),
+            Address::random(),
+            U256::from(100),
+            7,
+        )
+    };
+    let logs = vec![transfer(0), transfer(1)];
+    let block = Block::<TxHash> {
+        number: Some(U64::from(7)),
+        timestamp: U256::from(1_700_000_000),
+        ..Block::default()
+    };
+
+    // Responses are served last-pushed first
+    mock.push::<Vec<Log>, _>(logs.clone())?;
+    mock.push::<Block<TxHash>, _>(block)?;
+    mock.push::<Vec<Log>, _>(logs)?;
+
+    let first = token.enriched_transfers(0).await?;
+    let second = token.enriched_transfers(0).await?;
+
+    assert_eq!(first.len(), 2);
+    assert!(
+        first
+            .iter()
+            .all(|transfer| transfer.timestamp == 1_700_000_000)
+    );
+    assert_eq!(second, first);
+    assert_eq!(counting.as_ref().as_ref().calls("eth_getBlockByNumber"), 1);
+    Ok(())
+}
+
+#[test]
+fn test_to_token_units_overflow() {
+    assert_eq!(
+        to_token_units("1.5", 18).unwrap(),
+        U256::from(1_500_000_000_000_000_000u64)
+    );
+
+    // 10^60 whole tokens is ~2^262 raw units at 18 decimals
+    let absurd = format!("1{}", "0".repeat(60));
+    assert!(matches!(
+        to_token_units(&absurd, 18),
+        Err(MyTokenError::AmountOverflow)
+    ));
+
+    // U256::MAX itself still fits, one raw unit more doesn't
+    let max = from_token_units(U256::MAX, 18).unwrap();
+    assert_eq!(to_token_units(&max, 18).unwrap(), U256::MAX);
+    let above_max =
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936";
+    assert!(matches!(
+        to_token_units(above_max, 0),
+        Err(MyTokenError::AmountOverflow)
+    ));
+
+    assert!(matches!(
+        to_token_units("0.0000001", 6),
+        Err(MyTokenError::InvalidAmount(_))
+    ));
+}
+
+#[test]
+fn test_unit_conversions_refuse_absurd_decimals() {
+    assert!(matches!(
+        from_token_units(U256::from(1), 100),
+        Err(MyTokenError::UnsupportedDecimals(100))
+    ));
+    assert!(matches!(
+        to_token_units("1", 100),
+        Err(MyTokenError::UnsupportedDecimals(100))
+    ));
+
+    // 77 is the most that still fits
+    assert_eq!(
+        from_token_units(U256::exp10(77), 77).unwrap(),
+        "1".to_owned()
+    );
+}
+
+#[test]
+fn test_offline_transfer_recovers_signer() -> Result<()> {
+    let (provider, _mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+
+    let gas = LegacyGas {
+        limit: U256::from(100_000),
+        price: U256::from(1_000_000_000u64),
+    };
+    let signed = token.sign_transfer_offline(
+        &wallet,
+        U256::from(3),
+        31337,
+        Address::random(),
+        U256::one(),
+        gas,
+    )?;
+
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&signed))?;
+    assert_eq!(signature.recover(tx.sighash())?, wallet.address());
+    assert_eq!(tx.nonce(), Some(&U256::from(3)));
+    assert_eq!(tx.chain_id(), Some(31337.into()));
+    assert_eq!(tx.to_addr(), Some(&token.address()));
+    Ok(())
+}
+
+#[test]
+fn test_explorer_tx_urls() {
+    let hash: TxHash = "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+        .parse()
+        .unwrap();
+
+    assert_eq!(Chain::from_id(11155111), Some(Chain::Sepolia));
+    assert_eq!(
+        Chain::Sepolia.explorer_tx_url(hash).as_deref(),
+        Some(
+            "https://sepolia.etherscan.io/tx/0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060"
+        )
+    );
+    assert_eq!(Chain::from_id(31337), Some(Chain::Anvil));
+    assert_eq!(Chain::Anvil.explorer_tx_url(hash), None);
+}
+
+#[tokio::test]
+async fn test_try_balances_isolates_failures() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    let token =
+        MyTokenClient::new(Address::random(), Arc::new(provider)).with_multicall(Address::random());
+
+    // tryAggregate's answer: the middle call reverted, the others succeeded
+    let result =
+        |success: bool, data: Vec<u8>| Token::Tuple(vec![Token::Bool(success), Token::Bytes(data)]);
+    let returned = Bytes::from(abi::encode(&[Token::Array(vec![
+        result(true, abi::encode(&[Token::Uint(U256::from(100))])),
+        result(false, Vec::new()),
+        result(true, abi::encode(&[Token::Uint(U256::from(5))])),
+    ])]));
+    mock.push::<Bytes, _>(returned)?;
+
+    let accounts = [Address::random(), Address::random(), Address::random()];
+    let balances = token.try_balances(&accounts).await;
+
+    assert_eq!(balances.len(), 3);
+    assert_eq!(balances[0].as_ref().ok(), Some(&U256::from(100)));
+    assert!(matches!(balances[1], Err(MyTokenError::Contract(_))));
+    assert_eq!(balances[2].as_ref().ok(), Some(&U256::from(5)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_behind_token_api_trait_object() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    let address = Address::random();
+    let token: Arc<dyn TokenApi> = Arc::new(MyTokenClient::new(address, Arc::new(provider)));
+
+    mock.push::<Bytes, _>(Bytes::from(abi::encode(&[Token::Uint(U256::from(42))])))?;
+
+    // Shared across tasks, as a web handler's state would be
+    let balance = tokio::spawn({
+        let token = token.clone();
+        async move { token.balance_of(Address::random()).await }
+    })
+    .await??;
+    assert_eq!(balance, U256::from(42));
+    assert_eq!(token.address(), address);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_airdrop_sends_once_per_recipient() -> Result<()> {
+    let sender = MockTxSender::new();
+    let token = Address::random();
+    let recipients = [
+        (Address::random(), U256::from(10)),
+        (Address::random(), U256::from(20)),
+        (Address::random(), U256::from(30)),
+    ];
+
+    let path = std::env::temp_dir().join(format!("airdrop-{:?}", Address::random()));
+    let mut log = AirdropLog::open(&path)?;
+    let summaries = airdrop(&sender, token, &recipients, &mut log, |_| {}).await?;
+    assert_eq!(summaries.len(), 3);
+    std::fs::remove_file(&path)?;
+
+    let sent = sender.sent();
+    assert_eq!(sent.len(), 3);
+    for (tx, &(to, amount)) in sent.iter().zip(&recipients) {
+        assert_eq!(tx.to_addr(), Some(&token));
+        let expected = TransferCall { to, amount }.encode();
+        assert_eq!(tx.data().map(|data| data.to_vec()), Some(expected));
+    }
+
+    // Each transfer was awaited before the next was sent
+    let hashes: Vec<_> = summaries.iter().map(|summary| summary.tx_hash).collect();
+    assert_eq!(sender.waited(), hashes);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rpc_errors_map_to_specific_variants() -> Result<()> {
+    let error = |code: i64, message: &str, data: Option<serde_json::Value>| {
+        MockResponse::Error(JsonRpcError {
+            code,
+            message: message.to_owned(),
+            data,
+        })
+    };
+
+    let cases = [
+        (
+            error(-32000, "insufficient funds for gas * price + value", None),
+            MyTokenError::InsufficientFunds,
+        ),
+        (
+            error(-32000, "nonce too low: next nonce 5, tx nonce 4", None),
+            MyTokenError::NonceTooLow,
+        ),
+        (
+            error(-32000, "replacement transaction underpriced", None),
+            MyTokenError::ReplacementUnderpriced,
+        ),
+        (
+            error(3, "execution reverted: MyToken: paused", None),
+            MyTokenError::ExecutionReverted(Some("MyToken: paused".into())),
+        ),
+    ];
+    for (response, expected) in cases {
+        let (provider, mock) = Provider::mocked();
+        let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+        mock.push_response(response);
+
+        let err = token.warm_up().await.unwrap_err();
+        assert_eq!(err.to_string(), expected.to_string());
+    }
+
+    // A reverted call with an `Error(string)` payload takes the reason from it
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+    let mut revert = ethers::utils::id("Error(string)").to_vec();
+    revert.extend(abi::encode(&[Token::String("account frozen".into())]));
+    let data = serde_json::Value::String(Bytes::from(revert).to_string());
+    mock.push_response(error(3, "execution reverted", Some(data)));
+    assert!(matches!(
+        token.balance_of(Address::random()).await,
+        Err(MyTokenError::ExecutionReverted(Some(reason))) if reason == "account frozen"
+    ));
+
+    // The token's custom errors keep their fields
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+    let mut revert = ethers::utils::id("InsufficientBalance(uint256,uint256)").to_vec();
+    revert.extend(abi::encode(&[
+        Token::Uint(U256::from(40)),
+        Token::Uint(U256::from(100)),
+    ]));
+    let data = serde_json::Value::String(Bytes::from(revert).to_string());
+    mock.push_response(error(3, "execution reverted", Some(data)));
+    assert!(matches!(
+        token.balance_of(Address::random()).await,
+        Err(MyTokenError::InsufficientBalance { available, needed })
+            if available == U256::from(40) && needed == U256::from(100)
+    ));
+
+    // Anything else keeps the node's own message
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+    mock.push_response(error(-32601, "method not found", None));
+    assert!(matches!(
+        token.warm_up().await,
+        Err(MyTokenError::Middleware(message)) if message.contains("method not found")
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_url_scheme_selects_transport() -> Result<()> {
+    for (url, kind) in [
+        ("http://localhost:8545", TransportKind::Http),
+        ("HTTPS://rpc.example.org", TransportKind::Http),
+        ("ws://localhost:8545", TransportKind::Ws),
+        ("wss://rpc.example.org/ws", TransportKind::Ws),
+        ("/tmp/anvil.ipc", TransportKind::Ipc),
+        ("ipc:///tmp/anvil.ipc", TransportKind::Ipc),
+    ] {
+        assert_eq!(TransportKind::from_url(url)?, kind, "{url}");
+    }
+    // Neither a scheme it knows nor a socket path, such as a URL missing its
+    // scheme
+    for url in ["ftp://localhost", "localhost:8545", "anvil.ipc"] {
+        assert!(
+            matches!(TransportKind::from_url(url), Err(MyTokenError::Config(_))),
+            "{url}"
+        );
+    }
+
+    // HTTP needs no connection up front
+    let provider = connect("http://localhost:8545").await?;
+    assert_eq!(provider.as_ref().kind(), TransportKind::Http);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cursor_ahead_of_chain_rescans() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("cursor-{:?}", Address::random()));
+    let mut cursor = ScanCursor::open(&path)?;
+    assert_eq!(cursor.last_block(), None);
+    cursor.advance(100)?;
+
+    // The node restarted and is now only at block 5, with a transfer in block 3
+    let (provider, mock) = Provider::mocked();
+    let address = Address::random();
+    let token = MyTokenClient::new(address, Arc::new(provider));
+    let log = transfer_log(
+        address,
+        Address::random(),
+        Address::random(),
+        U256::from(100),
+        3,
+    );
+    let block = Block::<TxHash> {
+        number: Some(U64::from(3)),
+        timestamp: U256::from(1_700_000_000),
+        ..Block::default()
+    };
+    mock.push::<Block<TxHash>, _>(block)?;
+    mock.push::<Vec<Log>, _>(vec![log])?;
+    mock.push(U64::from(5))?;
+
+    let mut cursor = ScanCursor::open(&path)?;
+    assert_eq!(cursor.last_block(), Some(100));
+    let transfers = token.enriched_transfers_since(&mut cursor).await?;
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].block_number, 3);
+    assert_eq!(ScanCursor::open(&path)?.last_block(), Some(5));
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+// Send a transaction through a client configured with `network`, answering
+// from `mock`, and return what reached `eth_sendTransaction`
+async fn sent_with(network: NetworkConfig, mock: MockProvider) -> Result<TypedTransaction> {
+    let counting = Arc::new(Provider::new(Counting::new(mock)));
+    let token = MyTokenClient::new(Address::random(), counting.clone()).with_network(network);
+    let tx = Eip1559TransactionRequest::new().to(Address::random());
+    TxSender::send(&token, tx.into()).await?;
+
+    let params = counting.as_ref().as_ref().params("eth_sendTransaction");
+    let sent = params.and_then(|params| params.get(0).cloned());
+    Ok(serde_json::from_value(sent.unwrap_or_default())?)
+}
+
+#[tokio::test]
+async fn test_fixed_gas_sends_configured_values() -> Result<()> {
+    let mock = MockProvider::new();
+    mock.push(TxHash::random())?;
+
+    let network = NetworkConfig {
+        tx_type: TxType::Eip1559,
+        gas: GasStrategy::Fixed {
+            limit: 90_000,
+            price: 7_000_000_000,
+        },
+        ..NetworkConfig::default()
+    };
+    let sent = sent_with(network, mock).await?;
+
+    assert!(matches!(sent, TypedTransaction::Legacy(_)));
+    assert_eq!(sent.gas(), Some(&U256::from(90_000)));
+    assert_eq!(sent.gas_price(), Some(U256::from(7_000_000_000u64)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimate_gas_scales_node_estimate() -> Result<()> {
+    // Served last-pushed first: gas price, estimate, then the broadcast
+    let mock = MockProvider::new();
+    mock.push(TxHash::random())?;
+    mock.push(U256::from(40_000))?;
+    mock.push(U256::from(3_000_000_000u64))?;
+
+    let network = NetworkConfig {
+        gas: GasStrategy::Estimate { multiplier: 1.5 },
+        ..NetworkConfig::default()
+    };
+    let sent = sent_with(network, mock).await?;
+
+    assert!(matches!(sent, TypedTransaction::Legacy(_)));
+    assert_eq!(sent.gas(), Some(&U256::from(60_000)));
+    assert_eq!(sent.gas_price(), Some(U256::from(3_000_000_000u64)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oracle_gas_uses_fee_history() -> Result<()> {
+    let gwei = |n: u64| U256::from(n * 1_000_000_000);
+    let history = FeeHistory {
+        base_fee_per_gas: vec![gwei(18), gwei(19), gwei(20)],
+        gas_used_ratio: vec![0.5, 0.6],
+        oldest_block: U256::from(100),
+        reward: vec![vec![gwei(1)], vec![gwei(3)]],
+    };
+    // Served last-pushed first: fee history, estimate, then the broadcast
+    let mock = MockProvider::new();
+    mock.push(TxHash::random())?;
+    mock.push(U256::from(40_000))?;
+    mock.push(history)?;
+
+    let network = NetworkConfig {
+        tx_type: TxType::Eip1559,
+        gas: GasStrategy::Oracle { percentile: 50.0 },
+        ..NetworkConfig::default()
+    };
+    let sent = sent_with(network, mock).await?;
+
+    let TypedTransaction::Eip1559(inner) = sent else {
+        panic!("expected an EIP-1559 transaction, got {sent:?}");
+    };
+    assert_eq!(inner.gas, Some(U256::from(40_000)));
+    assert_eq!(inner.max_priority_fee_per_gas, Some(gwei(2)));
+    assert_eq!(inner.max_fee_per_gas, Some(gwei(42)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_events_default_to_recent_blocks() -> Result<()> {
+    let mock = MockProvider::new();
+    let counting = Arc::new(Provider::new(Counting::new(mock.clone())));
+    let address = Address::random();
+    let network = NetworkConfig {
+        log_lookback_blocks: 1_000,
+        ..NetworkConfig::default()
+    };
+    let token = MyTokenClient::new(address, counting.clone()).with_network(network);
+
+    let recent = transfer_log(
+        address,
+        Address::random(),
+        Address::random(),
+        U256::from(100),
+        49_990,
+    );
+    let block = Block::<TxHash> {
+        number: Some(U64::from(49_990)),
+        timestamp: U256::from(1_700_000_000),
+        ..Block::default()
+    };
+
+    // Served last-pushed first: latest block, logs, then the timestamp
+    mock.push::<Block<TxHash>, _>(block)?;
+    mock.push::<Vec<Log>, _>(vec![recent])?;
+    mock.push(U64::from(50_000))?;
+
+    let transfers = token.transfer_events(None).await?;
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].block_number, 49_990);
+
+    // The last 1,000 blocks, from 49,001, not the whole chain
+    let params = counting.as_ref().as_ref().params("eth_getLogs");
+    let from_block = params.and_then(|params| params[0]["fromBlock"].as_str().map(str::to_owned));
+    assert_eq!(from_block.as_deref(), Some("0xbf69"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_history_fetches_pages_on_demand() -> Result<()> {
+    let mock = MockProvider::new();
+    let counting = Arc::new(Provider::new(Counting::new(mock.clone())));
+    let address = Address::random();
+    let token = MyTokenClient::new(address, counting.clone());
+
+    let transfer = |block: u64| {
+        let (from, to) = (Address::random(), Address::random());
+        transfer_log(address, from, to, U256::from(block), block)
+    };
+    let block = |number: u64| Block::<TxHash> {
+        number: Some(U64::from(number)),
+        timestamp: U256::from(1_700_000_000 + number),
+        ..Block::default()
+    };
+
+    // Blocks 10 to 15 in pages of two, so three pages in all. Served
+    // last-pushed first: the latest block, then logs and a timestamp per page
+    mock.push::<Block<TxHash>, _>(block(12))?;
+    mock.push::<Vec<Log>, _>(vec![transfer(12)])?;
+    mock.push::<Block<TxHash>, _>(block(10))?;
+    mock.push::<Vec<Log>, _>(vec![transfer(10)])?;
+    mock.push(U64::from(15))?;
+
+    let history = token.transfer_history(10, 2);
+    futures::pin_mut!(history);
+    let transport = counting.as_ref().as_ref();
+    assert_eq!(transport.calls("eth_getLogs"), 0);
+
+    let first = history.next().await.transpose()?;
+    assert_eq!(transport.calls("eth_getLogs"), 1);
+    let second = history.next().await.transpose()?;
+    assert_eq!(transport.calls("eth_getLogs"), 2);
+
+    let blocks = |page: Option<Vec<EnrichedTransfer>>| {
+        page.unwrap_or_default()
+            .iter()
+            .map(|transfer| transfer.block_number)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(blocks(first), [10]);
+    assert_eq!(blocks(second), [12]);
+
+    // The second page covered blocks 12 and 13 only
+    let params = transport.params("eth_getLogs");
+    let window =
+        params.map(|params| (params[0]["fromBlock"].clone(), params[0]["toBlock"].clone()));
+    assert_eq!(
+        window,
+        Some((serde_json::json!("0xc"), serde_json::json!("0xd")))
+    );
+    assert_eq!(transport.calls("eth_blockNumber"), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cached_balance_reads_within_ttl() -> Result<()> {
+    let config = Config::from_toml("read_cache_ttl_ms = 60000")?;
+    let mock = MockProvider::new();
+    let counting = Arc::new(Provider::new(Counting::new(mock.clone())));
+    mock.push(U256::from(31337))?;
+    let token = MyTokenClient::new(Address::random(), counting.clone())
+        .with_config(&config)
+        .await?;
+    let account = Address::random();
+
+    let balance = |amount: u64| Bytes::from(abi::encode(&[Token::Uint(U256::from(amount))]));
+    mock.push::<Bytes, _>(balance(7))?;
+    mock.push::<Bytes, _>(balance(5))?;
+
+    assert_eq!(
+        token.balance_of_cached(account, false).await?,
+        U256::from(5)
+    );
+    assert_eq!(
+        token.balance_of_cached(account, false).await?,
+        U256::from(5)
+    );
+    assert_eq!(counting.as_ref().as_ref().calls("eth_call"), 1);
+
+    // A fresh read goes to the node, and refreshes the cached value
+    assert_eq!(token.balance_of_cached(account, true).await?, U256::from(7));
+    assert_eq!(
+        token.balance_of_cached(account, false).await?,
+        U256::from(7)
+    );
+    assert_eq!(counting.as_ref().as_ref().calls("eth_call"), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_airdrop_resumes_after_failure() -> Result<()> {
+    let token = Address::random();
+    let recipients = [
+        (Address::random(), U256::from(10)),
+        (Address::random(), U256::from(20)),
+        (Address::random(), U256::from(30)),
+    ];
+    let path = std::env::temp_dir().join(format!("airdrop-{:?}", Address::random()));
+
+    // The second transfer reverts, stopping the run after the first
+    let sender = MockTxSender::new();
+    sender.push_summary(TxSummary {
+        tx_hash: TxHash::random(),
+        status: TxStatus::Success,
+        ..TxSummary::skipped()
+    });
+    sender.push_summary(TxSummary {
+        status: TxStatus::Reverted,
+        ..TxSummary::skipped()
+    });
+    let mut log = AirdropLog::open(&path)?;
+    let mut reported = Vec::new();
+    let result = airdrop(&sender, token, &recipients, &mut log, |progress| {
+        reported.push((progress.done, progress.recipient));
+    })
+    .await;
+    assert!(matches!(result, Err(MyTokenError::Reverted(_))));
+    assert_eq!(reported, [(1, recipients[0].0)]);
+    // Logged as reverted, not left as sent for the next run to wait on
+    let (to, amount) = recipients[1];
+    assert_eq!(log.pending(1, to, amount), None);
+    assert_eq!(log.credited(1, to, amount), None);
+
+    // A new run, reading the log back from disk
+    let sender = MockTxSender::new();
+    let mut log = AirdropLog::open(&path)?;
+    let summaries = airdrop(&sender, token, &recipients, &mut log, |_| {}).await?;
+
+    let sent = sender.sent();
+    assert_eq!(sent.len(), 2);
+    for (tx, &(to, amount)) in sent.iter().zip(&recipients[1..]) {
+        let expected = TransferCall { to, amount }.encode();
+        assert_eq!(tx.data().map(|data| data.to_vec()), Some(expected));
+    }
+    assert_eq!(summaries[0].status, TxStatus::Skipped);
+    assert!(
+        recipients
+            .iter()
+            .enumerate()
+            .all(|(index, &(to, amount))| log.credited(index, to, amount).is_some())
+    );
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_airdrop_waits_for_transfer_sent_before_crash() -> Result<()> {
+    let token = Address::random();
+    let recipients = [
+        (Address::random(), U256::from(10)),
+        (Address::random(), U256::from(20)),
+    ];
+    let path = std::env::temp_dir().join(format!("airdrop-{:?}", Address::random()));
+
+    // A run that broadcast the first transfer and stopped before its receipt
+    let in_flight = TxHash::random();
+    AirdropLog::open(&path)?.record_sent(0, recipients[0].0, recipients[0].1, in_flight)?;
+
+    let sender = MockTxSender::new();
+    let mut log = AirdropLog::open(&path)?;
+    assert_eq!(
+        log.pending(0, recipients[0].0, recipients[0].1),
+        Some(in_flight)
+    );
+    airdrop(&sender, token, &recipients, &mut log, |_| {}).await?;
+
+    // Its receipt was looked up instead of paying the recipient again
+    let sent = sender.sent();
+    assert_eq!(sent.len(), 1);
+    let expected = TransferCall {
+        to: recipients[1].0,
+        amount: recipients[1].1,
+    }
+    .encode();
+    assert_eq!(sent[0].data().map(|data| data.to_vec()), Some(expected));
+    assert_eq!(sender.waited()[0], in_flight);
+    assert_eq!(
+        AirdropLog::open(&path)?.credited(0, recipients[0].0, recipients[0].1),
+        Some(in_flight)
+    );
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_airdrop_pays_recipient_listed_twice() -> Result<()> {
+    let token = Address::random();
+    let to = Address::random();
+    let recipients = [(to, U256::from(10)), (to, U256::from(25))];
+    let path = std::env::temp_dir().join(format!("airdrop-{:?}", Address::random()));
+
+    let sender = MockTxSender::new();
+    let mut log = AirdropLog::open(&path)?;
+    airdrop(&sender, token, &recipients, &mut log, |_| {}).await?;
+    assert_eq!(sender.sent().len(), 2);
+
+    // Both entries are credited, so a re-run sends nothing
+    let sender = MockTxSender::new();
+    let mut log = AirdropLog::open(&path)?;
+    let summaries = airdrop(&sender, token, &recipients, &mut log, |_| {}).await?;
+    assert!(sender.sent().is_empty());
+    assert!(
+        summaries
+            .iter()
+            .all(|summary| summary.status == TxStatus::Skipped)
+    );
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_config_parsing_is_deterministic() -> Result<()> {
+    let source = r#"
+        timestamp_cache_size = 64
+        read_cache_ttl_ms = 2000
+
+        [networks.1]
+        tx_type = "eip1559"
+        gas = { strategy = "estimate", multiplier = 1.5 }
+        confirmations = 3
+
+        [networks.31337]
+        gas = { strategy = "fixed", limit = 100000, price = 1000000000 }
+        poll_interval_ms = 100
+    "#;
+
+    let first = Config::from_toml(source)?;
+    assert_eq!(first, Config::from_toml(source)?);
+    assert_eq!(first.clone(), first);
+    assert_ne!(first, Config::default());
+    Ok(())
+}
+
+#[test]
+fn test_gas_multiplier_must_be_finite_and_at_least_one() -> Result<()> {
+    for multiplier in ["0.5", "nan", "inf", "-1.0"] {
+        let source =
+            format!("[networks.1]\ngas = {{ strategy = \"estimate\", multiplier = {multiplier} }}");
+        assert!(
+            matches!(Config::from_toml(&source), Err(MyTokenError::Config(_))),
+            "multiplier {multiplier} accepted"
+        );
+    }
+    for multiplier in [0.99, f64::NAN, f64::INFINITY] {
+        assert!(matches!(
+            GasStrategy::estimate(multiplier),
+            Err(MyTokenError::Config(_))
+        ));
+    }
+    assert_eq!(
+        GasStrategy::estimate(1.0)?,
+        GasStrategy::Estimate { multiplier: 1.0 }
+    );
+    Ok(())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_receipt_polling_backs_off() -> Result<()> {
+    let chain = Arc::new(Provider::new(SlowlyMined::new(Duration::from_secs(30))));
+    let network = NetworkConfig {
+        poll_interval_ms: Some(100),
+        max_poll_interval_ms: 10_000,
+        ..NetworkConfig::default()
+    };
+    let token = MyTokenClient::new(Address::random(), chain.clone()).with_network(network);
+
+    let summary = TxSender::wait(&token, TxHash::random()).await?;
+    assert_eq!(summary.status, TxStatus::Success);
+
+    // Polling every 100 ms would have taken 300 polls
+    let polls = chain.as_ref().as_ref().polls.load(Ordering::SeqCst);
+    assert!(polls <= 12, "polled {polls} times in 30s");
+    Ok(())
+}
+
+// Answers a mock JSON-RPC server gives, from the method and params
+type Respond = fn(&str, &serde_json::Value) -> serde_json::Value;
+
+// Serve JSON-RPC over HTTP on a local port, answering each request with
+// `respond` and keeping every request it was sent
+async fn mock_rpc(respond: Respond) -> Result<(String, Arc<Mutex<Vec<serde_json::Value>>>)> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new().route(
+        "/",
+        post({
+            let received = received.clone();
+            move |Json(request): Json<serde_json::Value>| async move {
+                let method = request["method"].as_str().unwrap_or_default();
+                let result = respond(method, &request["params"]);
+                let response = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "result": result,
+                });
+                received.lock().unwrap().push(request);
+                Json(response)
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    Ok((url, received))
+}
+
+// A private relay, taking raw transactions and answering with their hashes,
+// and reporting every transaction asked about as pending with it
+async fn mock_relay() -> Result<(String, Arc<Mutex<Vec<serde_json::Value>>>)> {
+    mock_rpc(|method, params| match method {
+        "eth_getTransactionByHash" => {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap_or_default();
+            serde_json::json!(Transaction {
+                hash,
+                ..Transaction::default()
+            })
+        }
+        _ => {
+            let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap_or_default();
+            serde_json::json!(H256(ethers::utils::keccak256(&raw)))
+        }
+    })
+    .await
+}
+
+// The raw transactions sent to a mock relay
+fn relayed(received: &Mutex<Vec<serde_json::Value>>) -> Vec<Bytes> {
+    let received = received.lock().unwrap();
+    received
+        .iter()
+        .filter(|request| request["method"] == "eth_sendRawTransaction")
+        .filter_map(|request| serde_json::from_value(request["params"][0].clone()).ok())
+        .collect()
+}
+
+// Gas fixed up front, so a mocked write asks the node for no estimates
+fn fixed_gas() -> NetworkConfig {
+    NetworkConfig {
+        gas: GasStrategy::Fixed {
+            limit: 90_000,
+            price: 1_000_000_000,
+        },
+        ..NetworkConfig::default()
+    }
+}
+
+// Queue what a write with `fixed_gas` asks a mocked node for, served
+// last-pushed first: the broadcast's hash, then `receipt` and the block
+// number it's polled with. The chain id the summary needs is asked for once
+// per client, so is pushed before the first write's.
+fn push_mined(mock: &MockProvider, receipt: TransactionReceipt) -> Result<()> {
+    let tx_hash = receipt.transaction_hash;
+    mock.push(receipt.block_number.unwrap_or_default())?;
+    mock.push(receipt)?;
+    mock.push(tx_hash)?;
+    Ok(())
+}
+
+// A receipt for `tx_hash`, mined successfully in block 5
+fn mined(tx_hash: TxHash) -> TransactionReceipt {
+    TransactionReceipt {
+        transaction_hash: tx_hash,
+        block_number: Some(U64::from(5)),
+        status: Some(U64::one()),
+        ..TransactionReceipt::default()
+    }
+}
+
+#[tokio::test]
+async fn test_writes_go_through_configured_relay() -> Result<()> {
+    let (relay_url, received) = mock_relay().await?;
+    let wallet = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(31337u64);
+    let mock = MockProvider::new();
+    let node = Provider::new(Counting::new(mock.clone()));
+    let client = Arc::new(SignerMiddleware::new(node, wallet.clone()));
+
+    let network = NetworkConfig {
+        relay_url: Some(relay_url),
+        ..fixed_gas()
+    };
+    let token = MyTokenClient::new(Address::random(), client.clone()).with_network(network);
+
+    // Served last-pushed first: the nonce, then the receipt and block number
+    // polled once the relay has it, then the chain id for the summary
+    mock.push(U256::from(31337))?;
+    mock.push(U64::from(5))?;
+    mock.push(mined(TxHash::zero()))?;
+    mock.push(U256::zero())?;
+
+    let to = Address::random();
+    let summary = token.transfer(to, U256::from(100)).await?;
+    assert_eq!(summary.status, TxStatus::Success);
+
+    let received = relayed(&received);
+    assert_eq!(received.len(), 1);
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&received[0]))?;
+    assert_eq!(signature.recover(tx.sighash())?, wallet.address());
+    assert_eq!(tx.to_addr(), Some(&token.address()));
+
+    // The node was only asked for the receipt of what the relay took
+    let node = client.inner().as_ref();
+    assert_eq!(node.calls("eth_sendTransaction"), 0);
+    assert_eq!(node.calls("eth_sendRawTransaction"), 0);
+    let relayed = H256(ethers::utils::keccak256(&received[0]));
+    assert_eq!(
+        node.params("eth_getTransactionReceipt"),
+        Some(serde_json::json!([relayed]))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_slow_relayed_write_is_not_resent() -> Result<()> {
+    let (relay_url, received) = mock_relay().await?;
+    let wallet = LocalWallet::new(&mut rand::thread_rng()).with_chain_id(31337u64);
+    // The node never sees the transaction pending, only its receipt once mined
+    let node = Provider::new(SlowlyMined::new(Duration::from_millis(300)));
+    let client = Arc::new(SignerMiddleware::new(node, wallet));
+
+    let network = NetworkConfig {
+        poll_interval_ms: Some(10),
+        max_poll_interval_ms: 10,
+        receipt_timeout_ms: 50,
+        relay_url: Some(relay_url),
+        ..fixed_gas()
+    };
+    let token = MyTokenClient::new(Address::random(), client).with_network(network);
+    let summary = token.transfer(Address::random(), U256::from(100)).await?;
+    assert_eq!(summary.status, TxStatus::Success);
+
+    // The relay was asked about it instead, and said it still had it
+    assert_eq!(relayed(&received).len(), 1);
+    let asked = received
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|request| request["method"] == "eth_getTransactionByHash")
+        .count();
+    assert!(asked > 0, "relay never asked about the slow transaction");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fallback_keeps_writes_on_current_url() -> Result<()> {
+    let (relay_url, received) = mock_relay().await?;
+    // Nothing listens on port 1, which is where a write goes before any read
+    // has moved on from it
+    let provider = connect_fallback(&["http://127.0.0.1:1", &relay_url]).await?;
+
+    let result = provider.send_raw_transaction(Bytes::from(vec![0xc0])).await;
+    assert!(result.is_err());
+    assert!(relayed(&received).is_empty());
+    assert_eq!(provider.as_ref().current(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fallback_needs_a_url() {
+    assert!(matches!(
+        connect_fallback(&[]).await,
+        Err(MyTokenError::Config(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_unwritable_receipt_store_keeps_mined_write() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    // A directory can't be appended to as a file
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider))
+        .with_network(fixed_gas())
+        .with_receipt_store(Arc::new(JsonLinesStore::new(std::env::temp_dir())));
+
+    let tx_hash = TxHash::random();
+    mock.push(U256::from(31337))?;
+    push_mined(&mock, mined(tx_hash))?;
+
+    // The transfer was mined, so it isn't reported as failed for the record
+    let summary = token.transfer(Address::random(), U256::from(100)).await?;
+    assert_eq!(summary.tx_hash, tx_hash);
+    assert_eq!(summary.status, TxStatus::Success);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keyed_transfer_broadcast_before_failure_is_not_resent() -> Result<()> {
+    let mock = MockProvider::new();
+    let counting = Arc::new(Provider::new(Counting::new(mock.clone())));
+    let store = Arc::new(MemoryStore::default());
+    let token = MyTokenClient::new(Address::random(), counting.clone())
+        .with_network(fixed_gas())
+        .with_idempotency_store(store.clone());
+    let to = Address::random();
+
+    // Broadcast, then the node fails before the receipt is seen
+    let tx_hash = TxHash::random();
+    mock.push(tx_hash)?;
+    assert!(
+        token
+            .transfer_with_key("order-7", to, U256::from(100))
+            .await
+            .is_err()
+    );
+    assert_eq!(store.get("order-7"), Some(KeyedTransfer::Sent(tx_hash)));
+
+    // The retry waits for that transaction instead of sending another
+    mock.push(U256::from(31337))?;
+    mock.push(U64::from(5))?;
+    mock.push(mined(tx_hash))?;
+    let summary = token
+        .transfer_with_key("order-7", to, U256::from(100))
+        .await?;
+    assert_eq!(summary.tx_hash, tx_hash);
+    assert_eq!(counting.as_ref().as_ref().calls("eth_sendTransaction"), 1);
+    assert_eq!(store.get("order-7"), Some(KeyedTransfer::Mined(summary)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_percent_of_huge_balance_does_not_overflow() -> Result<()> {
+    let mock = MockProvider::new();
+    let provider = Provider::new(Counting::new(mock.clone())).with_sender(Address::random());
+    let counting = Arc::new(provider);
+    let token = MyTokenClient::new(Address::random(), counting.clone()).with_network(fixed_gas());
+
+    // A balance this large overflows if multiplied by the basis points first
+    mock.push(U256::from(31337))?;
+    push_mined(&mock, mined(TxHash::random()))?;
+    mock.push::<Bytes, _>(Bytes::from(abi::encode(&[Token::Uint(U256::MAX)])))?;
+
+    let to = Address::random();
+    token.transfer_percent(to, 5000).await?;
+    let sent = counting.as_ref().as_ref().params("eth_sendTransaction");
+    let data: Bytes = serde_json::from_value(sent.unwrap()[0]["data"].clone())?;
+    let amount = U256::MAX / 2;
+    assert_eq!(data.to_vec(), TransferCall { to, amount }.encode());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mined_write_without_chain_id_succeeds() -> Result<()> {
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider)).with_network(fixed_gas());
+
+    // Nothing queued for the chain id, so the node fails when asked for it
+    let tx_hash = TxHash::random();
+    push_mined(&mock, mined(tx_hash))?;
+
+    let summary = token.transfer(Address::random(), U256::from(100)).await?;
+    assert_eq!(summary.tx_hash, tx_hash);
+    assert_eq!(summary.chain, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_receipt_store_records_each_write() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("receipts-{:?}.jsonl", Address::random()));
+    let (provider, mock) = Provider::mocked();
+    let address = Address::random();
+    let token = MyTokenClient::new(address, Arc::new(provider))
+        .with_network(fixed_gas())
+        .with_receipt_store(Arc::new(JsonLinesStore::new(&path)));
+
+    let receipt = |tx_hash: TxHash| TransactionReceipt {
+        to: Some(address),
+        ..mined(tx_hash)
+    };
+    let hashes = [TxHash::random(), TxHash::random()];
+    push_mined(&mock, receipt(hashes[1]))?;
+    mock.push(U256::from(31337))?;
+    push_mined(&mock, receipt(hashes[0]))?;
+
+    let recipients = [Address::random(), Address::random()];
+    token.transfer(recipients[0], U256::from(100)).await?;
+    token.transfer(recipients[1], U256::from(250)).await?;
+
+    let records = JsonLinesStore::read(&path)?;
+    assert_eq!(records.len(), 2);
+    for ((record, recipient), (tx_hash, amount)) in records
+        .iter()
+        .zip(recipients)
+        .zip(hashes.into_iter().zip(["100", "250"]))
+    {
+        assert_eq!(record.operation, "transfer");
+        assert_eq!(record.args["to"], checksum(recipient));
+        assert_eq!(record.args["amount"], amount);
+        assert_eq!(record.to, Some(address));
+        assert_eq!(record.tx_hash, tx_hash);
+        assert_eq!(record.status, TxStatus::Success);
+        assert_eq!(record.chain_id, Some(31337));
+    }
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn test_transfer_records_metrics() -> Result<()> {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    // Local to this thread, which the test's runtime runs everything on, so
+    // tests running alongside don't add to the counts
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let _recording = metrics::set_default_local_recorder(&recorder);
+
+    let (provider, mock) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider)).with_network(fixed_gas());
+    mock.push(U256::from(31337))?;
+    push_mined(&mock, mined(TxHash::random()))?;
+
+    token.transfer(Address::random(), U256::from(100)).await?;
+    // A read reverting with a string, counted by the error it became rather
+    // than the string
+    mock.push_response(MockResponse::Error(JsonRpcError {
+        code: 3,
+        message: "execution reverted: MyToken: paused".to_owned(),
+        data: None,
+    }));
+    assert!(token.balance_of(Address::random()).await.is_err());
+
+    let snapshot = snapshotter.snapshot().into_vec();
+    let metric = |name: &str, operation: &str| {
+        snapshot
+            .iter()
+            .find(|(key, ..)| {
+                key.key().name() == name
+                    && key
+                        .key()
+                        .labels()
+                        .any(|label| label.key() == "operation" && label.value() == operation)
+            })
+            .map(|(key, .., value)| (key, value))
+    };
+    assert!(matches!(
+        metric(my_token::OPERATIONS_METRIC, "transfer"),
+        Some((_, DebugValue::Counter(1)))
+    ));
+    assert!(matches!(
+        metric(my_token::LATENCY_METRIC, "transfer"),
+        Some((_, DebugValue::Histogram(samples))) if samples.len() == 1
+    ));
+    assert!(metric(my_token::REVERTS_METRIC, "transfer").is_none());
+
+    let (key, value) = metric(my_token::REVERTS_METRIC, "balanceOf").expect("revert counted");
+    assert!(matches!(value, DebugValue::Counter(1)));
+    assert!(
+        key.key()
+            .labels()
+            .any(|label| label.key() == "reason" && label.value() == "ExecutionReverted")
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dry_run_broadcasts_nothing() -> Result<()> {
+    let mock = MockProvider::new();
+    let counting = Arc::new(Provider::new(Counting::new(mock.clone())));
+    let token = MyTokenClient::new(Address::random(), counting.clone()).with_dry_run(true);
+
+    mock.push::<Bytes, Bytes>(abi::encode(&[Token::Bool(true)]).into())?;
+    let summary = token.transfer(Address::random(), U256::from(100)).await?;
+
+    // Identified by the hash of the transaction it didn't send
+    assert_eq!(summary.status, TxStatus::Simulated);
+    let simulated = token.simulated_txs();
+    assert_eq!(simulated.len(), 1);
+    assert_eq!(summary.tx_hash, simulated[0].tx_hash);
+    assert!(!summary.tx_hash.is_zero());
+    assert!(simulated[0].description.starts_with("call transfer("));
+    let transport = counting.as_ref().as_ref();
+    assert_eq!(transport.calls("eth_call"), 1);
+    assert_eq!(transport.calls("eth_sendTransaction"), 0);
+    assert_eq!(transport.calls("eth_getTransactionReceipt"), 0);
+    Ok(())
+}
+
+// A node for the CLI to talk to, with an 18-decimal token whose calls succeed
+async fn mock_token_node() -> Result<(String, Arc<Mutex<Vec<serde_json::Value>>>)> {
+    mock_rpc(|method, params| {
+        let call = &params[0];
+        let data = call.get("input").or_else(|| call.get("data"));
+        let decimals = Bytes::from(DecimalsCall.encode()).to_string();
+        match method {
+            "eth_chainId" => serde_json::json!(U64::from(31337)),
+            "eth_call" if data.and_then(|data| data.as_str()) == Some(&decimals) => {
+                serde_json::json!(Bytes::from(abi::encode(&[Token::Uint(U256::from(18))])))
+            }
+            "eth_call" => serde_json::json!(Bytes::from(abi::encode(&[Token::Bool(true)]))),
+            _ => serde_json::Value::Null,
+        }
+    })
+    .await
+}
+
+// Run the CLI in dry-run mode against the node at `rpc_url`
+async fn run_cli(rpc_url: &str, args: &[&str]) -> Result<std::process::Output> {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    let key = ethers::utils::hex::encode(wallet.signer().to_bytes());
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_my_token"))
+        .args(["--rpc-url", rpc_url, "--private-key", &key, "--dry-run"])
+        .args(args)
+        .output()
+        .await?;
+    Ok(output)
+}
+
+// The amounts of the transfers the CLI simulated
+fn simulated_transfers(received: &Mutex<Vec<serde_json::Value>>) -> Vec<U256> {
+    let received = received.lock().unwrap();
+    received
+        .iter()
+        .filter(|request| request["method"] == "eth_call")
+        .filter_map(|request| {
+            let call = &request["params"][0];
+            let data = call.get("input").or_else(|| call.get("data"))?;
+            let data: Bytes = serde_json::from_value(data.clone()).ok()?;
+            TransferCall::decode(&data).ok()
+        })
+        .map(|transfer| transfer.amount)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_cli_parses_human_amounts() -> Result<()> {
+    let to = format!("{:?}", Address::random());
+
+    let (rpc_url, received) = mock_token_node().await?;
+    let output = run_cli(&rpc_url, &["transfer", &to, "1.5"]).await?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        simulated_transfers(&received),
+        [U256::from(1_500_000_000_000_000_000u64)]
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("dry run: would call transfer("), "{stderr}");
+
+    // Malformed and too precise amounts are refused before anything is sent
+    for amount in ["1.5.3", "1,5", "0.0000000000000000001"] {
+        let (rpc_url, received) = mock_token_node().await?;
+        let output = run_cli(&rpc_url, &["transfer", &to, amount]).await?;
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("isn't an amount"), "{stderr}");
+        assert!(simulated_transfers(&received).is_empty());
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verification_posts_source_and_polls() -> Result<()> {
+    // Explorer API that hasn't indexed the contract on the first submission,
+    // takes the second, then reports it pending once before it passes
+    let submitted: Arc<Mutex<Option<HashMap<String, String>>>> = Arc::default();
+    let submissions = Arc::new(AtomicUsize::new(0));
+    let polls = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route(
+        "/api",
+        post({
+            let submitted = submitted.clone();
+            let submissions = submissions.clone();
+            move |Form(form): Form<HashMap<String, String>>| async move {
+                if submissions.fetch_add(1, Ordering::SeqCst) == 0 {
+                    let result = format!(
+                        "Unable to locate ContractCode at {}",
+                        form["contractaddress"]
+                    );
+                    return Json(
+                        serde_json::json!({ "status": "0", "message": "NOTOK", "result": result }),
+                    );
+                }
+                *submitted.lock().unwrap() = Some(form);
+                Json(serde_json::json!({ "status": "1", "message": "OK", "result": "guid-1" }))
+            }
+        })
+        .get({
+            let polls = polls.clone();
+            move |Query(query): Query<HashMap<String, String>>| async move {
+                assert_eq!(query["guid"], "guid-1");
+                let result = match polls.fetch_add(1, Ordering::SeqCst) {
+                    0 => "Pending in queue",
+                    _ => "Pass - Verified",
+                };
+                Json(serde_json::json!({ "status": "1", "message": "OK", "result": result }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/api", listener.local_addr()?);
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let address = Address::random();
+    let constructor_args = abi::encode(&[Token::Address(Address::random()), Token::Uint(6.into())]);
+    let source = r#"{"language":"Solidity","sources":{}}"#;
+    Verification::new("key", source, "v0.8.24+commit.e11b9ed9")
+        .with_api_url(&url)
+        .with_poll_interval(Duration::from_millis(10))
+        .verify(11155111, address, &constructor_args)
+        .await?;
+
+    let form = submitted.lock().unwrap().take().expect("source submitted");
+    assert_eq!(form["action"], "verifysourcecode");
+    assert_eq!(form["contractaddress"], format!("{address:?}"));
+    assert_eq!(form["sourceCode"], source);
+    assert_eq!(form["codeformat"], "solidity-standard-json-input");
+    assert_eq!(form["contractname"], "src/MyToken.sol:MyToken");
+    assert_eq!(form["compilerversion"], "v0.8.24+commit.e11b9ed9");
+    // Hex without the 0x prefix, as Etherscan wants it
+    assert_eq!(
+        form["constructorArguements"],
+        hex::encode(&constructor_args)
+    );
+    assert_eq!(submissions.load(Ordering::SeqCst), 2);
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+
+    server.abort();
+    Ok(())
+}
+
+#[test]
+fn test_network_config_opts_into_verification() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("standard-input-{:?}.json", Address::random()));
+    let source = r#"{"language":"Solidity","sources":{}}"#;
+    std::fs::write(&path, source)?;
+    let config = Config::from_toml(&format!(
+        r#"
+        [networks.11155111.verify]
+        etherscan_api_key = "key"
+        standard_json = {path:?}
+        compiler_version = "v0.8.24+commit.e11b9ed9"
+        "#
+    ))?;
+
+    let verification = config
+        .network(11155111)
+        .verify
+        .expect("verification configured")
+        .verification()?;
+    assert_eq!(verification.api_key, "key");
+    assert_eq!(verification.standard_json, source);
+    assert_eq!(verification.api_url, ETHERSCAN_API_URL);
+    // Other networks aren't verified
+    assert_eq!(config.network(1).verify, None);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_indexer_refuses_database_without_block_hashes() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("indexer-{:?}.sqlite", Address::random()));
+    // As the indexer wrote it before it kept block hashes
+    rusqlite::Connection::open(&path)?.execute_batch(
+        "CREATE TABLE transfers (block_number INTEGER, tx_hash TEXT, log_index INTEGER);
+         INSERT INTO transfers VALUES (3, '0x01', 0);",
+    )?;
+    let (provider, _) = Provider::mocked();
+    let token = MyTokenClient::new(Address::random(), Arc::new(provider));
+
+    let result = Indexer::open(token.at(token.address()), &path);
+    assert!(matches!(result, Err(MyTokenError::Storage(_))));
+    // Nothing was dropped by opening it
+    let rows: u64 = rusqlite::Connection::open(&path)?.query_row(
+        "SELECT COUNT(*) FROM transfers",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(rows, 1);
+
+    let indexer = Indexer::reindex(token.at(token.address()), &path)?;
+    assert_eq!(indexer.last_block()?, None);
+    drop(indexer);
+    assert!(Indexer::open(token, &path).is_ok());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "server")]
+
+mod common;
+
+use common::TestEnv;
+use ethers::{signers::Signer, types::Address};
+use eyre::Result;
+use my_token::{MyTokenClient, TokenApi, TokenInfo, TokenServer, checksum};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_server_serves_token_info() -> Result<()> {
+    let env = TestEnv::new().await?;
+    let address = env.deploy_once(env.owner.address()).await?;
+    let token = MyTokenClient::new(address, env.client.clone());
+    let api: Arc<dyn TokenApi> = Arc::new(MyTokenClient::new(address, env.client.clone()));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let server = tokio::spawn(
+        TokenServer::new(api)
+            .with_write_token("secret")
+            .serve(listener),
+    );
+    let http = reqwest::Client::new();
+
+    let info: TokenInfo = http
+        .get(format!("{url}/info"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    assert_eq!(
+        info,
+        TokenInfo {
+            address: checksum(address),
+            name: token.name().await?,
+            symbol: token.symbol().await?,
+            decimals: token.decimals().await?,
+            total_supply: token.total_supply().await?.to_string(),
+        }
+    );
+
+    // Writes are refused without the bearer token
+    let transfer = serde_json::json!({ "to": Address::random(), "amount": "1" });
+    let unauthorized = http
+        .post(format!("{url}/transfer"))
+        .json(&transfer)
+        .send()
+        .await?;
+    assert_eq!(unauthorized.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let authorized = http
+        .post(format!("{url}/transfer"))
+        .bearer_auth("secret")
+        .json(&transfer)
+        .send()
+        .await?;
+    assert!(authorized.status().is_success());
+
+    server.abort();
+    Ok(())
+}
@@ -0,0 +1,122 @@
+// Property test: random sequences of mints and burns keep the supply accounting
+// consistent. Runs against Anvil, reverting to a snapshot after every case.
+
+mod common;
+
+use common::{TestClient, TestEnv, test_wallets};
+use ethers::{
+    signers::Signer,
+    types::{Address, U256},
+    utils::parse_ether,
+};
+use eyre::Result;
+use my_token::MyTokenClient;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+use std::collections::BTreeSet;
+
+const ACTORS: usize = 3;
+
+#[derive(Debug, Clone)]
+enum Op {
+    /// Mint up to `amount` to an actor, clamped to what the cap still allows
+    Mint { actor: usize, amount: u128 },
+    /// Burn `percent` of an actor's balance
+    Burn { actor: usize, percent: u8 },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (0..ACTORS, 0..=10u128.pow(24)).prop_map(|(actor, amount)| Op::Mint { actor, amount }),
+        (0..ACTORS, 0..=100u8).prop_map(|(actor, percent)| Op::Burn { actor, percent }),
+    ]
+}
+
+#[test]
+fn test_mint_burn_conserve_supply() -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let env = runtime.block_on(TestEnv::new())?;
+    let address = runtime.block_on(env.deploy_once(env.owner.address()))?;
+
+    // The owner mints; each actor, the owner included, burns its own tokens
+    let owner = MyTokenClient::new(address, env.client.clone());
+    let mut actors = vec![MyTokenClient::new(address, env.client.clone())];
+    for wallet in test_wallets(ACTORS - 1) {
+        runtime.block_on(env.fund(wallet.address(), parse_ether(1)?))?;
+        actors.push(MyTokenClient::new(address, env.signer_client(wallet)));
+    }
+
+    let mut runner = TestRunner::new(Config {
+        cases: 16,
+        ..Config::default()
+    });
+    let result = runner.run(&prop::collection::vec(op(), 1..8), |ops| {
+        runtime.block_on(async {
+            let snapshot = env.snapshot().await.map_err(fail)?;
+            let outcome = run_ops(&owner, &actors, &ops).await;
+            env.revert(snapshot).await.map_err(fail)?;
+            outcome
+        })
+    });
+    result.map_err(|err| eyre::eyre!("{err}"))
+}
+
+async fn run_ops(
+    owner: &MyTokenClient<TestClient>,
+    actors: &[MyTokenClient<TestClient>],
+    ops: &[Op],
+) -> Result<(), TestCaseError> {
+    for op in ops {
+        match *op {
+            Op::Mint { actor, amount } => {
+                let to = actors[actor].sender().map_err(fail)?;
+                let amount =
+                    U256::from(amount).min(owner.remaining_mintable().await.map_err(fail)?);
+                owner.mint(to, amount).await.map_err(fail)?;
+            }
+            Op::Burn { actor, percent } => {
+                let token = &actors[actor];
+                let balance = token
+                    .balance_of(token.sender().map_err(fail)?)
+                    .await
+                    .map_err(fail)?;
+                token.burn(balance * percent / 100).await.map_err(fail)?;
+            }
+        }
+        check_invariants(owner).await?;
+    }
+    Ok(())
+}
+
+async fn check_invariants(token: &MyTokenClient<TestClient>) -> Result<(), TestCaseError> {
+    // Everyone who ever received tokens, going by the Transfer log
+    let transfers = token
+        .contract()
+        .transfer_filter()
+        .from_block(0u64)
+        .query()
+        .await
+        .map_err(fail)?;
+    let holders: Vec<Address> = transfers
+        .iter()
+        .map(|event| event.to)
+        .filter(|to| !to.is_zero())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let balances = token.balances_unbatched(&holders).await.map_err(fail)?;
+    let sum = balances
+        .into_iter()
+        .fold(U256::zero(), |sum, balance| sum + balance);
+    let total_supply = token.total_supply().await.map_err(fail)?;
+    let cap = token.cap().await.map_err(fail)?;
+
+    prop_assert_eq!(total_supply, sum);
+    prop_assert!(total_supply <= cap);
+    Ok(())
+}
+
+fn fail(err: impl std::fmt::Display) -> TestCaseError {
+    TestCaseError::fail(err.to_string())
+}